@@ -0,0 +1,2 @@
+-- Drop rule_cache table
+DROP TABLE IF EXISTS rule_cache;
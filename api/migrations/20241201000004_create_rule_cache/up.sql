@@ -0,0 +1,17 @@
+-- Create rule_cache table
+CREATE TABLE IF NOT EXISTS rule_cache (
+    id SERIAL PRIMARY KEY,
+    sonar_host_url VARCHAR(255) NOT NULL,
+    rule_key VARCHAR(255) NOT NULL,
+    name TEXT NOT NULL,
+    description TEXT,
+    rule_type VARCHAR(50),
+    severity VARCHAR(50),
+    tags TEXT NOT NULL DEFAULT '',
+    remediation_effort VARCHAR(50),
+    fetched_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (sonar_host_url, rule_key)
+);
+
+-- Create indexes for better performance
+CREATE INDEX IF NOT EXISTS idx_rule_cache_fetched_at ON rule_cache(fetched_at);
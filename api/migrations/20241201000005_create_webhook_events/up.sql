@@ -0,0 +1,17 @@
+-- Create webhook_events table
+CREATE TABLE IF NOT EXISTS webhook_events (
+    id SERIAL PRIMARY KEY,
+    project_key VARCHAR(255) NOT NULL,
+    branch VARCHAR(255),
+    branch_type VARCHAR(50),
+    task_id VARCHAR(255),
+    analysis_id VARCHAR(255),
+    status VARCHAR(50),
+    quality_gate_status VARCHAR(50),
+    analysed_at TIMESTAMP,
+    payload TEXT NOT NULL,
+    received_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Create indexes for better performance
+CREATE INDEX IF NOT EXISTS idx_webhook_events_project_key ON webhook_events(project_key, received_at);
@@ -0,0 +1,5 @@
+-- Drop snapshot tables
+DROP TABLE IF EXISTS snapshot_issues;
+DROP TABLE IF EXISTS snapshot_measures;
+DROP TABLE IF EXISTS snapshot_conditions;
+DROP TABLE IF EXISTS analysis_snapshots;
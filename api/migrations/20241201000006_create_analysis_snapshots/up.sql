@@ -0,0 +1,56 @@
+-- Create analysis_snapshots table
+CREATE TABLE IF NOT EXISTS analysis_snapshots (
+    id SERIAL PRIMARY KEY,
+    project_key VARCHAR(255) NOT NULL,
+    branch VARCHAR(255),
+    pull_request VARCHAR(255),
+    source VARCHAR(50) NOT NULL,
+    analysis_id VARCHAR(255),
+    quality_gate_status VARCHAR(50),
+    issue_count INTEGER NOT NULL DEFAULT 0,
+    results TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Create snapshot_conditions table
+CREATE TABLE IF NOT EXISTS snapshot_conditions (
+    id SERIAL PRIMARY KEY,
+    snapshot_id INTEGER NOT NULL REFERENCES analysis_snapshots(id) ON DELETE CASCADE,
+    metric_key VARCHAR(255) NOT NULL,
+    comparator VARCHAR(10) NOT NULL,
+    error_threshold VARCHAR(255),
+    actual_value VARCHAR(255),
+    status VARCHAR(50) NOT NULL
+);
+
+-- Create snapshot_measures table
+CREATE TABLE IF NOT EXISTS snapshot_measures (
+    id SERIAL PRIMARY KEY,
+    snapshot_id INTEGER NOT NULL REFERENCES analysis_snapshots(id) ON DELETE CASCADE,
+    metric VARCHAR(255) NOT NULL,
+    value TEXT NOT NULL
+);
+
+-- Create snapshot_issues table
+CREATE TABLE IF NOT EXISTS snapshot_issues (
+    id SERIAL PRIMARY KEY,
+    snapshot_id INTEGER NOT NULL REFERENCES analysis_snapshots(id) ON DELETE CASCADE,
+    issue_key VARCHAR(255) NOT NULL,
+    rule VARCHAR(255) NOT NULL,
+    severity VARCHAR(50) NOT NULL,
+    issue_type VARCHAR(50) NOT NULL,
+    status VARCHAR(50) NOT NULL,
+    component TEXT NOT NULL,
+    line INTEGER,
+    message TEXT NOT NULL,
+    effort VARCHAR(50),
+    author VARCHAR(255),
+    tags TEXT NOT NULL DEFAULT '',
+    created_at TIMESTAMP
+);
+
+-- Create indexes for better performance
+CREATE INDEX IF NOT EXISTS idx_analysis_snapshots_project_key ON analysis_snapshots(project_key, created_at);
+CREATE INDEX IF NOT EXISTS idx_snapshot_conditions_snapshot_id ON snapshot_conditions(snapshot_id);
+CREATE INDEX IF NOT EXISTS idx_snapshot_measures_snapshot_id ON snapshot_measures(snapshot_id);
+CREATE INDEX IF NOT EXISTS idx_snapshot_issues_snapshot_id ON snapshot_issues(snapshot_id);
@@ -0,0 +1,3 @@
+-- Unlink projects and drop sonar_instances
+ALTER TABLE projects DROP COLUMN IF EXISTS sonar_instance_id;
+DROP TABLE IF EXISTS sonar_instances;
@@ -0,0 +1,36 @@
+-- Create sonar_instances table
+CREATE TABLE IF NOT EXISTS sonar_instances (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(255) NOT NULL UNIQUE,
+    url VARCHAR(255) NOT NULL UNIQUE,
+    admin_token TEXT,
+    analysis_token TEXT,
+    is_default BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- At most one default instance
+CREATE UNIQUE INDEX IF NOT EXISTS idx_sonar_instances_default ON sonar_instances(is_default) WHERE is_default;
+
+-- One instance per SonarQube URL admin tokens were generated for, with its latest token of each type.
+-- URLs are normalised like instance_url() does, so the API finds them again by URL.
+INSERT INTO sonar_instances (name, url, admin_token, analysis_token)
+SELECT hosts.url, hosts.url,
+    (SELECT token_value FROM admin_tokens
+        WHERE rtrim(btrim(sonar_host_url), '/') = hosts.url AND token_type = 'USER_TOKEN' AND token_value <> ''
+        ORDER BY id DESC LIMIT 1),
+    (SELECT token_value FROM admin_tokens
+        WHERE rtrim(btrim(sonar_host_url), '/') = hosts.url AND token_type = 'GLOBAL_ANALYSIS_TOKEN' AND token_value <> ''
+        ORDER BY id DESC LIMIT 1)
+FROM (SELECT DISTINCT rtrim(btrim(sonar_host_url), '/') AS url FROM admin_tokens) hosts
+WHERE hosts.url <> ''
+ON CONFLICT DO NOTHING;
+
+-- Link projects to the instance they are analysed on
+ALTER TABLE projects ADD COLUMN IF NOT EXISTS sonar_instance_id INTEGER REFERENCES sonar_instances(id);
+CREATE INDEX IF NOT EXISTS idx_projects_sonar_instance_id ON projects(sonar_instance_id);
+
+UPDATE projects SET sonar_instance_id = sonar_instances.id
+FROM sonar_instances
+WHERE projects.sonar_instance_id IS NULL AND rtrim(btrim(projects.sonar_host_url), '/') = sonar_instances.url;
@@ -0,0 +1,4 @@
+-- Remove token name and expiry columns
+ALTER TABLE admin_tokens DROP COLUMN IF EXISTS expiration_date;
+ALTER TABLE projects DROP COLUMN IF EXISTS sonar_token_expiration_date;
+ALTER TABLE projects DROP COLUMN IF EXISTS sonar_token_name;
@@ -0,0 +1,9 @@
+-- Track the name and expiry of project tokens, so they can be rotated and revoked
+ALTER TABLE projects ADD COLUMN IF NOT EXISTS sonar_token_name VARCHAR(255);
+ALTER TABLE projects ADD COLUMN IF NOT EXISTS sonar_token_expiration_date DATE;
+
+-- Expiry of admin tokens
+ALTER TABLE admin_tokens ADD COLUMN IF NOT EXISTS expiration_date DATE;
+
+-- Project tokens used to always be generated as <project_key>_token
+UPDATE projects SET sonar_token_name = project_key || '_token' WHERE sonar_token_name IS NULL AND sonar_token <> '';
@@ -0,0 +1,5 @@
+-- Restore the issue count as a required column
+DROP INDEX IF EXISTS idx_analysis_snapshots_project_source;
+UPDATE analysis_snapshots SET issue_count = 0 WHERE issue_count IS NULL;
+ALTER TABLE analysis_snapshots ALTER COLUMN issue_count SET DEFAULT 0;
+ALTER TABLE analysis_snapshots ALTER COLUMN issue_count SET NOT NULL;
@@ -0,0 +1,6 @@
+-- Snapshots taken while the issues could not be fetched have no issue count
+ALTER TABLE analysis_snapshots ALTER COLUMN issue_count DROP NOT NULL;
+ALTER TABLE analysis_snapshots ALTER COLUMN issue_count DROP DEFAULT;
+
+-- Snapshots are pruned per project and source
+CREATE INDEX IF NOT EXISTS idx_analysis_snapshots_project_source ON analysis_snapshots(project_key, source, created_at);
@@ -0,0 +1,3 @@
+-- Remove the remediation function columns
+ALTER TABLE rule_cache DROP COLUMN IF EXISTS remediation_gap_multiplier;
+ALTER TABLE rule_cache DROP COLUMN IF EXISTS remediation_function;
@@ -0,0 +1,6 @@
+-- Keep the remediation function of a rule and its gap multiplier apart from the base effort
+ALTER TABLE rule_cache ADD COLUMN IF NOT EXISTS remediation_function VARCHAR(50);
+ALTER TABLE rule_cache ADD COLUMN IF NOT EXISTS remediation_gap_multiplier VARCHAR(50);
+
+-- Cached rows may hold a gap multiplier as their effort, they are fetched again
+DELETE FROM rule_cache;
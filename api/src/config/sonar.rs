@@ -0,0 +1,108 @@
+use std::env;
+use std::time::Duration;
+
+// HTTP behaviour of the SonarQube client, read from the environment
+#[derive(Debug, Clone)]
+pub struct SonarClientConfig {
+    pub connect_timeout: Duration,
+    pub request_timeout: Duration,
+    // Retries apply to idempotent GETs only
+    pub max_retries: u32,
+    pub retry_base_delay: Duration,
+    pub retry_max_delay: Duration,
+}
+
+impl Default for SonarClientConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(5),
+            request_timeout: Duration::from_secs(30),
+            max_retries: 3,
+            retry_base_delay: Duration::from_millis(200),
+            retry_max_delay: Duration::from_secs(5),
+        }
+    }
+}
+
+impl SonarClientConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+
+        Self {
+            connect_timeout: env_secs("SONAR_CONNECT_TIMEOUT_SECS").unwrap_or(defaults.connect_timeout),
+            request_timeout: env_secs("SONAR_REQUEST_TIMEOUT_SECS").unwrap_or(defaults.request_timeout),
+            max_retries: env_number("SONAR_MAX_RETRIES")
+                .map(|retries| retries as u32)
+                .unwrap_or(defaults.max_retries),
+            retry_base_delay: env_number("SONAR_RETRY_BASE_DELAY_MS")
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.retry_base_delay),
+            retry_max_delay: env_number("SONAR_RETRY_MAX_DELAY_MS")
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.retry_max_delay),
+        }
+    }
+}
+
+// How long cached rule metadata is trusted before it is fetched again
+pub fn rule_cache_max_age() -> Duration {
+    env_number("RULE_CACHE_TTL_HOURS")
+        .map(|hours| Duration::from_secs(hours * 3600))
+        .unwrap_or(Duration::from_secs(7 * 24 * 3600))
+}
+
+// How long a source file downloaded for snippets is reused
+pub fn source_cache_max_age() -> Duration {
+    env_secs("SOURCE_CACHE_TTL_SECS").unwrap_or(Duration::from_secs(600))
+}
+
+// Shared secret SonarQube signs webhook deliveries with
+pub fn webhook_secret() -> Option<String> {
+    env_string("SONAR_WEBHOOK_SECRET")
+}
+
+// Public URL of our webhook receiver, registered on new projects when set
+pub fn webhook_url() -> Option<String> {
+    env_string("SONAR_WEBHOOK_URL")
+}
+
+// Keys tokens are encrypted with at rest, as comma separated id:base64 pairs of 32 byte keys
+pub fn token_encryption_keys() -> Option<String> {
+    env_string("TOKEN_ENCRYPTION_KEYS")
+}
+
+// Id of the key new tokens are encrypted under, the first configured key by default
+pub fn token_encryption_key_id() -> Option<String> {
+    env_string("TOKEN_ENCRYPTION_KEY_ID")
+}
+
+// SonarQube instance made the default at startup when no instance is marked as default yet
+pub fn sonar_host_url() -> String {
+    env_string("SONAR_HOST_URL").unwrap_or_else(|| "http://localhost:9000".to_string())
+}
+
+// Lifetime of generated tokens when the request gives no expiration date, tokens do not expire when unset
+pub fn token_expiry_days() -> Option<u64> {
+    env_number("SONAR_TOKEN_EXPIRY_DAYS").filter(|days| *days > 0)
+}
+
+// Snapshots taken by /api/results that are kept per project, all of them when set to 0
+pub fn snapshot_retention() -> Option<u64> {
+    match env_number("SNAPSHOT_RETENTION") {
+        Some(0) => None,
+        Some(keep) => Some(keep),
+        None => Some(100),
+    }
+}
+
+fn env_string(name: &str) -> Option<String> {
+    env::var(name).ok().map(|value| value.trim().to_string()).filter(|value| !value.is_empty())
+}
+
+fn env_number(name: &str) -> Option<u64> {
+    env::var(name).ok().and_then(|value| value.trim().parse().ok())
+}
+
+fn env_secs(name: &str) -> Option<Duration> {
+    env_number(name).map(Duration::from_secs)
+}
@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 use sea_orm::entity::prelude::*;
-use chrono::NaiveDateTime;
+use chrono::{NaiveDate, NaiveDateTime};
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
 #[sea_orm(table_name = "admin_tokens")]
@@ -12,6 +12,7 @@ pub struct Model {
     pub token_value: String,
     pub token_type: String,
     pub sonar_host_url: String,
+    pub expiration_date: Option<NaiveDate>,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
 }
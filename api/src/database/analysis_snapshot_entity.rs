@@ -0,0 +1,24 @@
+use serde::{Deserialize, Serialize};
+use sea_orm::entity::prelude::*;
+use chrono::NaiveDateTime;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "analysis_snapshots")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub project_key: String,
+    pub branch: Option<String>,
+    pub pull_request: Option<String>,
+    pub source: String, // "results" or "webhook"
+    pub analysis_id: Option<String>,
+    pub quality_gate_status: Option<String>,
+    pub issue_count: Option<i32>, // None when the issues could not be fetched
+    pub results: String, // /api/results body as it was returned
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
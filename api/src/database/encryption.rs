@@ -0,0 +1,281 @@
+use crate::config::sonar::{token_encryption_key_id, token_encryption_keys};
+use crate::database::service::ProjectService;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use base64::{Engine as _, engine::general_purpose};
+use sea_orm::DbErr;
+use std::collections::HashMap;
+use std::sync::Arc;
+use thiserror::Error;
+
+// Stored values look like enc:v1:<key id>:<wrapped data key>:<encrypted token>
+const ENCRYPTED_PREFIX: &str = "enc:v1:";
+const NONCE_LEN: usize = 12;
+
+#[derive(Debug, Error)]
+pub enum EncryptionError {
+    #[error("invalid TOKEN_ENCRYPTION_KEYS: {0}")]
+    Config(String),
+    #[error("encryption key '{0}' is not configured")]
+    UnknownKey(String),
+    #[error("malformed encrypted value")]
+    Malformed,
+    #[error("decryption failed, wrong key or tampered value")]
+    Decrypt,
+    #[error("encryption failed")]
+    Encrypt,
+}
+
+impl From<EncryptionError> for DbErr {
+    fn from(error: EncryptionError) -> Self {
+        DbErr::Custom(error.to_string())
+    }
+}
+
+// Envelope encryption of tokens: each value gets its own data key, wrapped by a key encryption key
+// from the configuration. Rotating a key only re-wraps the data keys.
+#[derive(Clone, Default)]
+pub struct TokenCipher {
+    keys: Arc<HashMap<String, Aes256Gcm>>,
+    active_key_id: Option<String>,
+}
+
+impl TokenCipher {
+    pub fn from_env() -> Result<Self, EncryptionError> {
+        match token_encryption_keys() {
+            Some(keys) => Self::new(&keys, token_encryption_key_id().as_deref()),
+            None => Ok(Self::default()),
+        }
+    }
+
+    // `keys` is a comma separated list of id:base64 pairs of 32 byte keys. New values are
+    // encrypted under `active_key_id`, or the first key when it is not given.
+    pub fn new(keys: &str, active_key_id: Option<&str>) -> Result<Self, EncryptionError> {
+        let mut parsed = HashMap::new();
+        let mut first_key_id = None;
+
+        for entry in keys.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+            let Some((key_id, encoded)) = entry.split_once(':') else {
+                return Err(EncryptionError::Config(format!("expected id:key, got '{}'", entry)));
+            };
+            let key_id = key_id.trim();
+            let key = general_purpose::STANDARD
+                .decode(encoded.trim())
+                .map_err(|e| EncryptionError::Config(format!("key '{}' is not base64: {}", key_id, e)))?;
+            let cipher = Aes256Gcm::new_from_slice(&key)
+                .map_err(|_| EncryptionError::Config(format!("key '{}' must be 32 bytes", key_id)))?;
+            if parsed.insert(key_id.to_string(), cipher).is_some() {
+                return Err(EncryptionError::Config(format!("key '{}' is listed twice", key_id)));
+            }
+            first_key_id.get_or_insert_with(|| key_id.to_string());
+        }
+
+        let active_key_id = active_key_id.map(str::to_string).or(first_key_id);
+        if let Some(key_id) = &active_key_id
+            && !parsed.contains_key(key_id)
+        {
+            return Err(EncryptionError::UnknownKey(key_id.clone()));
+        }
+
+        Ok(Self {
+            keys: Arc::new(parsed),
+            active_key_id,
+        })
+    }
+
+    // False when no key is configured, tokens are then written in plaintext
+    pub fn is_enabled(&self) -> bool {
+        self.active_key_id.is_some()
+    }
+
+    pub fn is_encrypted(value: &str) -> bool {
+        value.starts_with(ENCRYPTED_PREFIX)
+    }
+
+    // Id of the key an encrypted value is wrapped with, None for plaintext
+    pub fn key_id(value: &str) -> Option<&str> {
+        value.strip_prefix(ENCRYPTED_PREFIX)?.split(':').next()
+    }
+
+    pub fn encrypt(&self, plaintext: &str) -> Result<String, EncryptionError> {
+        let Some(key_id) = &self.active_key_id else {
+            return Ok(plaintext.to_string());
+        };
+
+        let data_key = Aes256Gcm::generate_key(OsRng);
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = Aes256Gcm::new(&data_key)
+            .encrypt(&nonce, plaintext.as_bytes())
+            .map_err(|_| EncryptionError::Encrypt)?;
+
+        let wrapped_key = self.wrap(key_id, &data_key)?;
+        Ok(format!("{}{}:{}:{}", ENCRYPTED_PREFIX, key_id, wrapped_key, encode(&nonce, &ciphertext)))
+    }
+
+    // Plaintext values are returned unchanged, so rows written before encryption stay readable
+    pub fn decrypt(&self, value: &str) -> Result<String, EncryptionError> {
+        let Some((key_id, wrapped_key, data)) = split(value)? else {
+            return Ok(value.to_string());
+        };
+
+        let data_key = self.unwrap(key_id, wrapped_key)?;
+        let (nonce, ciphertext) = decode(data)?;
+        let plaintext = Aes256Gcm::new_from_slice(&data_key)
+            .map_err(|_| EncryptionError::Malformed)?
+            .decrypt(&Nonce::from(nonce), ciphertext.as_slice())
+            .map_err(|_| EncryptionError::Decrypt)?;
+
+        String::from_utf8(plaintext).map_err(|_| EncryptionError::Malformed)
+    }
+
+    // The value under the active key, or None when it already is. Encrypted values keep
+    // their data key and ciphertext, only the wrapped key changes.
+    pub fn rewrap(&self, value: &str) -> Result<Option<String>, EncryptionError> {
+        let Some(active_key_id) = &self.active_key_id else {
+            return Ok(None);
+        };
+        let Some((key_id, wrapped_key, data)) = split(value)? else {
+            return self.encrypt(value).map(Some);
+        };
+        if key_id == active_key_id {
+            return Ok(None);
+        }
+
+        let data_key = self.unwrap(key_id, wrapped_key)?;
+        let wrapped_key = self.wrap(active_key_id, &data_key)?;
+        Ok(Some(format!("{}{}:{}:{}", ENCRYPTED_PREFIX, active_key_id, wrapped_key, data)))
+    }
+
+    // The key id is bound as associated data, so a wrapped key cannot be relabelled
+    fn wrap(&self, key_id: &str, data_key: &[u8]) -> Result<String, EncryptionError> {
+        let key = self.keys.get(key_id).ok_or_else(|| EncryptionError::UnknownKey(key_id.to_string()))?;
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let wrapped = key
+            .encrypt(&nonce, Payload { msg: data_key, aad: key_id.as_bytes() })
+            .map_err(|_| EncryptionError::Encrypt)?;
+        Ok(encode(&nonce, &wrapped))
+    }
+
+    fn unwrap(&self, key_id: &str, wrapped_key: &str) -> Result<Vec<u8>, EncryptionError> {
+        let key = self.keys.get(key_id).ok_or_else(|| EncryptionError::UnknownKey(key_id.to_string()))?;
+        let (nonce, wrapped) = decode(wrapped_key)?;
+        key.decrypt(&Nonce::from(nonce), Payload { msg: &wrapped, aad: key_id.as_bytes() })
+            .map_err(|_| EncryptionError::Decrypt)
+    }
+}
+
+// (key id, wrapped data key, encrypted token), None for plaintext
+fn split(value: &str) -> Result<Option<(&str, &str, &str)>, EncryptionError> {
+    let Some(rest) = value.strip_prefix(ENCRYPTED_PREFIX) else {
+        return Ok(None);
+    };
+    let mut parts = rest.splitn(3, ':');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(key_id), Some(wrapped_key), Some(data)) => Ok(Some((key_id, wrapped_key, data))),
+        _ => Err(EncryptionError::Malformed),
+    }
+}
+
+fn encode(nonce: &[u8], ciphertext: &[u8]) -> String {
+    general_purpose::STANDARD.encode([nonce, ciphertext].concat())
+}
+
+fn decode(value: &str) -> Result<([u8; NONCE_LEN], Vec<u8>), EncryptionError> {
+    let bytes = general_purpose::STANDARD.decode(value).map_err(|_| EncryptionError::Malformed)?;
+    if bytes.len() <= NONCE_LEN {
+        return Err(EncryptionError::Malformed);
+    }
+    let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+    let nonce = nonce.try_into().map_err(|_| EncryptionError::Malformed)?;
+    Ok((nonce, ciphertext.to_vec()))
+}
+
+// `tokens status`, `tokens encrypt` and `tokens reencrypt`
+pub async fn run_command(project_service: &ProjectService, args: &[String]) -> Result<(), DbErr> {
+    match args.first().map(String::as_str) {
+        Some("status") | None => {
+            for (key_id, count) in project_service.token_key_usage().await? {
+                println!("{}: {} token(s)", key_id, count);
+            }
+        }
+        Some("encrypt") => {
+            let changed = project_service.encrypt_plaintext_tokens().await?;
+            println!("Encrypted {} plaintext token(s)", changed);
+        }
+        Some("reencrypt") => {
+            let changed = project_service.reencrypt_tokens().await?;
+            println!("Re-encrypted {} token(s) under the active key", changed);
+        }
+        Some(other) => {
+            return Err(DbErr::Custom(format!("Unknown tokens command '{}', expected status, encrypt or reencrypt", other)));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(byte: u8) -> String {
+        general_purpose::STANDARD.encode([byte; 32])
+    }
+
+    fn cipher(active_key_id: &str) -> TokenCipher {
+        let keys = format!("old:{},new:{}", key(1), key(2));
+        TokenCipher::new(&keys, Some(active_key_id)).unwrap()
+    }
+
+    #[test]
+    fn encrypt_then_decrypt_returns_the_token() {
+        let cipher = cipher("old");
+        let encrypted = cipher.encrypt("squ_secret").unwrap();
+
+        assert!(TokenCipher::is_encrypted(&encrypted));
+        assert_eq!(TokenCipher::key_id(&encrypted), Some("old"));
+        assert!(!encrypted.contains("squ_secret"));
+        assert_eq!(cipher.decrypt(&encrypted).unwrap(), "squ_secret");
+    }
+
+    #[test]
+    fn plaintext_passes_through() {
+        assert_eq!(TokenCipher::default().encrypt("squ_secret").unwrap(), "squ_secret");
+        assert_eq!(cipher("old").decrypt("squ_secret").unwrap(), "squ_secret");
+        assert_eq!(TokenCipher::key_id("squ_secret"), None);
+    }
+
+    #[test]
+    fn rewrap_moves_the_value_to_the_active_key() {
+        let encrypted = cipher("old").encrypt("squ_secret").unwrap();
+        let rotated = cipher("new");
+
+        let rewrapped = rotated.rewrap(&encrypted).unwrap().unwrap();
+        assert_eq!(TokenCipher::key_id(&rewrapped), Some("new"));
+        assert_eq!(rotated.decrypt(&rewrapped).unwrap(), "squ_secret");
+        assert!(rotated.rewrap(&rewrapped).unwrap().is_none());
+
+        // Only the wrapped data key changes
+        let (_, _, data) = split(&encrypted).unwrap().unwrap();
+        let (_, _, rewrapped_data) = split(&rewrapped).unwrap().unwrap();
+        assert_eq!(data, rewrapped_data);
+    }
+
+    #[test]
+    fn relabelled_key_id_fails_to_decrypt() {
+        let cipher = cipher("old");
+        let encrypted = cipher.encrypt("squ_secret").unwrap();
+        let relabelled = encrypted.replacen("enc:v1:old:", "enc:v1:new:", 1);
+
+        assert!(matches!(cipher.decrypt(&relabelled), Err(EncryptionError::Decrypt)));
+    }
+
+    #[test]
+    fn malformed_values_are_rejected() {
+        let cipher = cipher("old");
+
+        assert!(matches!(cipher.decrypt("enc:v1:old"), Err(EncryptionError::Malformed)));
+        assert!(matches!(cipher.decrypt("enc:v1:old:not base64:AAAA"), Err(EncryptionError::Malformed)));
+        let too_short = general_purpose::STANDARD.encode([0u8; NONCE_LEN]);
+        assert!(matches!(cipher.decrypt(&format!("enc:v1:old:{}:{}", too_short, too_short)), Err(EncryptionError::Malformed)));
+    }
+}
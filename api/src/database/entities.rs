@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 use sea_orm::entity::prelude::*;
-use chrono::NaiveDateTime;
+use chrono::{NaiveDate, NaiveDateTime};
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
 #[sea_orm(table_name = "projects")]
@@ -11,7 +11,10 @@ pub struct Model {
     pub project_name: String,
     pub project_path: String,
     pub sonar_token: String,
+    pub sonar_token_name: Option<String>,
+    pub sonar_token_expiration_date: Option<NaiveDate>,
     pub sonar_host_url: String,
+    pub sonar_instance_id: Option<i32>,
     pub language: String,
     pub sources_path: String,
     pub tests_path: String,
@@ -0,0 +1,306 @@
+use crate::database::{
+    admin_token_entity, analysis_snapshot_entity, entities, rule_cache_entity, snapshot_condition_entity,
+    snapshot_issue_entity, snapshot_measure_entity, sonar_instance_entity, webhook_event_entity,
+};
+use sea_orm::IdenStatic;
+use sea_orm::*;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use tracing::{info, warn};
+
+// SQL of a migration, embedded from api/migrations/<version>_<name>/
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    up: &'static str,
+    down: &'static str,
+}
+
+macro_rules! migration {
+    ($version:literal, $name:literal) => {
+        Migration {
+            version: $version,
+            name: $name,
+            up: include_str!(concat!("../../migrations/", stringify!($version), "_", $name, "/up.sql")),
+            down: include_str!(concat!("../../migrations/", stringify!($version), "_", $name, "/down.sql")),
+        }
+    };
+}
+
+// In version order. The first five are the baseline that init.sql used to create; their
+// statements are idempotent, so a database created by init.sql simply records them as applied.
+pub const MIGRATIONS: &[Migration] = &[
+    migration!(20241201000001, "create_projects"),
+    migration!(20241201000002, "create_admin_tokens"),
+    migration!(20241201000003, "add_token_type"),
+    migration!(20241201000004, "create_rule_cache"),
+    migration!(20241201000005, "create_webhook_events"),
+    migration!(20241201000006, "create_analysis_snapshots"),
+    migration!(20241201000007, "create_sonar_instances"),
+    migration!(20241201000008, "add_token_expiration"),
+    migration!(20241201000009, "allow_unknown_issue_count"),
+    migration!(20241201000010, "add_rule_remediation_function"),
+];
+
+const CREATE_MIGRATIONS_TABLE: &str = "CREATE TABLE IF NOT EXISTS schema_migrations (
+    version BIGINT PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    checksum VARCHAR(64) NOT NULL,
+    applied_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+)";
+
+// Key of the advisory lock that serialises migrations between replicas starting together
+const MIGRATION_LOCK_KEY: i64 = 20241201;
+
+#[derive(Debug, Serialize)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub name: String,
+    pub applied_at: Option<chrono::NaiveDateTime>,
+    // up.sql changed since it was applied
+    pub modified: bool,
+    // Applied, but unknown to this build
+    pub missing: bool,
+}
+
+struct AppliedMigration {
+    name: String,
+    checksum: String,
+    applied_at: chrono::NaiveDateTime,
+}
+
+fn checksum(sql: &str) -> String {
+    hex::encode(Sha256::digest(sql.as_bytes()))
+}
+
+// Held until the transaction ends. The pool hands out any connection, so a session lock could
+// be released on another connection than the one that took it.
+async fn lock_migrations(txn: &DatabaseTransaction) -> Result<(), DbErr> {
+    txn.execute(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        "SELECT pg_advisory_xact_lock($1)",
+        [MIGRATION_LOCK_KEY.into()],
+    ))
+    .await?;
+    Ok(())
+}
+
+async fn is_applied(txn: &DatabaseTransaction, version: i64) -> Result<bool, DbErr> {
+    let row = txn
+        .query_one(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            "SELECT 1 AS applied FROM schema_migrations WHERE version = $1",
+            [version.into()],
+        ))
+        .await?;
+    Ok(row.is_some())
+}
+
+async fn applied_migrations(db: &DatabaseConnection) -> Result<HashMap<i64, AppliedMigration>, DbErr> {
+    // Concurrent CREATE TABLE IF NOT EXISTS can still collide, so it runs under the lock
+    let txn = db.begin().await?;
+    lock_migrations(&txn).await?;
+    txn.execute_unprepared(CREATE_MIGRATIONS_TABLE).await?;
+    txn.commit().await?;
+
+    let rows = db
+        .query_all(Statement::from_string(
+            DbBackend::Postgres,
+            "SELECT version, name, checksum, applied_at FROM schema_migrations ORDER BY version",
+        ))
+        .await?;
+
+    let mut applied = HashMap::new();
+    for row in rows {
+        applied.insert(
+            row.try_get::<i64>("", "version")?,
+            AppliedMigration {
+                name: row.try_get("", "name")?,
+                checksum: row.try_get("", "checksum")?,
+                applied_at: row.try_get("", "applied_at")?,
+            },
+        );
+    }
+    Ok(applied)
+}
+
+// Applies pending migrations up to `target` (all of them when None), each in its own transaction
+pub async fn up(db: &DatabaseConnection, target: Option<i64>) -> Result<Vec<i64>, DbErr> {
+    let applied = applied_migrations(db).await?;
+    let mut done = Vec::new();
+
+    for migration in MIGRATIONS {
+        if target.is_some_and(|target| migration.version > target) {
+            break;
+        }
+        if let Some(existing) = applied.get(&migration.version) {
+            if existing.checksum != checksum(migration.up) {
+                warn!("Migration {}_{} was modified after it was applied", migration.version, migration.name);
+            }
+            continue;
+        }
+
+        // Another replica may be applying it, in which case this waits for it and skips it
+        let txn = db.begin().await?;
+        lock_migrations(&txn).await?;
+        if is_applied(&txn, migration.version).await? {
+            txn.commit().await?;
+            continue;
+        }
+        txn.execute_unprepared(migration.up).await?;
+        txn.execute(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            "INSERT INTO schema_migrations (version, name, checksum) VALUES ($1, $2, $3)",
+            [migration.version.into(), migration.name.into(), checksum(migration.up).into()],
+        ))
+        .await?;
+        txn.commit().await?;
+
+        info!("Applied migration {}_{}", migration.version, migration.name);
+        done.push(migration.version);
+    }
+
+    Ok(done)
+}
+
+// Reverts the last `steps` applied migrations, newest first
+pub async fn down(db: &DatabaseConnection, steps: usize) -> Result<Vec<i64>, DbErr> {
+    let applied = applied_migrations(db).await?;
+    let mut versions: Vec<i64> = applied.keys().copied().collect();
+    versions.sort_unstable_by(|a, b| b.cmp(a));
+    let mut done = Vec::new();
+
+    for version in versions.into_iter().take(steps) {
+        let Some(migration) = MIGRATIONS.iter().find(|migration| migration.version == version) else {
+            return Err(DbErr::Custom(format!("Migration {} is applied but unknown to this build, cannot revert it", version)));
+        };
+
+        let txn = db.begin().await?;
+        lock_migrations(&txn).await?;
+        if !is_applied(&txn, version).await? {
+            txn.commit().await?;
+            continue;
+        }
+        txn.execute_unprepared(migration.down).await?;
+        txn.execute(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            "DELETE FROM schema_migrations WHERE version = $1",
+            [version.into()],
+        ))
+        .await?;
+        txn.commit().await?;
+
+        info!("Reverted migration {}_{}", migration.version, migration.name);
+        done.push(version);
+    }
+
+    Ok(done)
+}
+
+pub async fn status(db: &DatabaseConnection) -> Result<Vec<MigrationStatus>, DbErr> {
+    let mut applied = applied_migrations(db).await?;
+
+    let mut statuses: Vec<MigrationStatus> = MIGRATIONS
+        .iter()
+        .map(|migration| {
+            let existing = applied.remove(&migration.version);
+            MigrationStatus {
+                version: migration.version,
+                name: migration.name.to_string(),
+                modified: existing.as_ref().is_some_and(|existing| existing.checksum != checksum(migration.up)),
+                applied_at: existing.map(|existing| existing.applied_at),
+                missing: false,
+            }
+        })
+        .collect();
+    statuses.extend(applied.into_iter().map(|(version, existing)| MigrationStatus {
+        version,
+        name: existing.name,
+        applied_at: Some(existing.applied_at),
+        modified: false,
+        missing: true,
+    }));
+    statuses.sort_by_key(|status| status.version);
+
+    Ok(statuses)
+}
+
+// Columns an entity maps that the table does not have, as "table.column"
+async fn missing_columns<E: EntityTrait>(db: &DatabaseConnection, entity: E) -> Result<Vec<String>, DbErr> {
+    let table = entity.table_name();
+    let rows = db
+        .query_all(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            "SELECT column_name FROM information_schema.columns WHERE table_schema = current_schema() AND table_name = $1",
+            [table.into()],
+        ))
+        .await?;
+
+    let mut existing = HashSet::new();
+    for row in rows {
+        existing.insert(row.try_get::<String>("", "column_name")?);
+    }
+
+    Ok(E::Column::iter()
+        .map(|column| column.as_str().to_string())
+        .filter(|column| !existing.contains(column))
+        .map(|column| format!("{}.{}", table, column))
+        .collect())
+}
+
+// Compares the SeaORM entities with the migrated schema, so a column added to an entity
+// without a migration is caught at startup instead of on the first query that uses it
+pub async fn check_entities(db: &DatabaseConnection) -> Result<Vec<String>, DbErr> {
+    let mut missing = Vec::new();
+    missing.extend(missing_columns(db, entities::Entity).await?);
+    missing.extend(missing_columns(db, admin_token_entity::Entity).await?);
+    missing.extend(missing_columns(db, rule_cache_entity::Entity).await?);
+    missing.extend(missing_columns(db, webhook_event_entity::Entity).await?);
+    missing.extend(missing_columns(db, analysis_snapshot_entity::Entity).await?);
+    missing.extend(missing_columns(db, snapshot_condition_entity::Entity).await?);
+    missing.extend(missing_columns(db, snapshot_measure_entity::Entity).await?);
+    missing.extend(missing_columns(db, snapshot_issue_entity::Entity).await?);
+    missing.extend(missing_columns(db, sonar_instance_entity::Entity).await?);
+    Ok(missing)
+}
+
+// `migrate up [version]`, `migrate down [steps]` and `migrate status`
+pub async fn run_command(db: &DatabaseConnection, args: &[String]) -> Result<(), DbErr> {
+    match args.first().map(String::as_str) {
+        Some("up") | None => {
+            let target = match args.get(1) {
+                Some(version) => Some(version.parse().map_err(|_| DbErr::Custom(format!("Invalid version: {}", version)))?),
+                None => None,
+            };
+            let done = up(db, target).await?;
+            println!("Applied {} migration(s)", done.len());
+        }
+        Some("down") => {
+            let steps = match args.get(1) {
+                Some(steps) => steps.parse().map_err(|_| DbErr::Custom(format!("Invalid number of steps: {}", steps)))?,
+                None => 1,
+            };
+            let done = down(db, steps).await?;
+            println!("Reverted {} migration(s)", done.len());
+        }
+        Some("status") => {
+            for status in status(db).await? {
+                let state = match (&status.applied_at, status.missing, status.modified) {
+                    (_, true, _) => "applied, unknown to this build".to_string(),
+                    (Some(applied_at), _, true) => format!("applied {}, modified since", applied_at),
+                    (Some(applied_at), _, false) => format!("applied {}", applied_at),
+                    (None, _, _) => "pending".to_string(),
+                };
+                println!("{}_{}: {}", status.version, status.name, state);
+            }
+            for column in check_entities(db).await? {
+                println!("Entity column missing from the schema: {}", column);
+            }
+        }
+        Some(other) => {
+            return Err(DbErr::Custom(format!("Unknown migrate command '{}', expected up, down or status", other)));
+        }
+    }
+    Ok(())
+}
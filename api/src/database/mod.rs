@@ -1,6 +1,15 @@
+pub mod encryption;
 pub mod entities;
+pub mod migrations;
 pub mod service;
 pub mod admin_token_entity;
+pub mod rule_cache_entity;
+pub mod webhook_event_entity;
+pub mod analysis_snapshot_entity;
+pub mod snapshot_condition_entity;
+pub mod snapshot_measure_entity;
+pub mod snapshot_issue_entity;
+pub mod sonar_instance_entity;
 
 use sea_orm::Database;
 use sea_orm::DatabaseConnection;
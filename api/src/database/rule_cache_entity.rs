@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+use sea_orm::entity::prelude::*;
+use chrono::NaiveDateTime;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "rule_cache")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub sonar_host_url: String,
+    pub rule_key: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub rule_type: Option<String>,
+    pub severity: Option<String>,
+    pub tags: String, // comma separated
+    pub remediation_function: Option<String>,
+    pub remediation_effort: Option<String>,
+    pub remediation_gap_multiplier: Option<String>,
+    pub fetched_at: NaiveDateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
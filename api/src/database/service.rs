@@ -1,11 +1,38 @@
 use crate::database::admin_token_entity::ActiveModel as AdminTokenActiveModel;
 use crate::database::admin_token_entity::Entity as AdminTokenEntity;
+use crate::database::admin_token_entity::Model as AdminTokenModel;
+use crate::database::analysis_snapshot_entity::ActiveModel as SnapshotActiveModel;
+use crate::database::analysis_snapshot_entity::Entity as SnapshotEntity;
+use crate::database::analysis_snapshot_entity::Model as SnapshotModel;
+use crate::database::encryption::TokenCipher;
 use crate::database::entities::ActiveModel as ProjectActiveModel;
 use crate::database::entities::Entity as ProjectEntity;
 use crate::database::entities::Model as ProjectModel;
+use crate::database::rule_cache_entity::ActiveModel as RuleCacheActiveModel;
+use crate::database::rule_cache_entity::Entity as RuleCacheEntity;
+use crate::database::rule_cache_entity::Model as RuleCacheModel;
+use crate::database::snapshot_condition_entity::ActiveModel as SnapshotConditionActiveModel;
+use crate::database::snapshot_condition_entity::Entity as SnapshotConditionEntity;
+use crate::database::snapshot_condition_entity::Model as SnapshotConditionModel;
+use crate::database::snapshot_issue_entity::ActiveModel as SnapshotIssueActiveModel;
+use crate::database::snapshot_issue_entity::Entity as SnapshotIssueEntity;
+use crate::database::snapshot_issue_entity::Model as SnapshotIssueModel;
+use crate::database::snapshot_measure_entity::ActiveModel as SnapshotMeasureActiveModel;
+use crate::database::snapshot_measure_entity::Entity as SnapshotMeasureEntity;
+use crate::database::snapshot_measure_entity::Model as SnapshotMeasureModel;
+use crate::database::sonar_instance_entity::ActiveModel as SonarInstanceActiveModel;
+use crate::database::sonar_instance_entity::Entity as SonarInstanceEntity;
+use crate::database::sonar_instance_entity::Model as SonarInstanceModel;
+use crate::database::webhook_event_entity::ActiveModel as WebhookEventActiveModel;
+use crate::database::webhook_event_entity::Entity as WebhookEventEntity;
+use crate::database::webhook_event_entity::Model as WebhookEventModel;
+use crate::sonarqube::client::{AnalysisTarget, Issue, Measure, ProjectQualityGate, ProjectStatus, RuleDetails, SonarQubeClient};
+use crate::sonarqube::webhooks::WebhookPayload;
 use chrono::Utc;
+use sea_orm::sea_query::{Expr, OnConflict};
 use sea_orm::*;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateProjectRequest {
@@ -16,11 +43,50 @@ pub struct CreateProjectRequest {
     pub sources_path: String,
     pub tests_path: String,
     pub coverage_report_path: Option<String>,
+    // Name of the quality gate to select for the new project, the default gate otherwise
+    pub quality_gate: Option<String>,
+    // Name of the permission template to apply to the new project
+    pub permission_template: Option<String>,
+    // SonarQube instance to create the project on, the default instance otherwise
+    pub sonar_instance_id: Option<i32>,
+}
+
+// Unset fields are left unchanged
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateProjectRequest {
+    pub project_path: String, // identifies the project
+    pub new_project_key: Option<String>,
+    pub new_project_path: Option<String>,
+    pub project_name: Option<String>,
+    pub language: Option<String>,
+    pub sources_path: Option<String>,
+    pub tests_path: Option<String>,
+    pub coverage_report_path: Option<String>,
+    pub visibility: Option<String>, // "public" or "private", only stored in SonarQube
+}
+
+impl UpdateProjectRequest {
+    pub fn is_empty(&self) -> bool {
+        self.new_project_key.is_none()
+            && self.new_project_path.is_none()
+            && self.project_name.is_none()
+            && self.language.is_none()
+            && self.sources_path.is_none()
+            && self.tests_path.is_none()
+            && self.coverage_report_path.is_none()
+            && self.visibility.is_none()
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ScanProjectRequest {
     pub project_path: String,
+    // Branch or pull request to read or analyse, the main branch by default
+    #[serde(flatten)]
+    pub target: AnalysisTarget,
+    // Only used by /api/generate-command for pull request analysis
+    pub pull_request_branch: Option<String>,
+    pub pull_request_base: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -30,6 +96,8 @@ pub struct CreateAdminTokenRequest {
     pub token_name: String,
     pub token_type: String, // "USER_TOKEN" or "GLOBAL_ANALYSIS_TOKEN"
     pub sonar_host_url: String,
+    // SONAR_TOKEN_EXPIRY_DAYS from now when not given
+    pub expiration_date: Option<chrono::NaiveDate>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -40,10 +108,101 @@ pub struct AdminTokenResponse {
     pub token_value: String,
     pub token_type: String,
     pub sonar_host_url: String,
+    pub expiration_date: Option<chrono::NaiveDate>,
     pub created_at: chrono::NaiveDateTime,
     pub updated_at: chrono::NaiveDateTime,
 }
 
+// A token as generated in SonarQube
+#[derive(Debug, Clone)]
+pub struct GeneratedToken {
+    pub name: String,
+    pub value: String,
+    pub expiration_date: Option<chrono::NaiveDate>,
+}
+
+// A stored project or admin token that expires soon or has expired
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExpiringTokenResponse {
+    pub kind: String, // "project" or "admin"
+    pub id: i32,      // project or admin token id
+    pub token_name: Option<String>,
+    pub token_type: String,
+    pub owner: String, // project path, or username of an admin token
+    pub sonar_host_url: String,
+    pub expiration_date: chrono::NaiveDate,
+    pub expired: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateSonarInstanceRequest {
+    pub name: String,
+    pub url: String,
+    pub admin_token: Option<String>,    // USER_TOKEN of a user with admin privileges
+    pub analysis_token: Option<String>, // GLOBAL_ANALYSIS_TOKEN
+    pub is_default: Option<bool>,
+}
+
+// Unset fields are left unchanged, an empty token removes it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateSonarInstanceRequest {
+    pub id: i32,
+    pub name: Option<String>,
+    pub url: Option<String>,
+    pub admin_token: Option<String>,
+    pub analysis_token: Option<String>,
+    pub is_default: Option<bool>, // only true, the default moves by making another instance the default
+}
+
+// Tokens are never returned, only whether they are set
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SonarInstanceResponse {
+    pub id: i32,
+    pub name: String,
+    pub url: String,
+    pub has_admin_token: bool,
+    pub has_analysis_token: bool,
+    pub is_default: bool,
+    pub project_count: u64,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+// An instance with its tokens decrypted, what SonarQube clients are built from
+#[derive(Debug, Clone)]
+pub struct SonarInstance {
+    pub id: i32,
+    pub name: String,
+    pub url: String,
+    admin_token: Option<String>,
+    analysis_token: Option<String>,
+}
+
+impl SonarInstance {
+    // "GLOBAL_ANALYSIS_TOKEN" or "USER_TOKEN"
+    pub fn token(&self, token_type: &str) -> Option<&str> {
+        match token_type {
+            "GLOBAL_ANALYSIS_TOKEN" => self.analysis_token.as_deref(),
+            _ => self.admin_token.as_deref(),
+        }
+    }
+}
+
+// URLs are compared without a trailing slash
+fn instance_url(url: &str) -> String {
+    url.trim().trim_end_matches('/').to_string()
+}
+
+// Unmarks the current default instance, before another one is marked
+async fn clear_default_sonar_instance<C: ConnectionTrait>(db: &C) -> Result<(), DbErr> {
+    SonarInstanceEntity::update_many()
+        .col_expr(crate::database::sonar_instance_entity::Column::IsDefault, Expr::value(false))
+        .filter(crate::database::sonar_instance_entity::Column::IsDefault.eq(true))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ProjectResponse {
     pub id: i32,
@@ -51,15 +210,140 @@ pub struct ProjectResponse {
     pub project_name: String,
     pub project_path: String,
     pub sonar_token: String,
+    pub sonar_token_name: Option<String>,
+    pub sonar_token_expiration_date: Option<chrono::NaiveDate>,
     pub sonar_host_url: String,
+    pub sonar_instance_id: Option<i32>,
     pub language: String,
     pub sources_path: String,
     pub tests_path: String,
     pub coverage_report_path: Option<String>,
+    // Read from SonarQube, left out when it couldn't be read
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quality_gate: Option<ProjectQualityGate>,
     pub created_at: chrono::NaiveDateTime,
     pub updated_at: chrono::NaiveDateTime,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WebhookEventResponse {
+    pub id: i32,
+    pub project_key: String,
+    pub branch: Option<String>,
+    pub branch_type: Option<String>,
+    pub task_id: Option<String>,
+    pub analysis_id: Option<String>,
+    pub status: Option<String>,
+    pub quality_gate_status: Option<String>,
+    pub analysed_at: Option<chrono::NaiveDateTime>,
+    pub payload: serde_json::Value,
+    pub received_at: chrono::NaiveDateTime,
+}
+
+// Rows per INSERT when storing snapshot issues, well under Postgres' bind parameter limit
+const SNAPSHOT_INSERT_BATCH: usize = 1000;
+
+// What a snapshot is built from, borrowed from the results that were just returned
+pub struct NewSnapshot<'a> {
+    pub project_key: &'a str,
+    pub target: &'a AnalysisTarget,
+    pub source: &'a str, // "results" or "webhook"
+    pub analysis_id: Option<String>,
+    pub quality_gate: Option<&'a ProjectStatus>,
+    pub measures: &'a [Measure],
+    // None when the issues could not be fetched
+    pub issues: Option<&'a [Issue]>,
+    pub results: &'a serde_json::Value,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SnapshotResponse {
+    pub id: i32,
+    pub project_key: String,
+    pub branch: Option<String>,
+    pub pull_request: Option<String>,
+    pub source: String,
+    pub analysis_id: Option<String>,
+    pub quality_gate_status: Option<String>,
+    pub issue_count: Option<i32>,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+impl From<SnapshotModel> for SnapshotResponse {
+    fn from(model: SnapshotModel) -> Self {
+        Self {
+            id: model.id,
+            project_key: model.project_key,
+            branch: model.branch,
+            pull_request: model.pull_request,
+            source: model.source,
+            analysis_id: model.analysis_id,
+            quality_gate_status: model.quality_gate_status,
+            issue_count: model.issue_count,
+            created_at: model.created_at,
+        }
+    }
+}
+
+// A stored snapshot with its normalized gate conditions and measures
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SnapshotDetails {
+    pub snapshot: SnapshotResponse,
+    pub conditions: Vec<SnapshotConditionModel>,
+    pub measures: Vec<SnapshotMeasureModel>,
+    pub results: serde_json::Value,
+}
+
+// Filters of the normalized snapshot issues, unset ones match everything
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SnapshotIssueFilter {
+    pub severity: Option<String>,
+    pub issue_type: Option<String>,
+    pub rule: Option<String>,
+}
+
+impl From<WebhookEventModel> for WebhookEventResponse {
+    fn from(model: WebhookEventModel) -> Self {
+        Self {
+            id: model.id,
+            project_key: model.project_key,
+            branch: model.branch,
+            branch_type: model.branch_type,
+            task_id: model.task_id,
+            analysis_id: model.analysis_id,
+            status: model.status,
+            quality_gate_status: model.quality_gate_status,
+            analysed_at: model.analysed_at,
+            payload: serde_json::from_str(&model.payload).unwrap_or(serde_json::Value::String(model.payload)),
+            received_at: model.received_at,
+        }
+    }
+}
+
+impl From<RuleCacheModel> for RuleDetails {
+    fn from(model: RuleCacheModel) -> Self {
+        Self {
+            key: model.rule_key,
+            name: model.name,
+            description: model.description,
+            rule_type: model.rule_type,
+            severity: model.severity,
+            tags: model.tags.split(',').filter(|t| !t.is_empty()).map(str::to_string).collect(),
+            remediation_function: model.remediation_function,
+            remediation_effort: model.remediation_effort,
+            remediation_gap_multiplier: model.remediation_gap_multiplier,
+        }
+    }
+}
+
+impl ProjectResponse {
+    pub fn set_token(&mut self, token: GeneratedToken) {
+        self.sonar_token = token.value;
+        self.sonar_token_name = Some(token.name);
+        self.sonar_token_expiration_date = token.expiration_date;
+    }
+}
+
 impl From<ProjectModel> for ProjectResponse {
     fn from(model: ProjectModel) -> Self {
         Self {
@@ -68,11 +352,15 @@ impl From<ProjectModel> for ProjectResponse {
             project_name: model.project_name,
             project_path: model.project_path,
             sonar_token: model.sonar_token,
+            sonar_token_name: model.sonar_token_name,
+            sonar_token_expiration_date: model.sonar_token_expiration_date,
             sonar_host_url: model.sonar_host_url,
+            sonar_instance_id: model.sonar_instance_id,
             language: model.language,
             sources_path: model.sources_path,
             tests_path: model.tests_path,
             coverage_report_path: model.coverage_report_path,
+            quality_gate: None,
             created_at: model.created_at,
             updated_at: model.updated_at,
         }
@@ -82,23 +370,31 @@ impl From<ProjectModel> for ProjectResponse {
 #[derive(Clone)]
 pub struct ProjectService {
     db: DatabaseConnection,
+    // Encrypts project, admin and instance tokens on write and decrypts them on read
+    cipher: TokenCipher,
 }
 
 impl ProjectService {
-    pub fn new(db: DatabaseConnection) -> Self {
-        Self { db }
+    pub fn new(db: DatabaseConnection, cipher: TokenCipher) -> Self {
+        Self { db, cipher }
+    }
+
+    fn project_response(&self, model: ProjectModel) -> Result<ProjectResponse, DbErr> {
+        let mut response = ProjectResponse::from(model);
+        response.sonar_token = self.cipher.decrypt(&response.sonar_token)?;
+        Ok(response)
     }
 
-    pub async fn create_project(&self, request: CreateProjectRequest) -> Result<ProjectResponse, DbErr> {
+    pub async fn create_project(&self, request: CreateProjectRequest, instance: &SonarInstance) -> Result<ProjectResponse, DbErr> {
         let now = Utc::now().naive_utc();
-        let sonar_host_url = "http://localhost:9000".to_string();
 
         let project = ProjectActiveModel {
             project_key: Set(request.project_key),
             project_name: Set(request.project_name),
             project_path: Set(request.project_path),
             sonar_token: Set(String::new()), // Will be set after SonarQube token creation
-            sonar_host_url: Set(sonar_host_url), // Default SonarQube URL
+            sonar_host_url: Set(instance.url.clone()),
+            sonar_instance_id: Set(Some(instance.id)),
             language: Set(request.language),
             sources_path: Set(request.sources_path),
             tests_path: Set(request.tests_path),
@@ -109,7 +405,7 @@ impl ProjectService {
         };
 
         let result = project.insert(&self.db).await?;
-        Ok(ProjectResponse::from(result))
+        self.project_response(result)
     }
 
     pub async fn get_project_by_path(&self, project_path: &str) -> Result<Option<ProjectResponse>, DbErr> {
@@ -118,15 +414,26 @@ impl ProjectService {
             .one(&self.db)
             .await?;
 
-        Ok(project.map(ProjectResponse::from))
+        project.map(|project| self.project_response(project)).transpose()
+    }
+
+    pub async fn get_project_by_key(&self, project_key: &str) -> Result<Option<ProjectResponse>, DbErr> {
+        let project = ProjectEntity::find()
+            .filter(crate::database::entities::Column::ProjectKey.eq(project_key))
+            .one(&self.db)
+            .await?;
+
+        project.map(|project| self.project_response(project)).transpose()
     }
 
-    pub async fn update_sonar_token(&self, project_id: i32, token: String) -> Result<(), DbErr> {
+    pub async fn update_sonar_token(&self, project_id: i32, token: &GeneratedToken) -> Result<(), DbErr> {
         let project = ProjectEntity::find_by_id(project_id).one(&self.db).await?;
         
         if let Some(project) = project {
             let mut project: ProjectActiveModel = project.into();
-            project.sonar_token = Set(token);
+            project.sonar_token = Set(self.cipher.encrypt(&token.value)?);
+            project.sonar_token_name = Set(Some(token.name.clone()));
+            project.sonar_token_expiration_date = Set(token.expiration_date);
             project.updated_at = Set(Utc::now().naive_utc());
             project.update(&self.db).await?;
         }
@@ -134,9 +441,60 @@ impl ProjectService {
         Ok(())
     }
 
+    pub async fn update_project(&self, project_id: i32, request: &UpdateProjectRequest) -> Result<Option<ProjectResponse>, DbErr> {
+        let txn = self.db.begin().await?;
+        let Some(project) = ProjectEntity::find_by_id(project_id).one(&txn).await? else {
+            return Ok(None);
+        };
+
+        // Snapshots and webhook events refer to the project by key, they follow a rename
+        if let Some(project_key) = &request.new_project_key
+            && *project_key != project.project_key
+        {
+            SnapshotEntity::update_many()
+                .col_expr(crate::database::analysis_snapshot_entity::Column::ProjectKey, Expr::value(project_key.clone()))
+                .filter(crate::database::analysis_snapshot_entity::Column::ProjectKey.eq(project.project_key.as_str()))
+                .exec(&txn)
+                .await?;
+            WebhookEventEntity::update_many()
+                .col_expr(crate::database::webhook_event_entity::Column::ProjectKey, Expr::value(project_key.clone()))
+                .filter(crate::database::webhook_event_entity::Column::ProjectKey.eq(project.project_key.as_str()))
+                .exec(&txn)
+                .await?;
+        }
+
+        let mut project: ProjectActiveModel = project.into();
+        if let Some(project_key) = &request.new_project_key {
+            project.project_key = Set(project_key.clone());
+        }
+        if let Some(project_path) = &request.new_project_path {
+            project.project_path = Set(project_path.clone());
+        }
+        if let Some(project_name) = &request.project_name {
+            project.project_name = Set(project_name.clone());
+        }
+        if let Some(language) = &request.language {
+            project.language = Set(language.clone());
+        }
+        if let Some(sources_path) = &request.sources_path {
+            project.sources_path = Set(sources_path.clone());
+        }
+        if let Some(tests_path) = &request.tests_path {
+            project.tests_path = Set(tests_path.clone());
+        }
+        if let Some(coverage_report_path) = &request.coverage_report_path {
+            project.coverage_report_path = Set(Some(coverage_report_path.clone()));
+        }
+        project.updated_at = Set(Utc::now().naive_utc());
+
+        let result = project.update(&txn).await?;
+        txn.commit().await?;
+        self.project_response(result).map(Some)
+    }
+
     pub async fn get_all_projects(&self) -> Result<Vec<ProjectResponse>, DbErr> {
         let projects = ProjectEntity::find().all(&self.db).await?;
-        Ok(projects.into_iter().map(ProjectResponse::from).collect())
+        projects.into_iter().map(|project| self.project_response(project)).collect()
     }
 
     pub async fn create_admin_token(&self, request: CreateAdminTokenRequest) -> Result<AdminTokenResponse, DbErr> {
@@ -154,33 +512,110 @@ impl ProjectService {
             token_name: Set(request.token_name),
             token_value: Set(String::new()), // Will be set after SonarQube token creation
             token_type: Set(token_type),
-            sonar_host_url: Set(request.sonar_host_url),
+            sonar_host_url: Set(instance_url(&request.sonar_host_url)),
+            expiration_date: Set(request.expiration_date),
             created_at: Set(now),
             updated_at: Set(now),
             ..Default::default()
         };
 
         let result = admin_token.insert(&self.db).await?;
+        self.admin_token_response(result)
+    }
+
+    fn admin_token_response(&self, model: AdminTokenModel) -> Result<AdminTokenResponse, DbErr> {
         Ok(AdminTokenResponse {
-            id: result.id,
-            username: result.username,
-            token_name: result.token_name,
-            token_value: result.token_value,
-            token_type: result.token_type,
-            sonar_host_url: result.sonar_host_url,
-            created_at: result.created_at,
-            updated_at: result.updated_at,
+            id: model.id,
+            username: model.username,
+            token_name: model.token_name,
+            token_value: self.cipher.decrypt(&model.token_value)?,
+            token_type: model.token_type,
+            sonar_host_url: model.sonar_host_url,
+            expiration_date: model.expiration_date,
+            created_at: model.created_at,
+            updated_at: model.updated_at,
         })
     }
 
-    pub async fn get_admin_token_by_type(&self, sonar_host_url: &str, token_type: &str) -> Result<Option<String>, DbErr> {
+    // Latest token of this type generated for `username` on the instance at `sonar_host_url`
+    pub async fn find_admin_token(&self, sonar_host_url: &str, username: &str, token_type: &str) -> Result<Option<AdminTokenResponse>, DbErr> {
         let admin_token = AdminTokenEntity::find()
-            .filter(crate::database::admin_token_entity::Column::SonarHostUrl.eq(sonar_host_url))
+            .filter(crate::database::admin_token_entity::Column::SonarHostUrl.eq(instance_url(sonar_host_url)))
+            .filter(crate::database::admin_token_entity::Column::Username.eq(username))
             .filter(crate::database::admin_token_entity::Column::TokenType.eq(token_type))
+            .order_by_desc(crate::database::admin_token_entity::Column::Id)
             .one(&self.db)
             .await?;
 
-        Ok(admin_token.map(|token| token.token_value))
+        admin_token.map(|admin_token| self.admin_token_response(admin_token)).transpose()
+    }
+
+    pub async fn get_admin_token(&self, token_id: i32) -> Result<Option<AdminTokenResponse>, DbErr> {
+        let admin_token = AdminTokenEntity::find_by_id(token_id).one(&self.db).await?;
+        admin_token.map(|token| self.admin_token_response(token)).transpose()
+    }
+
+    // Rotation keeps the row, so an admin token has one row however often it is rotated
+    pub async fn replace_admin_token(&self, token_id: i32, token: &GeneratedToken) -> Result<Option<AdminTokenResponse>, DbErr> {
+        let Some(admin_token) = AdminTokenEntity::find_by_id(token_id).one(&self.db).await? else {
+            return Ok(None);
+        };
+
+        let mut admin_token: AdminTokenActiveModel = admin_token.into();
+        admin_token.token_name = Set(token.name.clone());
+        admin_token.token_value = Set(self.cipher.encrypt(&token.value)?);
+        admin_token.expiration_date = Set(token.expiration_date);
+        admin_token.updated_at = Set(Utc::now().naive_utc());
+
+        let result = admin_token.update(&self.db).await?;
+        self.admin_token_response(result).map(Some)
+    }
+
+    // Project and admin tokens expiring on or before `until`, soonest first. Tokens without
+    // an expiration date never show up.
+    pub async fn get_expiring_tokens(&self, until: chrono::NaiveDate) -> Result<Vec<ExpiringTokenResponse>, DbErr> {
+        let today = Utc::now().date_naive();
+        let projects = ProjectEntity::find()
+            .filter(crate::database::entities::Column::SonarTokenExpirationDate.lte(until))
+            .all(&self.db)
+            .await?;
+        let admin_tokens = AdminTokenEntity::find()
+            .filter(crate::database::admin_token_entity::Column::ExpirationDate.lte(until))
+            .all(&self.db)
+            .await?;
+
+        let mut tokens: Vec<ExpiringTokenResponse> = projects
+            .into_iter()
+            .filter_map(|project| {
+                let expiration_date = project.sonar_token_expiration_date?;
+                Some(ExpiringTokenResponse {
+                    kind: "project".to_string(),
+                    id: project.id,
+                    token_name: project.sonar_token_name,
+                    token_type: "PROJECT_ANALYSIS_TOKEN".to_string(),
+                    owner: project.project_path,
+                    sonar_host_url: project.sonar_host_url,
+                    expiration_date,
+                    expired: expiration_date <= today,
+                })
+            })
+            .collect();
+        tokens.extend(admin_tokens.into_iter().filter_map(|admin_token| {
+            let expiration_date = admin_token.expiration_date?;
+            Some(ExpiringTokenResponse {
+                kind: "admin".to_string(),
+                id: admin_token.id,
+                token_name: Some(admin_token.token_name),
+                token_type: admin_token.token_type,
+                owner: admin_token.username,
+                sonar_host_url: admin_token.sonar_host_url,
+                expiration_date,
+                expired: expiration_date <= today,
+            })
+        }));
+        tokens.sort_by_key(|token| token.expiration_date);
+
+        Ok(tokens)
     }
 
     pub async fn update_admin_token_value(&self, token_id: i32, token_value: String) -> Result<(), DbErr> {
@@ -188,7 +623,7 @@ impl ProjectService {
         
         if let Some(admin_token) = admin_token {
             let mut admin_token: AdminTokenActiveModel = admin_token.into();
-            admin_token.token_value = Set(token_value);
+            admin_token.token_value = Set(self.cipher.encrypt(&token_value)?);
             admin_token.updated_at = Set(Utc::now().naive_utc());
             admin_token.update(&self.db).await?;
         }
@@ -196,6 +631,262 @@ impl ProjectService {
         Ok(())
     }
 
+    fn sonar_instance(&self, model: SonarInstanceModel) -> Result<SonarInstance, DbErr> {
+        let decrypt = |token: Option<String>| -> Result<Option<String>, DbErr> {
+            Ok(token.map(|token| self.cipher.decrypt(&token)).transpose()?)
+        };
+        Ok(SonarInstance {
+            id: model.id,
+            name: model.name,
+            url: model.url,
+            admin_token: decrypt(model.admin_token)?,
+            analysis_token: decrypt(model.analysis_token)?,
+        })
+    }
+
+    async fn sonar_instance_response(&self, model: SonarInstanceModel) -> Result<SonarInstanceResponse, DbErr> {
+        let project_count = ProjectEntity::find()
+            .filter(crate::database::entities::Column::SonarInstanceId.eq(model.id))
+            .count(&self.db)
+            .await?;
+
+        Ok(SonarInstanceResponse {
+            id: model.id,
+            name: model.name,
+            url: model.url,
+            has_admin_token: model.admin_token.is_some(),
+            has_analysis_token: model.analysis_token.is_some(),
+            is_default: model.is_default,
+            project_count,
+            created_at: model.created_at,
+            updated_at: model.updated_at,
+        })
+    }
+
+    // Empty tokens are stored as NULL
+    fn encrypt_instance_token(&self, token: &str) -> Result<Option<String>, DbErr> {
+        let token = token.trim();
+        if token.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(self.cipher.encrypt(token)?))
+    }
+
+    pub async fn get_sonar_instances(&self) -> Result<Vec<SonarInstanceResponse>, DbErr> {
+        let instances = SonarInstanceEntity::find()
+            .order_by_asc(crate::database::sonar_instance_entity::Column::Id)
+            .all(&self.db)
+            .await?;
+
+        let mut responses = Vec::with_capacity(instances.len());
+        for instance in instances {
+            responses.push(self.sonar_instance_response(instance).await?);
+        }
+        Ok(responses)
+    }
+
+    pub async fn get_sonar_instance_details(&self, instance_id: i32) -> Result<Option<SonarInstanceResponse>, DbErr> {
+        match SonarInstanceEntity::find_by_id(instance_id).one(&self.db).await? {
+            Some(instance) => self.sonar_instance_response(instance).await.map(Some),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn get_sonar_instance(&self, instance_id: i32) -> Result<Option<SonarInstance>, DbErr> {
+        let instance = SonarInstanceEntity::find_by_id(instance_id).one(&self.db).await?;
+        instance.map(|instance| self.sonar_instance(instance)).transpose()
+    }
+
+    pub async fn get_default_sonar_instance(&self) -> Result<Option<SonarInstance>, DbErr> {
+        let instance = SonarInstanceEntity::find()
+            .filter(crate::database::sonar_instance_entity::Column::IsDefault.eq(true))
+            .one(&self.db)
+            .await?;
+        instance.map(|instance| self.sonar_instance(instance)).transpose()
+    }
+
+    pub async fn get_sonar_instance_by_url(&self, url: &str) -> Result<Option<SonarInstance>, DbErr> {
+        let instance = SonarInstanceEntity::find()
+            .filter(crate::database::sonar_instance_entity::Column::Url.eq(instance_url(url)))
+            .one(&self.db)
+            .await?;
+        instance.map(|instance| self.sonar_instance(instance)).transpose()
+    }
+
+    // The first instance registered becomes the default
+    pub async fn create_sonar_instance(&self, request: CreateSonarInstanceRequest) -> Result<SonarInstanceResponse, DbErr> {
+        let now = Utc::now().naive_utc();
+        let txn = self.db.begin().await?;
+
+        let has_default = SonarInstanceEntity::find()
+            .filter(crate::database::sonar_instance_entity::Column::IsDefault.eq(true))
+            .count(&txn)
+            .await?
+            > 0;
+        let is_default = request.is_default.unwrap_or(false) || !has_default;
+        if is_default {
+            clear_default_sonar_instance(&txn).await?;
+        }
+
+        let instance = SonarInstanceActiveModel {
+            name: Set(request.name.trim().to_string()),
+            url: Set(instance_url(&request.url)),
+            admin_token: Set(self.encrypt_instance_token(request.admin_token.as_deref().unwrap_or_default())?),
+            analysis_token: Set(self.encrypt_instance_token(request.analysis_token.as_deref().unwrap_or_default())?),
+            is_default: Set(is_default),
+            created_at: Set(now),
+            updated_at: Set(now),
+            ..Default::default()
+        };
+        let result = instance.insert(&txn).await?;
+        txn.commit().await?;
+
+        self.sonar_instance_response(result).await
+    }
+
+    // Projects of the instance follow a URL change, their sonar_host_url is what scanner commands use
+    pub async fn update_sonar_instance(&self, request: &UpdateSonarInstanceRequest) -> Result<Option<SonarInstanceResponse>, DbErr> {
+        let txn = self.db.begin().await?;
+        let Some(instance) = SonarInstanceEntity::find_by_id(request.id).one(&txn).await? else {
+            return Ok(None);
+        };
+
+        let mut instance: SonarInstanceActiveModel = instance.into();
+        if let Some(name) = &request.name {
+            instance.name = Set(name.trim().to_string());
+        }
+        if let Some(url) = &request.url {
+            let url = instance_url(url);
+            ProjectEntity::update_many()
+                .col_expr(crate::database::entities::Column::SonarHostUrl, Expr::value(url.clone()))
+                .filter(crate::database::entities::Column::SonarInstanceId.eq(request.id))
+                .exec(&txn)
+                .await?;
+            instance.url = Set(url);
+        }
+        if let Some(admin_token) = &request.admin_token {
+            instance.admin_token = Set(self.encrypt_instance_token(admin_token)?);
+        }
+        if let Some(analysis_token) = &request.analysis_token {
+            instance.analysis_token = Set(self.encrypt_instance_token(analysis_token)?);
+        }
+        if request.is_default == Some(true) {
+            clear_default_sonar_instance(&txn).await?;
+            instance.is_default = Set(true);
+        }
+        instance.updated_at = Set(Utc::now().naive_utc());
+
+        let result = instance.update(&txn).await?;
+        txn.commit().await?;
+
+        self.sonar_instance_response(result).await.map(Some)
+    }
+
+    // Projects reference their instance, so this fails while any is linked to it
+    pub async fn delete_sonar_instance(&self, instance_id: i32) -> Result<bool, DbErr> {
+        let result = SonarInstanceEntity::delete_by_id(instance_id).exec(&self.db).await?;
+        Ok(result.rows_affected > 0)
+    }
+
+    // Keeps a token generated through /api/admin-token on the instance at `url`, registering
+    // the instance when it is not known yet
+    pub async fn store_sonar_instance_token(&self, url: &str, token_type: &str, token: &str) -> Result<(), DbErr> {
+        let url = instance_url(url);
+        let token = self.encrypt_instance_token(token)?;
+        let now = Utc::now().naive_utc();
+        let txn = self.db.begin().await?;
+
+        let existing = SonarInstanceEntity::find()
+            .filter(crate::database::sonar_instance_entity::Column::Url.eq(url.as_str()))
+            .one(&txn)
+            .await?;
+        let mut instance: SonarInstanceActiveModel = match existing {
+            Some(instance) => instance.into(),
+            None => {
+                let has_default = SonarInstanceEntity::find()
+                    .filter(crate::database::sonar_instance_entity::Column::IsDefault.eq(true))
+                    .count(&txn)
+                    .await?
+                    > 0;
+                SonarInstanceActiveModel {
+                    name: Set(url.clone()),
+                    url: Set(url.clone()),
+                    admin_token: Set(None),
+                    analysis_token: Set(None),
+                    is_default: Set(!has_default),
+                    created_at: Set(now),
+                    ..Default::default()
+                }
+            }
+        };
+        if token_type == "GLOBAL_ANALYSIS_TOKEN" {
+            instance.analysis_token = Set(token);
+        } else {
+            instance.admin_token = Set(token);
+        }
+        instance.updated_at = Set(now);
+        instance.save(&txn).await?;
+
+        txn.commit().await?;
+        Ok(())
+    }
+
+    // Called at startup: marks the instance at `url` as the default when no instance is, registering
+    // it if needed, and links projects created before instances existed to the default instance.
+    // Returns the default instance and how many projects were linked.
+    pub async fn ensure_default_sonar_instance(&self, url: &str) -> Result<(SonarInstance, u64), DbErr> {
+        let url = instance_url(url);
+        let now = Utc::now().naive_utc();
+        let txn = self.db.begin().await?;
+
+        let default = SonarInstanceEntity::find()
+            .filter(crate::database::sonar_instance_entity::Column::IsDefault.eq(true))
+            .one(&txn)
+            .await?;
+        let default = match default {
+            Some(instance) => instance,
+            None => {
+                let existing = SonarInstanceEntity::find()
+                    .filter(crate::database::sonar_instance_entity::Column::Url.eq(url.as_str()))
+                    .one(&txn)
+                    .await?;
+                match existing {
+                    Some(instance) => {
+                        let mut instance: SonarInstanceActiveModel = instance.into();
+                        instance.is_default = Set(true);
+                        instance.updated_at = Set(now);
+                        instance.update(&txn).await?
+                    }
+                    None => {
+                        SonarInstanceActiveModel {
+                            name: Set(url.clone()),
+                            url: Set(url.clone()),
+                            admin_token: Set(None),
+                            analysis_token: Set(None),
+                            is_default: Set(true),
+                            created_at: Set(now),
+                            updated_at: Set(now),
+                            ..Default::default()
+                        }
+                        .insert(&txn)
+                        .await?
+                    }
+                }
+            }
+        };
+
+        let linked = ProjectEntity::update_many()
+            .col_expr(crate::database::entities::Column::SonarInstanceId, Expr::value(default.id))
+            .col_expr(crate::database::entities::Column::SonarHostUrl, Expr::value(default.url.clone()))
+            .filter(crate::database::entities::Column::SonarInstanceId.is_null())
+            .exec(&txn)
+            .await?
+            .rows_affected;
+
+        txn.commit().await?;
+        Ok((self.sonar_instance(default)?, linked))
+    }
+
     pub async fn delete_project_by_path(&self, project_path: &str) -> Result<Option<ProjectResponse>, DbErr> {
         let project = ProjectEntity::find()
             .filter(crate::database::entities::Column::ProjectPath.eq(project_path))
@@ -203,7 +894,7 @@ impl ProjectService {
             .await?;
 
         if let Some(project) = project {
-            let project_response = ProjectResponse::from(project.clone());
+            let project_response = self.project_response(project.clone())?;
             let project_id = project.id;
             ProjectEntity::delete_by_id(project_id).exec(&self.db).await?;
             Ok(Some(project_response))
@@ -211,4 +902,342 @@ impl ProjectService {
             Ok(None)
         }
     }
+
+    // Returns the cached rules among `rule_keys` that were fetched after `fresh_since`
+    pub async fn get_cached_rules(&self, sonar_host_url: &str, rule_keys: &[String], fresh_since: chrono::NaiveDateTime) -> Result<Vec<RuleDetails>, DbErr> {
+        let rules = RuleCacheEntity::find()
+            .filter(crate::database::rule_cache_entity::Column::SonarHostUrl.eq(sonar_host_url))
+            .filter(crate::database::rule_cache_entity::Column::RuleKey.is_in(rule_keys.iter().cloned()))
+            .filter(crate::database::rule_cache_entity::Column::FetchedAt.gt(fresh_since))
+            .all(&self.db)
+            .await?;
+
+        Ok(rules.into_iter().map(RuleDetails::from).collect())
+    }
+
+    pub async fn upsert_cached_rules(&self, sonar_host_url: &str, rules: &[RuleDetails]) -> Result<(), DbErr> {
+        let now = Utc::now().naive_utc();
+
+        for chunk in rules.chunks(500) {
+            let models = chunk.iter().map(|rule| RuleCacheActiveModel {
+                sonar_host_url: Set(sonar_host_url.to_string()),
+                rule_key: Set(rule.key.clone()),
+                name: Set(rule.name.clone()),
+                description: Set(rule.description.clone()),
+                rule_type: Set(rule.rule_type.clone()),
+                severity: Set(rule.severity.clone()),
+                tags: Set(rule.tags.join(",")),
+                remediation_function: Set(rule.remediation_function.clone()),
+                remediation_effort: Set(rule.remediation_effort.clone()),
+                remediation_gap_multiplier: Set(rule.remediation_gap_multiplier.clone()),
+                fetched_at: Set(now),
+                ..Default::default()
+            });
+
+            RuleCacheEntity::insert_many(models)
+                .on_conflict(
+                    OnConflict::columns([
+                        crate::database::rule_cache_entity::Column::SonarHostUrl,
+                        crate::database::rule_cache_entity::Column::RuleKey,
+                    ])
+                    .update_columns([
+                        crate::database::rule_cache_entity::Column::Name,
+                        crate::database::rule_cache_entity::Column::Description,
+                        crate::database::rule_cache_entity::Column::RuleType,
+                        crate::database::rule_cache_entity::Column::Severity,
+                        crate::database::rule_cache_entity::Column::Tags,
+                        crate::database::rule_cache_entity::Column::RemediationFunction,
+                        crate::database::rule_cache_entity::Column::RemediationEffort,
+                        crate::database::rule_cache_entity::Column::RemediationGapMultiplier,
+                        crate::database::rule_cache_entity::Column::FetchedAt,
+                    ])
+                    .to_owned(),
+                )
+                .exec(&self.db)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn record_webhook_event(&self, payload: &WebhookPayload, analysis_id: Option<String>, raw_payload: String) -> Result<WebhookEventResponse, DbErr> {
+        let analysed_at = payload
+            .analysed_at
+            .as_deref()
+            .and_then(|date| SonarQubeClient::parse_sonar_date(date).ok())
+            .map(|date| date.naive_utc());
+
+        let event = WebhookEventActiveModel {
+            project_key: Set(payload.project.key.clone()),
+            branch: Set(payload.branch.as_ref().map(|branch| branch.name.clone())),
+            branch_type: Set(payload.branch.as_ref().and_then(|branch| branch.branch_type.clone())),
+            task_id: Set(payload.task_id.clone()),
+            analysis_id: Set(analysis_id),
+            status: Set(payload.status.clone()),
+            quality_gate_status: Set(payload.quality_gate.as_ref().and_then(|gate| gate.status.clone())),
+            analysed_at: Set(analysed_at),
+            payload: Set(raw_payload),
+            received_at: Set(Utc::now().naive_utc()),
+            ..Default::default()
+        };
+
+        let result = event.insert(&self.db).await?;
+        Ok(WebhookEventResponse::from(result))
+    }
+
+    // Most recent first
+    pub async fn get_webhook_events(&self, project_key: &str, limit: u64) -> Result<Vec<WebhookEventResponse>, DbErr> {
+        let events = WebhookEventEntity::find()
+            .filter(crate::database::webhook_event_entity::Column::ProjectKey.eq(project_key))
+            .order_by_desc(crate::database::webhook_event_entity::Column::ReceivedAt)
+            .limit(limit)
+            .all(&self.db)
+            .await?;
+
+        Ok(events.into_iter().map(WebhookEventResponse::from).collect())
+    }
+
+    // Stores the snapshot and its normalized rows in one transaction
+    pub async fn save_snapshot(&self, snapshot: NewSnapshot<'_>) -> Result<SnapshotResponse, DbErr> {
+        let txn = self.db.begin().await?;
+
+        let stored = SnapshotActiveModel {
+            project_key: Set(snapshot.project_key.to_string()),
+            branch: Set(snapshot.target.branch.clone()),
+            pull_request: Set(snapshot.target.pull_request.clone()),
+            source: Set(snapshot.source.to_string()),
+            analysis_id: Set(snapshot.analysis_id),
+            quality_gate_status: Set(snapshot.quality_gate.map(|gate| gate.status.clone())),
+            issue_count: Set(snapshot.issues.map(|issues| issues.len() as i32)),
+            results: Set(snapshot.results.to_string()),
+            created_at: Set(Utc::now().naive_utc()),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+
+        let conditions: Vec<SnapshotConditionActiveModel> = snapshot
+            .quality_gate
+            .map(|gate| gate.conditions.as_slice())
+            .unwrap_or_default()
+            .iter()
+            .map(|condition| SnapshotConditionActiveModel {
+                snapshot_id: Set(stored.id),
+                metric_key: Set(condition.metric_key.clone()),
+                comparator: Set(condition.comparator.clone()),
+                error_threshold: Set(condition.error_threshold.clone()),
+                actual_value: Set(condition.actual_value.clone()),
+                status: Set(condition.status.clone()),
+                ..Default::default()
+            })
+            .collect();
+        if !conditions.is_empty() {
+            SnapshotConditionEntity::insert_many(conditions).exec(&txn).await?;
+        }
+
+        let measures: Vec<SnapshotMeasureActiveModel> = snapshot
+            .measures
+            .iter()
+            .map(|measure| SnapshotMeasureActiveModel {
+                snapshot_id: Set(stored.id),
+                metric: Set(measure.metric.clone()),
+                value: Set(measure.value.clone()),
+                ..Default::default()
+            })
+            .collect();
+        if !measures.is_empty() {
+            SnapshotMeasureEntity::insert_many(measures).exec(&txn).await?;
+        }
+
+        for batch in snapshot.issues.unwrap_or_default().chunks(SNAPSHOT_INSERT_BATCH) {
+            let issues = batch.iter().map(|issue| SnapshotIssueActiveModel {
+                snapshot_id: Set(stored.id),
+                issue_key: Set(issue.key.clone()),
+                rule: Set(issue.rule.clone()),
+                severity: Set(issue.severity.clone()),
+                issue_type: Set(issue.issue_type.clone()),
+                status: Set(issue.status.clone()),
+                component: Set(issue.component.clone()),
+                line: Set(issue.line),
+                message: Set(issue.message.clone()),
+                effort: Set(issue.effort.clone()),
+                author: Set(issue.author.clone()),
+                tags: Set(issue.tags.join(",")),
+                created_at: Set(SonarQubeClient::parse_sonar_date(&issue.creation_date).ok().map(|date| date.naive_utc())),
+                ..Default::default()
+            });
+            SnapshotIssueEntity::insert_many(issues).exec(&txn).await?;
+        }
+
+        txn.commit().await?;
+        Ok(SnapshotResponse::from(stored))
+    }
+
+    // Deletes all but the `keep` most recent snapshots of the project taken from `source`, with their rows
+    pub async fn prune_snapshots(&self, project_key: &str, source: &str, keep: u64) -> Result<u64, DbErr> {
+        use crate::database::analysis_snapshot_entity::Column;
+
+        let stale: Vec<i32> = SnapshotEntity::find()
+            .select_only()
+            .column(Column::Id)
+            .filter(Column::ProjectKey.eq(project_key))
+            .filter(Column::Source.eq(source))
+            .order_by_desc(Column::CreatedAt)
+            .order_by_desc(Column::Id)
+            .offset(keep)
+            .into_tuple()
+            .all(&self.db)
+            .await?;
+        if stale.is_empty() {
+            return Ok(0);
+        }
+
+        let result = SnapshotEntity::delete_many()
+            .filter(Column::Id.is_in(stale))
+            .exec(&self.db)
+            .await?;
+        Ok(result.rows_affected)
+    }
+
+    // Most recent first. Without a branch or pull request, snapshots of every branch are listed
+    pub async fn get_snapshots(&self, project_key: &str, target: &AnalysisTarget, limit: u64) -> Result<Vec<SnapshotResponse>, DbErr> {
+        use crate::database::analysis_snapshot_entity::Column;
+
+        let mut query = SnapshotEntity::find().filter(Column::ProjectKey.eq(project_key));
+        if let Some(branch) = &target.branch {
+            query = query.filter(Column::Branch.eq(branch.as_str()));
+        }
+        if let Some(pull_request) = &target.pull_request {
+            query = query.filter(Column::PullRequest.eq(pull_request.as_str()));
+        }
+
+        let snapshots = query
+            .order_by_desc(Column::CreatedAt)
+            .limit(limit)
+            .all(&self.db)
+            .await?;
+
+        Ok(snapshots.into_iter().map(SnapshotResponse::from).collect())
+    }
+
+    pub async fn get_snapshot(&self, snapshot_id: i32) -> Result<Option<SnapshotDetails>, DbErr> {
+        let Some(snapshot) = SnapshotEntity::find_by_id(snapshot_id).one(&self.db).await? else {
+            return Ok(None);
+        };
+
+        let conditions = SnapshotConditionEntity::find()
+            .filter(crate::database::snapshot_condition_entity::Column::SnapshotId.eq(snapshot_id))
+            .order_by_asc(crate::database::snapshot_condition_entity::Column::Id)
+            .all(&self.db)
+            .await?;
+        let measures = SnapshotMeasureEntity::find()
+            .filter(crate::database::snapshot_measure_entity::Column::SnapshotId.eq(snapshot_id))
+            .order_by_asc(crate::database::snapshot_measure_entity::Column::Id)
+            .all(&self.db)
+            .await?;
+
+        let results = serde_json::from_str(&snapshot.results).unwrap_or(serde_json::Value::Null);
+        Ok(Some(SnapshotDetails {
+            snapshot: SnapshotResponse::from(snapshot),
+            conditions,
+            measures,
+            results,
+        }))
+    }
+
+    pub async fn get_snapshot_issues(&self, snapshot_id: i32, filter: &SnapshotIssueFilter) -> Result<Vec<SnapshotIssueModel>, DbErr> {
+        use crate::database::snapshot_issue_entity::Column;
+
+        let mut query = SnapshotIssueEntity::find().filter(Column::SnapshotId.eq(snapshot_id));
+        if let Some(severity) = &filter.severity {
+            query = query.filter(Column::Severity.eq(severity.as_str()));
+        }
+        if let Some(issue_type) = &filter.issue_type {
+            query = query.filter(Column::IssueType.eq(issue_type.as_str()));
+        }
+        if let Some(rule) = &filter.rule {
+            query = query.filter(Column::Rule.eq(rule.as_str()));
+        }
+
+        query.order_by_asc(Column::Id).all(&self.db).await
+    }
+
+    // Number of stored tokens per encryption key id, "plaintext" for unencrypted ones
+    pub async fn token_key_usage(&self) -> Result<BTreeMap<String, usize>, DbErr> {
+        let mut usage = BTreeMap::new();
+        let projects = ProjectEntity::find().all(&self.db).await?;
+        let admin_tokens = AdminTokenEntity::find().all(&self.db).await?;
+        let instances = SonarInstanceEntity::find().all(&self.db).await?;
+        let values = projects.iter().map(|project| &project.sonar_token)
+            .chain(admin_tokens.iter().map(|token| &token.token_value))
+            .chain(instances.iter().flat_map(|instance| instance.admin_token.iter().chain(instance.analysis_token.iter())));
+        for value in values {
+            let key_id = TokenCipher::key_id(value).unwrap_or("plaintext");
+            *usage.entry(key_id.to_string()).or_insert(0) += 1;
+        }
+        Ok(usage)
+    }
+
+    // Encrypts tokens still stored in plaintext, returns how many rows changed
+    pub async fn encrypt_plaintext_tokens(&self) -> Result<usize, DbErr> {
+        self.rewrite_tokens(true).await
+    }
+
+    // Moves every token under the active key, returns how many rows changed
+    pub async fn reencrypt_tokens(&self) -> Result<usize, DbErr> {
+        self.rewrite_tokens(false).await
+    }
+
+    // One transaction, so a value that cannot be decrypted leaves every row as it was.
+    // updated_at is left alone, the token itself does not change.
+    async fn rewrite_tokens(&self, plaintext_only: bool) -> Result<usize, DbErr> {
+        if !self.cipher.is_enabled() {
+            return Err(DbErr::Custom("TOKEN_ENCRYPTION_KEYS is not set".to_string()));
+        }
+        let rewrite = |value: &str| -> Result<Option<String>, DbErr> {
+            if plaintext_only && TokenCipher::is_encrypted(value) {
+                return Ok(None);
+            }
+            Ok(self.cipher.rewrap(value)?)
+        };
+
+        let txn = self.db.begin().await?;
+        let mut changed = 0;
+
+        for project in ProjectEntity::find().all(&txn).await? {
+            if let Some(sonar_token) = rewrite(&project.sonar_token)? {
+                let mut project: ProjectActiveModel = project.into();
+                project.sonar_token = Set(sonar_token);
+                project.update(&txn).await?;
+                changed += 1;
+            }
+        }
+        for admin_token in AdminTokenEntity::find().all(&txn).await? {
+            if let Some(token_value) = rewrite(&admin_token.token_value)? {
+                let mut admin_token: AdminTokenActiveModel = admin_token.into();
+                admin_token.token_value = Set(token_value);
+                admin_token.update(&txn).await?;
+                changed += 1;
+            }
+        }
+        for instance in SonarInstanceEntity::find().all(&txn).await? {
+            let admin_token = instance.admin_token.as_deref().map(rewrite).transpose()?.flatten();
+            let analysis_token = instance.analysis_token.as_deref().map(rewrite).transpose()?.flatten();
+            if admin_token.is_none() && analysis_token.is_none() {
+                continue;
+            }
+            let mut instance: SonarInstanceActiveModel = instance.into();
+            if let Some(admin_token) = admin_token {
+                instance.admin_token = Set(Some(admin_token));
+            }
+            if let Some(analysis_token) = analysis_token {
+                instance.analysis_token = Set(Some(analysis_token));
+            }
+            instance.update(&txn).await?;
+            changed += 1;
+        }
+
+        txn.commit().await?;
+        Ok(changed)
+    }
 }
@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "snapshot_conditions")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub snapshot_id: i32,
+    pub metric_key: String,
+    pub comparator: String,
+    pub error_threshold: Option<String>,
+    pub actual_value: Option<String>,
+    pub status: String,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,28 @@
+use serde::{Deserialize, Serialize};
+use sea_orm::entity::prelude::*;
+use chrono::NaiveDateTime;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "snapshot_issues")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub snapshot_id: i32,
+    pub issue_key: String,
+    pub rule: String,
+    pub severity: String,
+    pub issue_type: String,
+    pub status: String,
+    pub component: String,
+    pub line: Option<i32>,
+    pub message: String,
+    pub effort: Option<String>,
+    pub author: Option<String>,
+    pub tags: String, // comma separated
+    pub created_at: Option<NaiveDateTime>, // issue creation date in SonarQube
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+use sea_orm::entity::prelude::*;
+use chrono::NaiveDateTime;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "webhook_events")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub project_key: String,
+    pub branch: Option<String>,
+    pub branch_type: Option<String>, // BRANCH or PULL_REQUEST
+    pub task_id: Option<String>,
+    pub analysis_id: Option<String>,
+    pub status: Option<String>,
+    pub quality_gate_status: Option<String>,
+    pub analysed_at: Option<NaiveDateTime>,
+    pub payload: String, // raw JSON body
+    pub received_at: NaiveDateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -5,5 +5,10 @@ mod sonarqube;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    web::server::start().await
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("migrate") => web::server::migrate(&args[1..]).await,
+        Some("tokens") => web::server::tokens(&args[1..]).await,
+        _ => web::server::start().await,
+    }
 }
@@ -1,8 +1,62 @@
-use reqwest::Client;
+use reqwest::{Client, Method, Request, RequestBuilder, Response, StatusCode};
 use serde::{Deserialize, Serialize};
-use anyhow::Result;
 use base64::{Engine as _, engine::general_purpose};
-use tracing::info;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use rand::Rng;
+use std::collections::HashSet;
+use std::time::SystemTime;
+use tracing::{info, warn};
+use crate::config::sonar::SonarClientConfig;
+use crate::sonarqube::error::{Result, SonarQubeError};
+use crate::sonarqube::snippets::CodeSnippet;
+
+// Largest page size accepted by /api/issues/search
+const ISSUES_PAGE_SIZE: i32 = 500;
+// SonarQube refuses to page past the first 10,000 results of a single search
+const ISSUES_SEARCH_LIMIT: i32 = 10_000;
+pub const ISSUE_TYPES: [&str; 3] = ["BUG", "VULNERABILITY", "CODE_SMELL"];
+pub const ISSUE_SEVERITIES: [&str; 5] = ["INFO", "MINOR", "MAJOR", "CRITICAL", "BLOCKER"];
+// Issue keys sent per /api/issues/search call when looking issues up by key
+const ISSUE_KEYS_PER_SEARCH: usize = 100;
+// Most issues /api/issues/bulk_change accepts in one call
+pub const ISSUE_BULK_CHANGE_LIMIT: usize = 500;
+// Largest page size accepted by /api/hotspots/search
+const HOTSPOTS_PAGE_SIZE: i32 = 500;
+// Largest page size accepted by /api/measures/search_history
+const HISTORY_PAGE_SIZE: i32 = 1000;
+// Largest page size accepted by /api/rules/search
+const RULES_PAGE_SIZE: i32 = 500;
+// Largest page size accepted by /api/permissions/users and /api/permissions/groups
+const PERMISSIONS_PAGE_SIZE: i32 = 100;
+// Largest page size accepted by /api/projects/search
+const PROJECTS_PAGE_SIZE: i32 = 500;
+const SONAR_DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%z";
+// Format of the expirationDate parameter of /api/user_tokens/generate
+const TOKEN_EXPIRATION_FORMAT: &str = "%Y-%m-%d";
+
+type IssueFilters = Vec<(&'static str, String)>;
+
+// Branch or pull request to read, the main branch when neither is set
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AnalysisTarget {
+    pub branch: Option<String>,
+    pub pull_request: Option<String>,
+}
+
+impl AnalysisTarget {
+    pub fn is_valid(&self) -> bool {
+        self.branch.is_none() || self.pull_request.is_none()
+    }
+
+    // Query parameters understood by the issues, measures, quality gate, hotspots and sources APIs
+    pub fn params(&self) -> Vec<(&'static str, String)> {
+        match (&self.branch, &self.pull_request) {
+            (_, Some(pull_request)) => vec![("pullRequest", pull_request.clone())],
+            (Some(branch), None) => vec![("branch", branch.clone())],
+            (None, None) => Vec::new(),
+        }
+    }
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TokenResponse {
@@ -14,6 +68,9 @@ pub struct TokenResponse {
 pub struct ProjectIssuesResponse {
     pub issues: Vec<Issue>,
     pub paging: Paging,
+    // False when the search cap kept some issues out of the list
+    #[serde(default)]
+    pub complete: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -24,6 +81,8 @@ pub struct Issue {
     pub component: String,
     pub project: String,
     pub line: Option<i32>,
+    #[serde(rename = "textRange")]
+    pub text_range: Option<TextRange>,
     pub message: String,
     pub effort: Option<String>,
     pub debt: Option<String>,
@@ -31,6 +90,9 @@ pub struct Issue {
     pub resolution: Option<String>,
     pub hash: Option<String>,
     pub author: Option<String>,
+    pub assignee: Option<String>,
+    #[serde(default)]
+    pub comments: Vec<IssueComment>,
     #[serde(rename = "creationDate")]
     pub creation_date: String,
     #[serde(rename = "updateDate")]
@@ -38,6 +100,101 @@ pub struct Issue {
     pub tags: Vec<String>,
     #[serde(rename = "type")]
     pub issue_type: String,
+    // Workflow actions the token may apply, e.g. "confirm", "resolve", "falsepositive"
+    #[serde(default)]
+    pub transitions: Vec<String>,
+    // Filled from the rule cache, not part of SonarQube's payload
+    #[serde(rename = "ruleDetails", default, skip_serializing_if = "Option::is_none")]
+    pub rule_details: Option<RuleDetails>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<CodeSnippet>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssueComment {
+    pub key: String,
+    pub login: Option<String>,
+    pub markdown: Option<String>,
+    #[serde(rename = "htmlText")]
+    pub html_text: Option<String>,
+    #[serde(default)]
+    pub updatable: bool,
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<String>,
+}
+
+// Body of the issue write APIs, which echo the changed issue
+#[derive(Debug, Deserialize)]
+pub struct IssueChangeResponse {
+    pub issue: Issue,
+}
+
+// Actions applied by /api/issues/bulk_change, unset fields are left untouched
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BulkChange {
+    // SonarQube login, or an empty string to unassign
+    pub assign: Option<String>,
+    pub transition: Option<String>,
+    #[serde(default)]
+    pub add_tags: Vec<String>,
+    #[serde(default)]
+    pub remove_tags: Vec<String>,
+    pub set_severity: Option<String>,
+    pub set_type: Option<String>,
+    pub comment: Option<String>,
+}
+
+impl BulkChange {
+    pub fn is_empty(&self) -> bool {
+        self.params().is_empty()
+    }
+
+    fn params(&self) -> Vec<(&'static str, String)> {
+        let mut params = Vec::new();
+        if let Some(assign) = &self.assign {
+            params.push(("assign", assign.clone()));
+        }
+        if let Some(transition) = &self.transition {
+            params.push(("do_transition", transition.clone()));
+        }
+        if !self.add_tags.is_empty() {
+            params.push(("add_tags", self.add_tags.join(",")));
+        }
+        if !self.remove_tags.is_empty() {
+            params.push(("remove_tags", self.remove_tags.join(",")));
+        }
+        if let Some(severity) = &self.set_severity {
+            params.push(("set_severity", severity.clone()));
+        }
+        if let Some(issue_type) = &self.set_type {
+            params.push(("set_type", issue_type.clone()));
+        }
+        if let Some(comment) = &self.comment {
+            params.push(("comment", comment.clone()));
+        }
+        params
+    }
+}
+
+// Counts only, SonarQube does not say which issues were ignored or failed
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BulkChangeResponse {
+    pub total: i32,
+    pub success: i32,
+    pub ignored: i32,
+    pub failures: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TextRange {
+    #[serde(rename = "startLine")]
+    pub start_line: i32,
+    #[serde(rename = "endLine")]
+    pub end_line: i32,
+    #[serde(rename = "startOffset")]
+    pub start_offset: Option<i32>,
+    #[serde(rename = "endOffset")]
+    pub end_offset: Option<i32>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -49,6 +206,7 @@ pub struct Paging {
     pub total: i32,
 }
 
+// Answer of /api/measures/component, whatever metrics were asked
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CoverageResponse {
     pub component: Component,
@@ -60,6 +218,20 @@ pub struct QualityGateResponse {
     pub project_status: ProjectStatus,
 }
 
+// Gate a project is evaluated against, `default` when none was selected for it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectQualityGate {
+    pub name: String,
+    #[serde(default)]
+    pub default: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProjectQualityGateResponse {
+    #[serde(rename = "qualityGate")]
+    pub quality_gate: ProjectQualityGate,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ProjectStatus {
     pub status: String, // "OK", "WARN", "ERROR"
@@ -97,9 +269,50 @@ pub struct Component {
     pub key: String,
     pub name: String,
     pub qualifier: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
     pub measures: Vec<Measure>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ComponentTreeResponse {
+    pub paging: Paging,
+    #[serde(rename = "baseComponent")]
+    pub base_component: Component,
+    pub components: Vec<Component>,
+}
+
+// Paging, sorting and qualifier filters of a component tree query
+#[derive(Debug)]
+pub struct ComponentTreeQuery<'a> {
+    pub metric_keys: &'a [&'a str],
+    pub qualifiers: &'a [String], // "FIL", "DIR"
+    pub sort_metric: Option<&'a str>,
+    pub ascending: bool,
+    pub page: i32,
+    pub page_size: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MeasuresHistoryResponse {
+    pub paging: Paging,
+    pub measures: Vec<MetricHistory>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MetricHistory {
+    pub metric: String,
+    pub history: Vec<HistoryPoint>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HistoryPoint {
+    pub date: String,
+    pub value: Option<String>, // missing when the analysis had no value for the metric
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Measure {
     pub metric: String,
@@ -117,33 +330,614 @@ pub struct Period {
     pub date: String,
 }
 
-// Quality Gates list types
 #[derive(Debug, Serialize, Deserialize)]
-pub struct QualityGateItem {
-    pub id: i64,
+pub struct HotspotsResponse {
+    pub hotspots: Vec<Hotspot>,
+    pub paging: Paging,
+    // False when the search cap kept some hotspots out of the list
+    #[serde(default)]
+    pub complete: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Hotspot {
+    pub key: String,
+    pub component: String,
+    pub project: String,
+    #[serde(rename = "securityCategory")]
+    pub security_category: Option<String>,
+    #[serde(rename = "vulnerabilityProbability")]
+    pub vulnerability_probability: String, // "HIGH", "MEDIUM", "LOW"
+    pub status: String, // "TO_REVIEW", "REVIEWED"
+    pub resolution: Option<String>, // "FIXED", "SAFE", "ACKNOWLEDGED"
+    pub line: Option<i32>,
+    pub message: String,
+    pub assignee: Option<String>,
+    pub author: Option<String>,
+    #[serde(rename = "creationDate")]
+    pub creation_date: String,
+    #[serde(rename = "updateDate")]
+    pub update_date: String,
+    #[serde(rename = "ruleKey")]
+    pub rule_key: Option<String>,
+}
+
+// Rule metadata as returned by /api/rules/show and /api/rules/search
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Rule {
+    pub key: String,
+    pub name: String,
+    #[serde(rename = "htmlDesc")]
+    pub html_desc: Option<String>,
+    #[serde(rename = "mdDesc")]
+    pub md_desc: Option<String>,
+    #[serde(rename = "descriptionSections", default)]
+    pub description_sections: Vec<RuleDescriptionSection>,
+    #[serde(rename = "type")]
+    pub rule_type: Option<String>,
+    pub severity: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(rename = "sysTags", default)]
+    pub sys_tags: Vec<String>,
+    // CONSTANT_ISSUE, LINEAR or LINEAR_OFFSET
+    #[serde(rename = "remFnType")]
+    pub rem_fn_type: Option<String>,
+    #[serde(rename = "remFnBaseEffort")]
+    pub rem_fn_base_effort: Option<String>,
+    #[serde(rename = "remFnGapMultiplier")]
+    pub rem_fn_gap_multiplier: Option<String>,
+    #[serde(rename = "defaultRemFnType")]
+    pub default_rem_fn_type: Option<String>,
+    #[serde(rename = "defaultRemFnBaseEffort")]
+    pub default_rem_fn_base_effort: Option<String>,
+    #[serde(rename = "defaultRemFnGapMultiplier")]
+    pub default_rem_fn_gap_multiplier: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RuleDescriptionSection {
+    pub key: String,
+    pub content: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RuleShowResponse {
+    pub rule: Rule,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RulesSearchResponse {
+    pub rules: Vec<Rule>,
+    pub total: Option<i32>,
+    pub paging: Option<Paging>,
+}
+
+// Rule summary attached to each issue of the results
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleDetails {
+    pub key: String,
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(rename = "type")]
+    pub rule_type: Option<String>,
+    pub severity: Option<String>,
+    pub tags: Vec<String>,
+    // How the effort of an issue is computed: CONSTANT_ISSUE, LINEAR or LINEAR_OFFSET
+    #[serde(rename = "remediationFunction")]
+    pub remediation_function: Option<String>,
+    // Fixed effort per issue, None for LINEAR rules
+    #[serde(rename = "remediationEffort")]
+    pub remediation_effort: Option<String>,
+    // Effort per unit of the issue's gap, for LINEAR and LINEAR_OFFSET rules
+    #[serde(rename = "remediationGapMultiplier")]
+    pub remediation_gap_multiplier: Option<String>,
+}
+
+impl From<Rule> for RuleDetails {
+    fn from(rule: Rule) -> Self {
+        // Newer SonarQube versions split the description into sections
+        let description = rule.html_desc.or_else(|| {
+            let sections: Vec<String> = rule.description_sections.into_iter().map(|s| s.content).collect();
+            (!sections.is_empty()).then(|| sections.join("\n"))
+        }).or(rule.md_desc);
+
+
+        let mut tags = rule.sys_tags;
+        tags.extend(rule.tags);
+        tags.sort();
+        tags.dedup();
+
+        Self {
+            key: rule.key,
+            name: rule.name,
+            description,
+            rule_type: rule.rule_type,
+            severity: rule.severity,
+            tags,
+            remediation_function: rule.rem_fn_type.or(rule.default_rem_fn_type),
+            remediation_effort: rule.rem_fn_base_effort.or(rule.default_rem_fn_base_effort),
+            remediation_gap_multiplier: rule.rem_fn_gap_multiplier.or(rule.default_rem_fn_gap_multiplier),
+        }
+    }
+}
+
+// Compute Engine (background analysis processing) types
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CeTask {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub task_type: String,
+    #[serde(rename = "componentKey")]
+    pub component_key: Option<String>,
+    pub status: String, // "PENDING", "IN_PROGRESS", "SUCCESS", "FAILED", "CANCELED"
+    #[serde(rename = "submittedAt")]
+    pub submitted_at: Option<String>,
+    #[serde(rename = "startedAt")]
+    pub started_at: Option<String>,
+    #[serde(rename = "executedAt")]
+    pub executed_at: Option<String>,
+    #[serde(rename = "executionTimeMs")]
+    pub execution_time_ms: Option<i64>,
+    #[serde(rename = "errorMessage")]
+    pub error_message: Option<String>,
+    #[serde(rename = "analysisId")]
+    pub analysis_id: Option<String>,
+}
+
+impl CeTask {
+    pub fn is_finished(&self) -> bool {
+        matches!(self.status.as_str(), "SUCCESS" | "FAILED" | "CANCELED")
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CeComponentResponse {
+    #[serde(default)]
+    pub queue: Vec<CeTask>,
+    pub current: Option<CeTask>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CeTaskResponse {
+    pub task: CeTask,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CeActivityResponse {
+    pub tasks: Vec<CeTask>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProjectBranchesResponse {
+    pub branches: Vec<ProjectBranch>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProjectBranch {
+    pub name: String,
+    #[serde(rename = "isMain")]
+    pub is_main: bool,
+    #[serde(rename = "type")]
+    pub branch_type: Option<String>,
+    #[serde(rename = "analysisDate")]
+    pub analysis_date: Option<String>,
+    pub status: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProjectPullRequestsResponse {
+    #[serde(rename = "pullRequests")]
+    pub pull_requests: Vec<ProjectPullRequest>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProjectPullRequest {
+    pub key: String,
+    pub title: Option<String>,
+    pub branch: String,
+    pub base: Option<String>,
+    #[serde(rename = "analysisDate")]
+    pub analysis_date: Option<String>,
+    pub url: Option<String>,
+    pub status: Option<serde_json::Value>,
+}
+
+// Quality profile (rule set) types
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QualityProfile {
+    pub key: String,
+    pub name: String,
+    pub language: String,
+    #[serde(rename = "languageName")]
+    pub language_name: Option<String>,
+    #[serde(rename = "isDefault", default)]
+    pub is_default: bool,
+    #[serde(rename = "isInherited", default)]
+    pub is_inherited: bool,
+    #[serde(rename = "isBuiltIn", default)]
+    pub is_built_in: bool,
+    #[serde(rename = "parentKey")]
+    pub parent_key: Option<String>,
+    #[serde(rename = "parentName")]
+    pub parent_name: Option<String>,
+    #[serde(rename = "activeRuleCount")]
+    pub active_rule_count: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QualityProfilesResponse {
+    pub profiles: Vec<QualityProfile>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QualityProfileCreateResponse {
+    pub profile: QualityProfile,
+}
+
+// Project settings types
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingDefinition {
+    pub key: String,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    // STRING, TEXT, BOOLEAN, INTEGER, FLOAT, SINGLE_SELECT_LIST... Missing means STRING
+    #[serde(rename = "type")]
+    pub setting_type: Option<String>,
+    #[serde(rename = "multiValues", default)]
+    pub multi_values: bool,
+    #[serde(default)]
+    pub options: Vec<String>,
+    pub category: Option<String>,
+    #[serde(rename = "subCategory")]
+    pub sub_category: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SettingDefinitionsResponse {
+    pub definitions: Vec<SettingDefinition>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingValue {
+    pub key: String,
+    pub value: Option<String>,
+    pub values: Option<Vec<String>>,
+    // True when the value comes from the instance rather than the project
+    #[serde(default)]
+    pub inherited: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SettingValuesResponse {
+    pub settings: Vec<SettingValue>,
+}
+
+// A setting is either a single value or a list, depending on its definition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SettingInput {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewCodePeriod {
+    #[serde(rename = "projectKey")]
+    pub project_key: Option<String>,
+    #[serde(rename = "branchKey")]
+    pub branch_key: Option<String>,
+    // PREVIOUS_VERSION, NUMBER_OF_DAYS, REFERENCE_BRANCH or SPECIFIC_ANALYSIS
+    #[serde(rename = "type")]
+    pub period_type: String,
+    pub value: Option<String>,
+    #[serde(default)]
+    pub inherited: bool,
+}
+
+// Project permission types
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserPermissions {
+    pub login: String,
+    pub name: Option<String>,
+    pub permissions: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupPermissions {
+    pub name: String,
+    pub permissions: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UserPermissionsResponse {
+    pub users: Vec<UserPermissions>,
+    pub paging: Paging,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GroupPermissionsResponse {
+    pub groups: Vec<GroupPermissions>,
+    pub paging: Paging,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PermissionTemplate {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PermissionTemplatesResponse {
+    #[serde(rename = "permissionTemplates")]
+    pub permission_templates: Vec<PermissionTemplate>,
+}
+
+// Webhook types
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Webhook {
+    pub key: String,
+    pub name: String,
+    pub url: String,
+    #[serde(rename = "hasSecret", default)]
+    pub has_secret: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WebhooksResponse {
+    pub webhooks: Vec<Webhook>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WebhookCreateResponse {
+    pub webhook: Webhook,
+}
+
+// Token as listed by /api/user_tokens/search, without its value
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserToken {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub token_type: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<String>,
+    #[serde(rename = "lastConnectionDate")]
+    pub last_connection_date: Option<String>,
+    #[serde(rename = "expirationDate")]
+    pub expiration_date: Option<String>,
+    #[serde(rename = "isExpired", default)]
+    pub is_expired: bool,
+    pub project: Option<UserTokenProject>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserTokenProject {
+    pub key: String,
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UserTokensResponse {
+    pub login: String,
+    #[serde(rename = "userTokens", default)]
+    pub user_tokens: Vec<UserToken>,
+}
+
+// Project as listed by /api/projects/search
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SonarProject {
+    pub key: String,
     pub name: String,
-    #[serde(rename = "isDefault")]
-    pub is_default: Option<bool>,
+    pub qualifier: Option<String>,
+    pub visibility: Option<String>,
+    #[serde(rename = "lastAnalysisDate")]
+    pub last_analysis_date: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct QualityGatesListResponse {
-    #[serde(rename = "qualitygates")]
-    pub quality_gates: Vec<QualityGateItem>,
+pub struct ProjectsSearchResponse {
+    pub components: Vec<SonarProject>,
+    pub paging: Paging,
 }
 
 pub struct SonarQubeClient {
     client: Client,
     base_url: String,
     admin_token: String,
+    config: SonarClientConfig,
 }
 
-impl SonarQubeClient {
-    pub fn new(base_url: String, admin_token: String) -> Self {
+// A copy of the request to send when it may be retried: only GETs are idempotent,
+// and a streaming body can't be replayed. Anything else is sent once.
+fn replayable(request: &Request) -> Option<Request> {
+    if request.method() != Method::GET {
+        return None;
+    }
+    request.try_clone()
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+// Retry-After is either a number of seconds or an HTTP date
+fn parse_retry_after(value: &str, now: SystemTime) -> Option<std::time::Duration> {
+    if let Ok(seconds) = value.trim().parse::<u64>() {
+        return Some(std::time::Duration::from_secs(seconds));
+    }
+
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(date.duration_since(now).unwrap_or_default())
+}
+
+// Waits what Retry-After asks for, or backs off exponentially, never longer than retry_max_delay
+fn retry_delay(config: &SonarClientConfig, attempt: u32, retry_after: Option<std::time::Duration>) -> std::time::Duration {
+    if let Some(delay) = retry_after {
+        return delay.min(config.retry_max_delay);
+    }
+
+    let exponential = config.retry_base_delay.saturating_mul(2u32.saturating_pow(attempt));
+    let capped = exponential.min(config.retry_max_delay);
+    // Equal jitter: keep half of the delay, randomise the other half
+    let half = capped / 2;
+    half + rand::thread_rng().gen_range(std::time::Duration::ZERO..=half)
+}
+
+// Pages needed to read a slice of `total` issues, never past the search cap
+fn issue_page_count(total: i32) -> i32 {
+    (total.min(ISSUES_SEARCH_LIMIT) + ISSUES_PAGE_SIZE - 1) / ISSUES_PAGE_SIZE
+}
+
+// Splits a search by issue type, then by severity, or returns `None` once both are set
+fn split_issue_category(filters: &[(&'static str, String)]) -> Option<Vec<IssueFilters>> {
+    let has_filter = |name: &str| filters.iter().any(|(key, _)| *key == name);
+    let with_filter = |name: &'static str, value: &str| {
+        let mut slice = filters.to_vec();
+        slice.push((name, value.to_string()));
+        slice
+    };
+
+    if !has_filter("types") {
+        return Some(ISSUE_TYPES.iter().map(|t| with_filter("types", t)).collect());
+    }
+    if !has_filter("severities") {
+        return Some(ISSUE_SEVERITIES.iter().map(|s| with_filter("severities", s)).collect());
+    }
+    None
+}
+
+// Halves the [after, before) creation window, or returns `None` when it is a single second
+fn split_creation_window(filters: &[(&'static str, String)], after: DateTime<Utc>, before: DateTime<Utc>) -> Option<Vec<IssueFilters>> {
+    let span = before - after;
+    if span <= Duration::seconds(1) {
+        return None;
+    }
+
+    let middle = after + Duration::seconds(span.num_seconds() / 2);
+    let without_window: IssueFilters = filters
+        .iter()
+        .filter(|(key, _)| *key != "createdAfter" && *key != "createdBefore")
+        .cloned()
+        .collect();
+
+    let slices = [(after, middle), (middle, before)]
+        .into_iter()
+        .map(|(from, to)| {
+            let mut slice = without_window.clone();
+            slice.push(("createdAfter", from.format(SONAR_DATE_FORMAT).to_string()));
+            slice.push(("createdBefore", to.format(SONAR_DATE_FORMAT).to_string()));
+            slice
+        })
+        .collect();
+
+    Some(slices)
+}
+
+// Issues gathered across search slices, once each even when slices or pages overlap
+struct IssueCollector {
+    issues: Vec<Issue>,
+    seen: HashSet<String>,
+    // False when the search cap or an empty page kept some issues out
+    complete: bool,
+}
+
+impl IssueCollector {
+    fn new() -> Self {
         Self {
-            client: Client::new(),
+            issues: Vec::new(),
+            seen: HashSet::new(),
+            complete: true,
+        }
+    }
+
+    fn add(&mut self, issues: Vec<Issue>) {
+        for issue in issues {
+            if self.seen.insert(issue.key.clone()) {
+                self.issues.push(issue);
+            }
+        }
+    }
+
+    // Adds a page after the first one, false when it came back empty: issues resolved
+    // while paging shift the rest forward, so the list may now miss some
+    fn add_next_page(&mut self, issues: Vec<Issue>) -> bool {
+        if issues.is_empty() {
+            self.complete = false;
+            return false;
+        }
+        self.add(issues);
+        true
+    }
+
+    fn into_response(self, total: Option<i32>) -> ProjectIssuesResponse {
+        let fetched = self.issues.len() as i32;
+        ProjectIssuesResponse {
+            issues: self.issues,
+            paging: Paging {
+                page_index: 1,
+                page_size: fetched,
+                total: total.unwrap_or(fetched),
+            },
+            complete: self.complete,
+        }
+    }
+}
+
+impl SonarQubeClient {
+    pub fn new(base_url: String, admin_token: String) -> Result<Self> {
+        Self::with_config(base_url, admin_token, SonarClientConfig::from_env())
+    }
+
+    pub fn with_config(base_url: String, admin_token: String, config: SonarClientConfig) -> Result<Self> {
+        let client = Client::builder()
+            .connect_timeout(config.connect_timeout)
+            .timeout(config.request_timeout)
+            .build()
+            .map_err(SonarQubeError::Transport)?;
+
+        Ok(Self {
+            client,
             base_url,
             admin_token,
+            config,
+        })
+    }
+
+    // Only for idempotent GETs: replays the request with exponential backoff and jitter
+    // on connection errors, timeouts, 429 and 5xx, honouring Retry-After. POSTs must use send() directly.
+    async fn send_with_retry(&self, request: RequestBuilder) -> Result<Response> {
+        let request = request.build()?;
+        let mut attempt = 0;
+
+        loop {
+            let Some(attempt_request) = replayable(&request) else {
+                return Ok(self.client.execute(request).await?);
+            };
+            let result = self.client.execute(attempt_request).await;
+
+            let retryable = match &result {
+                Ok(response) => is_retryable_status(response.status()),
+                Err(e) => e.is_connect() || e.is_timeout(),
+            };
+            if !retryable || attempt >= self.config.max_retries {
+                return Ok(result?);
+            }
+
+            let retry_after = result
+                .as_ref()
+                .ok()
+                .and_then(|response| response.headers().get(reqwest::header::RETRY_AFTER))
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| parse_retry_after(value, SystemTime::now()));
+            let delay = retry_delay(&self.config, attempt, retry_after);
+
+            match &result {
+                Ok(response) => warn!("SonarQube answered {}, retrying in {:?}", response.status(), delay),
+                Err(e) => warn!("SonarQube request failed ({}), retrying in {:?}", e, delay),
+            }
+
+            actix_web::rt::time::sleep(delay).await;
+            attempt += 1;
         }
     }
 
@@ -163,23 +957,23 @@ impl SonarQubeClient {
             .send()
             .await?;
 
-        if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow::anyhow!("Failed to create project: {}", error_text));
-        }
+        SonarQubeError::check(response).await?;
 
         Ok(())
     }
 
-    pub async fn create_project_token(&self, project_key: &str) -> Result<String> {
+    pub async fn create_project_token(&self, project_key: &str, token_name: &str, expiration_date: Option<NaiveDate>) -> Result<String> {
         let url = format!("{}/api/user_tokens/generate", self.base_url);
         
         // SonarQube API expects form-encoded data
-        let params = [
-            ("name", format!("{}_token", project_key)),
+        let mut params = vec![
+            ("name", token_name.to_string()),
             ("type", "PROJECT_ANALYSIS_TOKEN".to_string()),
             ("projectKey", project_key.to_string()),
         ];
+        if let Some(expiration_date) = expiration_date {
+            params.push(("expirationDate", expiration_date.format(TOKEN_EXPIRATION_FORMAT).to_string()));
+        }
 
         let response = self.client
             .post(&url)
@@ -188,88 +982,265 @@ impl SonarQubeClient {
             .send()
             .await?;
 
-        if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow::anyhow!("Failed to create token: {}", error_text));
-        }
+        let response = SonarQubeError::check(response).await?;
 
         let token_response: TokenResponse = response.json().await?;
         Ok(token_response.token)
     }
 
-    pub async fn get_project_issues(&self, project_key: &str) -> Result<ProjectIssuesResponse> {
-        let url = format!("{}/api/issues/search", self.base_url);
-        
-        let params = [
-            ("componentKeys", project_key),
-            ("resolved", "false"),
-            ("ps", "500"),
+    // Fetches every unresolved issue of a project, paging through the results and
+    // splitting the search by type, severity and then creation date whenever a
+    // slice is larger than what SonarQube lets us page through.
+    pub async fn get_project_issues(&self, project_key: &str, target: &AnalysisTarget) -> Result<ProjectIssuesResponse> {
+        let mut base_filters: IssueFilters = vec![
+            ("componentKeys", project_key.to_string()),
+            ("resolved", "false".to_string()),
         ];
+        base_filters.extend(target.params());
+
+        let mut pending = vec![base_filters];
+        let mut collector = IssueCollector::new();
+        let mut total = None;
+
+        while let Some(filters) = pending.pop() {
+            let first_page = self.search_issues_page(&filters, 1, ISSUES_PAGE_SIZE, None).await?;
+            let slice_total = first_page.paging.total;
+            total.get_or_insert(slice_total);
+
+            if slice_total > ISSUES_SEARCH_LIMIT {
+                if let Some(slices) = self.split_issue_filters(&filters).await? {
+                    pending.extend(slices);
+                    continue;
+                }
+
+                // Even a one-second window is over the cap, keep what can be paged through
+                warn!("Issue search for {} is capped at {} of {} results", project_key, ISSUES_SEARCH_LIMIT, slice_total);
+                collector.complete = false;
+            }
+
+            collector.add(first_page.issues);
+            for page in 2..=issue_page_count(slice_total) {
+                let next_page = self.search_issues_page(&filters, page, ISSUES_PAGE_SIZE, None).await?;
+                if !collector.add_next_page(next_page.issues) {
+                    break;
+                }
+            }
+        }
 
-        let response = self.client
+        Ok(collector.into_response(total))
+    }
+
+    async fn search_issues_page(
+        &self,
+        filters: &[(&'static str, String)],
+        page: i32,
+        page_size: i32,
+        creation_order: Option<bool>,
+    ) -> Result<ProjectIssuesResponse> {
+        let url = format!("{}/api/issues/search", self.base_url);
+
+        let mut params = filters.to_vec();
+        params.push(("p", page.to_string()));
+        params.push(("ps", page_size.to_string()));
+        params.push(("additionalFields", "transitions,comments".to_string()));
+        if let Some(ascending) = creation_order {
+            params.push(("s", "CREATION_DATE".to_string()));
+            params.push(("asc", ascending.to_string()));
+        }
+
+        let request = self.client
             .get(&url)
             .query(&params)
-            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))))
-            .send()
-            .await?;
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))));
 
-        if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow::anyhow!("Failed to get project issues: {}", error_text));
-        }
+        let response = self.send_with_retry(request).await?;
+
+        let response = SonarQubeError::check(response).await?;
 
         let issues_response: ProjectIssuesResponse = response.json().await?;
         Ok(issues_response)
     }
 
-    pub async fn get_project_coverage(&self, project_key: &str) -> Result<CoverageResponse> {
+    // Splits an over-sized issue search into disjoint slices, or returns `None`
+    // when the slice is already a single second of creation dates.
+    async fn split_issue_filters(&self, filters: &[(&'static str, String)]) -> Result<Option<Vec<IssueFilters>>> {
+        if let Some(slices) = split_issue_category(filters) {
+            return Ok(Some(slices));
+        }
+
+        // createdAfter is inclusive and createdBefore exclusive, so windows never overlap
+        let window = match (Self::filter_date(filters, "createdAfter")?, Self::filter_date(filters, "createdBefore")?) {
+            (Some(after), Some(before)) => (after, before),
+            _ => {
+                let oldest = self.issue_creation_bound(filters, true).await?;
+                let newest = self.issue_creation_bound(filters, false).await?;
+                match (oldest, newest) {
+                    (Some(oldest), Some(newest)) => (oldest, newest + Duration::seconds(1)),
+                    _ => return Ok(None),
+                }
+            }
+        };
+
+        let (after, before) = window;
+        Ok(split_creation_window(filters, after, before))
+    }
+
+    async fn issue_creation_bound(&self, filters: &[(&'static str, String)], oldest: bool) -> Result<Option<DateTime<Utc>>> {
+        let page = self.search_issues_page(filters, 1, 1, Some(oldest)).await?;
+        page.issues
+            .first()
+            .map(|issue| Self::parse_sonar_date(&issue.creation_date))
+            .transpose()
+    }
+
+    fn filter_date(filters: &[(&'static str, String)], name: &str) -> Result<Option<DateTime<Utc>>> {
+        filters
+            .iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| Self::parse_sonar_date(value))
+            .transpose()
+    }
+
+    pub fn parse_sonar_date(value: &str) -> Result<DateTime<Utc>> {
+        let date = DateTime::parse_from_str(value, SONAR_DATE_FORMAT)
+            .map_err(|e| SonarQubeError::Decode(format!("invalid date '{}': {}", value, e)))?;
+        Ok(date.with_timezone(&Utc))
+    }
+
+    pub async fn get_project_coverage(&self, project_key: &str, target: &AnalysisTarget) -> Result<CoverageResponse> {
         let url = format!("{}/api/measures/component", self.base_url);
         
-        let params = [
-            ("component", project_key),
-            ("metricKeys", "coverage,branch_coverage,line_coverage,lines_to_cover,uncovered_lines"),
+        let mut params = vec![
+            ("component", project_key.to_string()),
+            ("metricKeys", "coverage,branch_coverage,line_coverage,lines_to_cover,uncovered_lines".to_string()),
         ];
+        params.extend(target.params());
 
-        let response = self.client
+        let request = self.client
             .get(&url)
             .query(&params)
-            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))))
-            .send()
-            .await?;
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))));
 
-        if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow::anyhow!("Failed to get project coverage: {}", error_text));
-        }
+        let response = self.send_with_retry(request).await?;
+
+        let response = SonarQubeError::check(response).await?;
 
         let coverage_response: CoverageResponse = response.json().await?;
     Ok(coverage_response)
 }
 
-pub async fn get_project_quality_gate(&self, project_key: &str) -> Result<QualityGateResponse> {
+    pub async fn get_component_tree(&self, project_key: &str, target: &AnalysisTarget, query: &ComponentTreeQuery<'_>) -> Result<ComponentTreeResponse> {
+        let url = format!("{}/api/measures/component_tree", self.base_url);
+
+        let mut params = vec![
+            ("component", project_key.to_string()),
+            ("metricKeys", query.metric_keys.join(",")),
+            ("p", query.page.to_string()),
+            ("ps", query.page_size.to_string()),
+            ("asc", query.ascending.to_string()),
+        ];
+        params.extend(target.params());
+        if !query.qualifiers.is_empty() {
+            params.push(("qualifiers", query.qualifiers.join(",")));
+        }
+        if let Some(metric) = query.sort_metric {
+            // Components without the metric would otherwise be listed first
+            params.push(("s", "metric".to_string()));
+            params.push(("metricSort", metric.to_string()));
+            params.push(("metricSortFilter", "withMeasuresOnly".to_string()));
+        }
+
+        let request = self.client
+            .get(&url)
+            .query(&params)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))));
+
+        let response = self.send_with_retry(request).await?;
+        let response = SonarQubeError::check(response).await?;
+
+        let tree_response: ComponentTreeResponse = response.json().await?;
+        Ok(tree_response)
+    }
+
+    // Time series of each metric, one point per analysis
+    pub async fn get_measures_history(&self, project_key: &str, target: &AnalysisTarget, metrics: &[String], from: Option<&str>, to: Option<&str>) -> Result<MeasuresHistoryResponse> {
+        let url = format!("{}/api/measures/search_history", self.base_url);
+
+        let mut filters = vec![
+            ("component", project_key.to_string()),
+            ("metrics", metrics.join(",")),
+        ];
+        filters.extend(target.params());
+        if let Some(from) = from {
+            filters.push(("from", from.to_string()));
+        }
+        if let Some(to) = to {
+            filters.push(("to", to.to_string()));
+        }
+
+        let mut measures: Vec<MetricHistory> = Vec::new();
+        let mut page = 1;
+
+        loop {
+            let mut params = filters.clone();
+            params.push(("p", page.to_string()));
+            params.push(("ps", HISTORY_PAGE_SIZE.to_string()));
+
+            let request = self.client
+                .get(&url)
+                .query(&params)
+                .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))));
+
+            let response = self.send_with_retry(request).await?;
+            let response = SonarQubeError::check(response).await?;
+
+            let history_page: MeasuresHistoryResponse = response.json().await?;
+            let paging = history_page.paging;
+
+            // Pages hold the next analyses of every metric, so histories are appended per metric
+            for metric_history in history_page.measures {
+                match measures.iter_mut().find(|m| m.metric == metric_history.metric) {
+                    Some(existing) => existing.history.extend(metric_history.history),
+                    None => measures.push(metric_history),
+                }
+            }
+
+            if paging.page_index * paging.page_size >= paging.total {
+                return Ok(MeasuresHistoryResponse {
+                    paging: Paging {
+                        page_index: 1,
+                        page_size: paging.total,
+                        total: paging.total,
+                    },
+                    measures,
+                });
+            }
+            page += 1;
+        }
+    }
+
+pub async fn get_project_quality_gate(&self, project_key: &str, target: &AnalysisTarget) -> Result<QualityGateResponse> {
     let url = format!("{}/api/qualitygates/project_status", self.base_url);
     
-    let params = [
-        ("projectKey", project_key),
+    let mut params = vec![
+        ("projectKey", project_key.to_string()),
     ];
+    params.extend(target.params());
 
-        let response = self.client
+        let request = self.client
             .get(&url)
             .query(&params)
-            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))))
-            .send()
-            .await?;
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))));
 
-        if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow::anyhow!("Failed to get project quality gate: {}", error_text));
-        }
+        let response = self.send_with_retry(request).await?;
+
+        let response = SonarQubeError::check(response).await?;
 
         let quality_gate_response: QualityGateResponse = response.json().await?;
         Ok(quality_gate_response)
 }
 
-    pub async fn generate_admin_token(&self, username: &str, password: &str, token_name: &str, token_type: &str) -> Result<String> {
+    pub async fn generate_admin_token(&self, username: &str, password: &str, token_name: &str, token_type: &str, expiration_date: Option<NaiveDate>) -> Result<String> {
         let url = format!("{}/api/user_tokens/generate", self.base_url);
         
         // Validate and set token type
@@ -280,10 +1251,13 @@ pub async fn get_project_quality_gate(&self, project_key: &str) -> Result<Qualit
         };
         
         // SonarQube API expects form-encoded data
-        let params = [
+        let mut params = vec![
             ("name", token_name.to_string()),
             ("type", valid_token_type.to_string()),
         ];
+        if let Some(expiration_date) = expiration_date {
+            params.push(("expirationDate", expiration_date.format(TOKEN_EXPIRATION_FORMAT).to_string()));
+        }
 
         let response = self.client
             .post(&url)
@@ -292,15 +1266,89 @@ pub async fn get_project_quality_gate(&self, project_key: &str) -> Result<Qualit
             .send()
             .await?;
 
-        if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow::anyhow!("Failed to generate admin token: {}", error_text));
+        let response = SonarQubeError::check(response).await?;
+
+        let token_response: TokenResponse = response.json().await?;
+        Ok(token_response.token)
+    }
+
+    // Generates a token for another user, which needs the admin token to belong to an administrator
+    pub async fn generate_user_token(&self, login: &str, token_name: &str, token_type: &str, expiration_date: Option<NaiveDate>) -> Result<String> {
+        let url = format!("{}/api/user_tokens/generate", self.base_url);
+        let mut params = vec![
+            ("login", login.to_string()),
+            ("name", token_name.to_string()),
+            ("type", token_type.to_string()),
+        ];
+        if let Some(expiration_date) = expiration_date {
+            params.push(("expirationDate", expiration_date.format(TOKEN_EXPIRATION_FORMAT).to_string()));
         }
 
+        let response = self.client
+            .post(&url)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))))
+            .form(&params)
+            .send()
+            .await?;
+
+        let response = SonarQubeError::check(response).await?;
+
         let token_response: TokenResponse = response.json().await?;
         Ok(token_response.token)
     }
 
+    // Tokens of `login`, or of the user the admin token belongs to
+    pub async fn search_user_tokens(&self, login: Option<&str>) -> Result<UserTokensResponse> {
+        let url = format!("{}/api/user_tokens/search", self.base_url);
+        let params: Vec<(&str, &str)> = login.map(|login| ("login", login)).into_iter().collect();
+
+        let request = self.client
+            .get(&url)
+            .query(&params)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))));
+
+        let response = self.send_with_retry(request).await?;
+        let response = SonarQubeError::check(response).await?;
+
+        Ok(response.json().await?)
+    }
+
+    pub async fn revoke_user_token(&self, token_name: &str, login: Option<&str>) -> Result<()> {
+        let url = format!("{}/api/user_tokens/revoke", self.base_url);
+        let mut params = vec![("name", token_name)];
+        if let Some(login) = login {
+            params.push(("login", login));
+        }
+
+        let response = self.client
+            .post(&url)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))))
+            .form(&params)
+            .send()
+            .await?;
+
+        SonarQubeError::check(response).await?;
+
+        Ok(())
+    }
+
+    // Revokes a token of `username`, authenticated with their credentials like generate_admin_token
+    pub async fn revoke_own_token(&self, username: &str, password: &str, token_name: &str) -> Result<()> {
+        let url = format!("{}/api/user_tokens/revoke", self.base_url);
+        let params = [("name", token_name)];
+
+        let response = self.client
+            .post(&url)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:{}", username, password))))
+            .form(&params)
+            .send()
+            .await?;
+
+        SonarQubeError::check(response).await?;
+
+        Ok(())
+    }
+
     pub async fn delete_project(&self, project_key: &str) -> Result<()> {
         let url = format!("{}/api/projects/delete", self.base_url);
         
@@ -316,11 +1364,118 @@ pub async fn get_project_quality_gate(&self, project_key: &str) -> Result<Qualit
             .send()
             .await?;
 
-        if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow::anyhow!("Failed to delete project: {}", error_text));
+        SonarQubeError::check(response).await?;
+
+        Ok(())
+    }
+
+    pub async fn find_project(&self, project_key: &str) -> Result<Option<SonarProject>> {
+        let url = format!("{}/api/projects/search", self.base_url);
+        let params = [("projects", project_key)];
+
+        let request = self.client
+            .get(&url)
+            .query(&params)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))));
+
+        let response = self.send_with_retry(request).await?;
+        let response = SonarQubeError::check(response).await?;
+
+        let projects: ProjectsSearchResponse = response.json().await?;
+        Ok(projects.components.into_iter().find(|project| project.key == project_key))
+    }
+
+    // Every project of the instance, paging through /api/projects/search
+    pub async fn search_projects(&self) -> Result<Vec<SonarProject>> {
+        let url = format!("{}/api/projects/search", self.base_url);
+        let mut projects = Vec::new();
+        let mut page = 1;
+
+        loop {
+            let params = [
+                ("qualifiers", "TRK".to_string()),
+                ("p", page.to_string()),
+                ("ps", PROJECTS_PAGE_SIZE.to_string()),
+            ];
+            let request = self.client
+                .get(&url)
+                .query(&params)
+                .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))));
+
+            let response = self.send_with_retry(request).await?;
+            let response = SonarQubeError::check(response).await?;
+            let projects_page: ProjectsSearchResponse = response.json().await?;
+
+            let fetched = projects_page.components.len() as i32;
+            projects.extend(projects_page.components);
+            if fetched == 0 || page * PROJECTS_PAGE_SIZE >= projects_page.paging.total {
+                break;
+            }
+            page += 1;
         }
 
+        Ok(projects)
+    }
+
+    // Lines of code per language key from the last analysis, largest first; empty when never analysed
+    pub async fn get_language_distribution(&self, project_key: &str) -> Result<Vec<(String, i64)>> {
+        let url = format!("{}/api/measures/component", self.base_url);
+        let params = [
+            ("component", project_key),
+            ("metricKeys", "ncloc_language_distribution"),
+        ];
+
+        let request = self.client
+            .get(&url)
+            .query(&params)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))));
+
+        let response = self.send_with_retry(request).await?;
+        let response = SonarQubeError::check(response).await?;
+        let measures: CoverageResponse = response.json().await?;
+
+        // Value looks like "java=1520;xml=34"
+        let mut distribution: Vec<(String, i64)> = measures.component.measures.iter()
+            .filter(|measure| measure.metric == "ncloc_language_distribution")
+            .flat_map(|measure| measure.value.split(';'))
+            .filter_map(|entry| {
+                let (language, lines) = entry.split_once('=')?;
+                Some((language.to_string(), lines.parse().ok()?))
+            })
+            .collect();
+        distribution.sort_by_key(|(_, lines)| std::cmp::Reverse(*lines));
+        Ok(distribution)
+    }
+
+    pub async fn update_project_key(&self, from_key: &str, to_key: &str) -> Result<()> {
+        let url = format!("{}/api/projects/update_key", self.base_url);
+        let params = [("from", from_key), ("to", to_key)];
+
+        let response = self.client
+            .post(&url)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))))
+            .form(&params)
+            .send()
+            .await?;
+
+        SonarQubeError::check(response).await?;
+
+        Ok(())
+    }
+
+    pub async fn update_project_visibility(&self, project_key: &str, visibility: &str) -> Result<()> {
+        let url = format!("{}/api/projects/update_visibility", self.base_url);
+        let params = [("project", project_key), ("visibility", visibility)];
+
+        let response = self.client
+            .post(&url)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))))
+            .form(&params)
+            .send()
+            .await?;
+
+        SonarQubeError::check(response).await?;
+
         Ok(())
     }
 
@@ -336,10 +1491,7 @@ pub async fn get_project_quality_gate(&self, project_key: &str) -> Result<Qualit
             .send()
             .await?;
 
-        if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow::anyhow!("Failed to create quality gate: {}", error_text));
-        }
+        SonarQubeError::check(response).await?;
 
         Ok(())
     }
@@ -361,10 +1513,7 @@ pub async fn get_project_quality_gate(&self, project_key: &str) -> Result<Qualit
             .send()
             .await?;
 
-        if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow::anyhow!("Failed to add quality gate condition: {}", error_text));
-        }
+        SonarQubeError::check(response).await?;
 
         Ok(())
     }
@@ -384,10 +1533,7 @@ pub async fn get_project_quality_gate(&self, project_key: &str) -> Result<Qualit
             .send()
             .await?;
 
-        if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow::anyhow!("Failed to rename quality gate: {}", error_text));
-        }
+        SonarQubeError::check(response).await?;
 
         Ok(())
     }
@@ -403,10 +1549,7 @@ pub async fn get_project_quality_gate(&self, project_key: &str) -> Result<Qualit
             .send()
             .await?;
 
-        if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow::anyhow!("Failed to delete quality gate: {}", error_text));
-        }
+        SonarQubeError::check(response).await?;
 
         Ok(())
     }
@@ -422,10 +1565,7 @@ pub async fn get_project_quality_gate(&self, project_key: &str) -> Result<Qualit
             .send()
             .await?;
 
-        if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow::anyhow!("Failed to set default quality gate: {}", error_text));
-        }
+        SonarQubeError::check(response).await?;
 
         Ok(())
     }
@@ -433,18 +1573,15 @@ pub async fn get_project_quality_gate(&self, project_key: &str) -> Result<Qualit
     pub async fn get_quality_gates(&self) -> Result<serde_json::Value> {
         let url = format!("{}/api/qualitygates/list", self.base_url);
 
-        let response = self.client
+        let request = self.client
             .get(&url)
-            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))))
-            .send()
-            .await?;
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))));
+
+        let response = self.send_with_retry(request).await?;
 
         info!("response {:?}", response);
 
-        if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow::anyhow!("Failed to list quality gates: {}", error_text));
-        }
+        let response = SonarQubeError::check(response).await?;
 
         let list: serde_json::Value = response.json().await?;
         Ok(list)
@@ -461,10 +1598,7 @@ pub async fn get_project_quality_gate(&self, project_key: &str) -> Result<Qualit
             .send()
             .await?;
 
-        if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow::anyhow!("Failed to delete quality gate condition: {}", error_text));
-        }
+        SonarQubeError::check(response).await?;
 
         Ok(())
     }
@@ -473,19 +1607,1062 @@ pub async fn get_project_quality_gate(&self, project_key: &str) -> Result<Qualit
         let url = format!("{}/api/qualitygates/show", self.base_url);
         let params = [("name", name.to_string())];
 
-        let response = self.client
+        let request = self.client
+            .get(&url)
+            .query(&params)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))));
+
+        let response = self.send_with_retry(request).await?;
+
+        let response = SonarQubeError::check(response).await?;
+
+        let details: serde_json::Value = response.json().await?;
+        Ok(details)
+    }
+
+    // Quality Profile APIs
+    // With a project key, only the profiles the project is analysed with are returned
+    pub async fn search_quality_profiles(&self, language: Option<&str>, project_key: Option<&str>) -> Result<Vec<QualityProfile>> {
+        let url = format!("{}/api/qualityprofiles/search", self.base_url);
+        let mut params = Vec::new();
+        if let Some(language) = language {
+            params.push(("language", language));
+        }
+        if let Some(project_key) = project_key {
+            params.push(("project", project_key));
+        }
+
+        let request = self.client
             .get(&url)
             .query(&params)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))));
+
+        let response = self.send_with_retry(request).await?;
+        let response = SonarQubeError::check(response).await?;
+
+        let profiles: QualityProfilesResponse = response.json().await?;
+        Ok(profiles.profiles)
+    }
+
+    pub async fn create_quality_profile(&self, name: &str, language: &str) -> Result<QualityProfile> {
+        let url = format!("{}/api/qualityprofiles/create", self.base_url);
+        let params = [("name", name), ("language", language)];
+
+        let response = self.client
+            .post(&url)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))))
+            .form(&params)
+            .send()
+            .await?;
+
+        let response = SonarQubeError::check(response).await?;
+        let created: QualityProfileCreateResponse = response.json().await?;
+        Ok(created.profile)
+    }
+
+    pub async fn copy_quality_profile(&self, from_key: &str, to_name: &str) -> Result<QualityProfile> {
+        let url = format!("{}/api/qualityprofiles/copy", self.base_url);
+        let params = [("fromKey", from_key), ("toName", to_name)];
+
+        let response = self.client
+            .post(&url)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))))
+            .form(&params)
+            .send()
+            .await?;
+
+        let response = SonarQubeError::check(response).await?;
+        let copied: QualityProfile = response.json().await?;
+        Ok(copied)
+    }
+
+    // A missing parent makes the profile stop inheriting
+    pub async fn change_quality_profile_parent(&self, name: &str, language: &str, parent_name: Option<&str>) -> Result<()> {
+        let url = format!("{}/api/qualityprofiles/change_parent", self.base_url);
+        let params = [
+            ("qualityProfile", name),
+            ("language", language),
+            ("parentQualityProfile", parent_name.unwrap_or("")),
+        ];
+
+        let response = self.client
+            .post(&url)
             .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))))
+            .form(&params)
             .send()
             .await?;
 
-        if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow::anyhow!("Failed to get quality gate details: {}", error_text));
+        SonarQubeError::check(response).await?;
+
+        Ok(())
+    }
+
+    pub async fn activate_quality_profile_rule(&self, profile_key: &str, rule_key: &str, severity: Option<&str>) -> Result<()> {
+        let url = format!("{}/api/qualityprofiles/activate_rule", self.base_url);
+        let mut params = vec![("key", profile_key), ("rule", rule_key)];
+        if let Some(severity) = severity {
+            params.push(("severity", severity));
         }
 
-        let details: serde_json::Value = response.json().await?;
-        Ok(details)
+        let response = self.client
+            .post(&url)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))))
+            .form(&params)
+            .send()
+            .await?;
+
+        SonarQubeError::check(response).await?;
+
+        Ok(())
+    }
+
+    pub async fn deactivate_quality_profile_rule(&self, profile_key: &str, rule_key: &str) -> Result<()> {
+        let url = format!("{}/api/qualityprofiles/deactivate_rule", self.base_url);
+        let params = [("key", profile_key), ("rule", rule_key)];
+
+        let response = self.client
+            .post(&url)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))))
+            .form(&params)
+            .send()
+            .await?;
+
+        SonarQubeError::check(response).await?;
+
+        Ok(())
+    }
+
+    pub async fn add_project_to_quality_profile(&self, project_key: &str, language: &str, profile_name: &str) -> Result<()> {
+        let url = format!("{}/api/qualityprofiles/add_project", self.base_url);
+        let params = [("project", project_key), ("language", language), ("qualityProfile", profile_name)];
+
+        let response = self.client
+            .post(&url)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))))
+            .form(&params)
+            .send()
+            .await?;
+
+        SonarQubeError::check(response).await?;
+
+        Ok(())
+    }
+
+    pub async fn select_quality_gate(&self, project_key: &str, gate_name: &str) -> Result<()> {
+        let url = format!("{}/api/qualitygates/select", self.base_url);
+        let params = [("projectKey", project_key), ("gateName", gate_name)];
+
+        let response = self.client
+            .post(&url)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))))
+            .form(&params)
+            .send()
+            .await?;
+
+        SonarQubeError::check(response).await?;
+
+        Ok(())
+    }
+
+    // The project falls back to the default gate
+    pub async fn deselect_quality_gate(&self, project_key: &str) -> Result<()> {
+        let url = format!("{}/api/qualitygates/deselect", self.base_url);
+        let params = [("projectKey", project_key)];
+
+        let response = self.client
+            .post(&url)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))))
+            .form(&params)
+            .send()
+            .await?;
+
+        SonarQubeError::check(response).await?;
+
+        Ok(())
+    }
+
+    pub async fn get_quality_gate_by_project(&self, project_key: &str) -> Result<ProjectQualityGate> {
+        let url = format!("{}/api/qualitygates/get_by_project", self.base_url);
+        let params = [("project", project_key)];
+
+        let request = self.client
+            .get(&url)
+            .query(&params)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))));
+
+        let response = self.send_with_retry(request).await?;
+        let response = SonarQubeError::check(response).await?;
+
+        let gate: ProjectQualityGateResponse = response.json().await?;
+        Ok(gate.quality_gate)
+    }
+
+    // Settings APIs
+    pub async fn list_setting_definitions(&self, component_key: &str) -> Result<Vec<SettingDefinition>> {
+        let url = format!("{}/api/settings/list_definitions", self.base_url);
+        let params = [("component", component_key)];
+
+        let request = self.client
+            .get(&url)
+            .query(&params)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))));
+
+        let response = self.send_with_retry(request).await?;
+        let response = SonarQubeError::check(response).await?;
+
+        let definitions: SettingDefinitionsResponse = response.json().await?;
+        Ok(definitions.definitions)
+    }
+
+    pub async fn get_settings(&self, component_key: &str, keys: &[String]) -> Result<Vec<SettingValue>> {
+        let url = format!("{}/api/settings/values", self.base_url);
+        let params = [("component", component_key.to_string()), ("keys", keys.join(","))];
+
+        let request = self.client
+            .get(&url)
+            .query(&params)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))));
+
+        let response = self.send_with_retry(request).await?;
+        let response = SonarQubeError::check(response).await?;
+
+        let values: SettingValuesResponse = response.json().await?;
+        Ok(values.settings)
+    }
+
+    pub async fn set_setting(&self, component_key: &str, key: &str, value: &SettingInput) -> Result<()> {
+        let url = format!("{}/api/settings/set", self.base_url);
+        let mut params = vec![("component", component_key.to_string()), ("key", key.to_string())];
+        match value {
+            SettingInput::Single(value) => params.push(("value", value.clone())),
+            // Multi-value settings repeat the `values` parameter
+            SettingInput::Multiple(values) => params.extend(values.iter().map(|value| ("values", value.clone()))),
+        }
+
+        let response = self.client
+            .post(&url)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))))
+            .form(&params)
+            .send()
+            .await?;
+
+        SonarQubeError::check(response).await?;
+
+        Ok(())
+    }
+
+    pub async fn reset_settings(&self, component_key: &str, keys: &[String]) -> Result<()> {
+        let url = format!("{}/api/settings/reset", self.base_url);
+        let params = [("component", component_key.to_string()), ("keys", keys.join(","))];
+
+        let response = self.client
+            .post(&url)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))))
+            .form(&params)
+            .send()
+            .await?;
+
+        SonarQubeError::check(response).await?;
+
+        Ok(())
+    }
+
+    // New code period APIs
+    pub async fn get_new_code_period(&self, project_key: &str, branch: Option<&str>) -> Result<NewCodePeriod> {
+        let url = format!("{}/api/new_code_periods/show", self.base_url);
+        let mut params = vec![("project", project_key)];
+        if let Some(branch) = branch {
+            params.push(("branch", branch));
+        }
+
+        let request = self.client
+            .get(&url)
+            .query(&params)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))));
+
+        let response = self.send_with_retry(request).await?;
+        let response = SonarQubeError::check(response).await?;
+
+        let period: NewCodePeriod = response.json().await?;
+        Ok(period)
+    }
+
+    pub async fn set_new_code_period(&self, project_key: &str, branch: Option<&str>, period_type: &str, value: Option<&str>) -> Result<()> {
+        let url = format!("{}/api/new_code_periods/set", self.base_url);
+        let mut params = vec![("project", project_key), ("type", period_type)];
+        if let Some(branch) = branch {
+            params.push(("branch", branch));
+        }
+        if let Some(value) = value {
+            params.push(("value", value));
+        }
+
+        let response = self.client
+            .post(&url)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))))
+            .form(&params)
+            .send()
+            .await?;
+
+        SonarQubeError::check(response).await?;
+
+        Ok(())
+    }
+
+    // The project or branch falls back to the inherited period
+    pub async fn unset_new_code_period(&self, project_key: &str, branch: Option<&str>) -> Result<()> {
+        let url = format!("{}/api/new_code_periods/unset", self.base_url);
+        let mut params = vec![("project", project_key)];
+        if let Some(branch) = branch {
+            params.push(("branch", branch));
+        }
+
+        let response = self.client
+            .post(&url)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))))
+            .form(&params)
+            .send()
+            .await?;
+
+        SonarQubeError::check(response).await?;
+
+        Ok(())
+    }
+
+    // Permission APIs
+    // Users holding at least one permission on the project
+    pub async fn get_project_user_permissions(&self, project_key: &str) -> Result<Vec<UserPermissions>> {
+        let url = format!("{}/api/permissions/users", self.base_url);
+        let mut users = Vec::new();
+        let mut page = 1;
+
+        loop {
+            let params = [
+                ("projectKey", project_key.to_string()),
+                ("p", page.to_string()),
+                ("ps", PERMISSIONS_PAGE_SIZE.to_string()),
+            ];
+            let request = self.client
+                .get(&url)
+                .query(&params)
+                .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))));
+
+            let response = self.send_with_retry(request).await?;
+            let response = SonarQubeError::check(response).await?;
+            let users_page: UserPermissionsResponse = response.json().await?;
+
+            let fetched = users_page.users.len() as i32;
+            users.extend(users_page.users);
+            if fetched == 0 || page * PERMISSIONS_PAGE_SIZE >= users_page.paging.total {
+                break;
+            }
+            page += 1;
+        }
+
+        Ok(users)
+    }
+
+    // Groups holding at least one permission on the project
+    pub async fn get_project_group_permissions(&self, project_key: &str) -> Result<Vec<GroupPermissions>> {
+        let url = format!("{}/api/permissions/groups", self.base_url);
+        let mut groups = Vec::new();
+        let mut page = 1;
+
+        loop {
+            let params = [
+                ("projectKey", project_key.to_string()),
+                ("p", page.to_string()),
+                ("ps", PERMISSIONS_PAGE_SIZE.to_string()),
+            ];
+            let request = self.client
+                .get(&url)
+                .query(&params)
+                .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))));
+
+            let response = self.send_with_retry(request).await?;
+            let response = SonarQubeError::check(response).await?;
+            let groups_page: GroupPermissionsResponse = response.json().await?;
+
+            let fetched = groups_page.groups.len() as i32;
+            groups.extend(groups_page.groups);
+            if fetched == 0 || page * PERMISSIONS_PAGE_SIZE >= groups_page.paging.total {
+                break;
+            }
+            page += 1;
+        }
+
+        Ok(groups)
+    }
+
+    pub async fn add_user_permission(&self, project_key: &str, login: &str, permission: &str) -> Result<()> {
+        self.change_permission("add_user", project_key, ("login", login), permission).await
+    }
+
+    pub async fn remove_user_permission(&self, project_key: &str, login: &str, permission: &str) -> Result<()> {
+        self.change_permission("remove_user", project_key, ("login", login), permission).await
+    }
+
+    pub async fn add_group_permission(&self, project_key: &str, group_name: &str, permission: &str) -> Result<()> {
+        self.change_permission("add_group", project_key, ("groupName", group_name), permission).await
+    }
+
+    pub async fn remove_group_permission(&self, project_key: &str, group_name: &str, permission: &str) -> Result<()> {
+        self.change_permission("remove_group", project_key, ("groupName", group_name), permission).await
+    }
+
+    // The four permission write APIs only differ by action and holder parameter
+    async fn change_permission(&self, action: &str, project_key: &str, holder: (&str, &str), permission: &str) -> Result<()> {
+        let url = format!("{}/api/permissions/{}", self.base_url, action);
+        let params = [("projectKey", project_key), holder, ("permission", permission)];
+
+        let response = self.client
+            .post(&url)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))))
+            .form(&params)
+            .send()
+            .await?;
+
+        SonarQubeError::check(response).await?;
+
+        Ok(())
+    }
+
+    pub async fn find_permission_template(&self, template_name: &str) -> Result<Option<PermissionTemplate>> {
+        let url = format!("{}/api/permissions/search_templates", self.base_url);
+        let params = [("q", template_name)];
+
+        let request = self.client
+            .get(&url)
+            .query(&params)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))));
+
+        let response = self.send_with_retry(request).await?;
+        let response = SonarQubeError::check(response).await?;
+
+        // `q` is a substring search
+        let templates: PermissionTemplatesResponse = response.json().await?;
+        Ok(templates.permission_templates.into_iter().find(|template| template.name == template_name))
+    }
+
+    // Replaces the project's permissions with the template's
+    pub async fn apply_permission_template(&self, project_key: &str, template_name: &str) -> Result<()> {
+        let url = format!("{}/api/permissions/apply_template", self.base_url);
+        let params = [("projectKey", project_key), ("templateName", template_name)];
+
+        let response = self.client
+            .post(&url)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))))
+            .form(&params)
+            .send()
+            .await?;
+
+        SonarQubeError::check(response).await?;
+
+        Ok(())
+    }
+
+    // Webhook APIs
+    // Without a project key, the global webhooks are listed
+    pub async fn list_webhooks(&self, project_key: Option<&str>) -> Result<Vec<Webhook>> {
+        let url = format!("{}/api/webhooks/list", self.base_url);
+        let params: Vec<(&str, &str)> = project_key.map(|key| ("project", key)).into_iter().collect();
+
+        let request = self.client
+            .get(&url)
+            .query(&params)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))));
+
+        let response = self.send_with_retry(request).await?;
+        let response = SonarQubeError::check(response).await?;
+
+        let webhooks: WebhooksResponse = response.json().await?;
+        Ok(webhooks.webhooks)
+    }
+
+    pub async fn create_webhook(&self, name: &str, webhook_url: &str, project_key: Option<&str>, secret: Option<&str>) -> Result<Webhook> {
+        let url = format!("{}/api/webhooks/create", self.base_url);
+        let mut params = vec![("name", name), ("url", webhook_url)];
+        if let Some(project_key) = project_key {
+            params.push(("project", project_key));
+        }
+        if let Some(secret) = secret {
+            params.push(("secret", secret));
+        }
+
+        let response = self.client
+            .post(&url)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))))
+            .form(&params)
+            .send()
+            .await?;
+
+        let response = SonarQubeError::check(response).await?;
+        let created: WebhookCreateResponse = response.json().await?;
+        Ok(created.webhook)
+    }
+
+    pub async fn delete_webhook(&self, webhook_key: &str) -> Result<()> {
+        let url = format!("{}/api/webhooks/delete", self.base_url);
+        let params = [("webhook", webhook_key)];
+
+        let response = self.client
+            .post(&url)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))))
+            .form(&params)
+            .send()
+            .await?;
+
+        SonarQubeError::check(response).await?;
+
+        Ok(())
+    }
+
+    // Security hotspots APIs
+    pub async fn search_hotspots(&self, project_key: &str, target: &AnalysisTarget, status: Option<&str>, resolution: Option<&str>) -> Result<HotspotsResponse> {
+        let url = format!("{}/api/hotspots/search", self.base_url);
+
+        let mut filters = vec![("projectKey", project_key.to_string())];
+        filters.extend(target.params());
+        if let Some(status) = status {
+            filters.push(("status", status.to_string()));
+        }
+        if let Some(resolution) = resolution {
+            filters.push(("resolution", resolution.to_string()));
+        }
+
+        let mut hotspots = Vec::new();
+        let mut page = 1;
+
+        loop {
+            let mut params = filters.clone();
+            params.push(("p", page.to_string()));
+            params.push(("ps", HOTSPOTS_PAGE_SIZE.to_string()));
+
+            let request = self.client
+                .get(&url)
+                .query(&params)
+                .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))));
+
+            let response = self.send_with_retry(request).await?;
+            let response = SonarQubeError::check(response).await?;
+
+            let hotspots_page: HotspotsResponse = response.json().await?;
+            let total = hotspots_page.paging.total;
+            let fetched = hotspots_page.hotspots.len();
+            hotspots.extend(hotspots_page.hotspots);
+
+            // Like issues, hotspots can't be paged past the search cap
+            let capped = page * HOTSPOTS_PAGE_SIZE >= ISSUES_SEARCH_LIMIT;
+            if fetched == 0 || capped || hotspots.len() as i32 >= total {
+                let fetched_total = hotspots.len() as i32;
+                if fetched_total < total {
+                    warn!("Hotspot search for {} returned {} of {} results", project_key, fetched_total, total);
+                }
+                return Ok(HotspotsResponse {
+                    hotspots,
+                    paging: Paging {
+                        page_index: 1,
+                        page_size: fetched_total,
+                        total,
+                    },
+                    complete: fetched_total >= total,
+                });
+            }
+            page += 1;
+        }
+    }
+
+    pub async fn get_hotspot(&self, hotspot_key: &str) -> Result<serde_json::Value> {
+        let url = format!("{}/api/hotspots/show", self.base_url);
+        let params = [("hotspot", hotspot_key)];
+
+        let request = self.client
+            .get(&url)
+            .query(&params)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))));
+
+        let response = self.send_with_retry(request).await?;
+        let response = SonarQubeError::check(response).await?;
+
+        let details: serde_json::Value = response.json().await?;
+        Ok(details)
+    }
+
+    // Rules APIs
+    pub async fn get_rule(&self, rule_key: &str) -> Result<Rule> {
+        let url = format!("{}/api/rules/show", self.base_url);
+        let params = [("key", rule_key)];
+
+        let request = self.client
+            .get(&url)
+            .query(&params)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))));
+
+        let response = self.send_with_retry(request).await?;
+        let response = SonarQubeError::check(response).await?;
+
+        let rule_response: RuleShowResponse = response.json().await?;
+        Ok(rule_response.rule)
+    }
+
+    // Fetches every rule of a repository such as "java" or "javascript"
+    pub async fn search_rules_by_repository(&self, repository: &str) -> Result<Vec<Rule>> {
+        let url = format!("{}/api/rules/search", self.base_url);
+        let mut rules = Vec::new();
+        let mut page = 1;
+
+        loop {
+            let params = [
+                ("repositories", repository.to_string()),
+                ("p", page.to_string()),
+                ("ps", RULES_PAGE_SIZE.to_string()),
+            ];
+
+            let request = self.client
+                .get(&url)
+                .query(&params)
+                .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))));
+
+            let response = self.send_with_retry(request).await?;
+            let response = SonarQubeError::check(response).await?;
+
+            let rules_page: RulesSearchResponse = response.json().await?;
+            let total = rules_page.paging.map(|p| p.total).or(rules_page.total).unwrap_or(0);
+            let fetched = rules_page.rules.len();
+            rules.extend(rules_page.rules);
+
+            if fetched == 0 || rules.len() as i32 >= total {
+                return Ok(rules);
+            }
+            page += 1;
+        }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    // Issues by key, in chunks so the query string stays short
+    pub async fn get_issues_by_keys(&self, issue_keys: &[String], target: &AnalysisTarget) -> Result<Vec<Issue>> {
+        let mut issues = Vec::new();
+
+        for chunk in issue_keys.chunks(ISSUE_KEYS_PER_SEARCH) {
+            let mut filters: IssueFilters = vec![("issues", chunk.join(","))];
+            filters.extend(target.params());
+            let issues_page = self.search_issues_page(&filters, 1, ISSUES_PAGE_SIZE, None).await?;
+            issues.extend(issues_page.issues);
+        }
+
+        Ok(issues)
+    }
+
+    pub async fn do_issue_transition(&self, issue_key: &str, transition: &str) -> Result<Issue> {
+        let url = format!("{}/api/issues/do_transition", self.base_url);
+        let params = [("issue", issue_key), ("transition", transition)];
+
+        let response = self.client
+            .post(&url)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))))
+            .form(&params)
+            .send()
+            .await?;
+
+        let response = SonarQubeError::check(response).await?;
+        let change: IssueChangeResponse = response.json().await?;
+        Ok(change.issue)
+    }
+
+    pub async fn add_issue_comment(&self, issue_key: &str, text: &str) -> Result<Issue> {
+        let url = format!("{}/api/issues/add_comment", self.base_url);
+        let params = [("issue", issue_key), ("text", text)];
+
+        let response = self.client
+            .post(&url)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))))
+            .form(&params)
+            .send()
+            .await?;
+
+        let response = SonarQubeError::check(response).await?;
+        let change: IssueChangeResponse = response.json().await?;
+        Ok(change.issue)
+    }
+
+    // An empty or missing assignee unassigns the issue
+    pub async fn assign_issue(&self, issue_key: &str, assignee: Option<&str>) -> Result<Issue> {
+        let url = format!("{}/api/issues/assign", self.base_url);
+        let params = [("issue", issue_key), ("assignee", assignee.unwrap_or(""))];
+
+        let response = self.client
+            .post(&url)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))))
+            .form(&params)
+            .send()
+            .await?;
+
+        let response = SonarQubeError::check(response).await?;
+        let change: IssueChangeResponse = response.json().await?;
+        Ok(change.issue)
+    }
+
+    pub async fn edit_issue_comment(&self, comment_key: &str, text: &str) -> Result<Issue> {
+        let url = format!("{}/api/issues/edit_comment", self.base_url);
+        let params = [("comment", comment_key), ("text", text)];
+
+        let response = self.client
+            .post(&url)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))))
+            .form(&params)
+            .send()
+            .await?;
+
+        let response = SonarQubeError::check(response).await?;
+        let change: IssueChangeResponse = response.json().await?;
+        Ok(change.issue)
+    }
+
+    pub async fn delete_issue_comment(&self, comment_key: &str) -> Result<Issue> {
+        let url = format!("{}/api/issues/delete_comment", self.base_url);
+        let params = [("comment", comment_key)];
+
+        let response = self.client
+            .post(&url)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))))
+            .form(&params)
+            .send()
+            .await?;
+
+        let response = SonarQubeError::check(response).await?;
+        let change: IssueChangeResponse = response.json().await?;
+        Ok(change.issue)
+    }
+
+    // Applies the change to at most ISSUE_BULK_CHANGE_LIMIT issues
+    pub async fn bulk_change_issues(&self, issue_keys: &[String], change: &BulkChange) -> Result<BulkChangeResponse> {
+        let url = format!("{}/api/issues/bulk_change", self.base_url);
+        let mut params = vec![
+            ("issues", issue_keys.join(",")),
+            ("sendNotifications", "false".to_string()),
+        ];
+        params.extend(change.params());
+
+        let response = self.client
+            .post(&url)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))))
+            .form(&params)
+            .send()
+            .await?;
+
+        let response = SonarQubeError::check(response).await?;
+        let bulk_response: BulkChangeResponse = response.json().await?;
+        Ok(bulk_response)
+    }
+
+    // Source APIs
+    pub async fn get_source_raw(&self, component_key: &str, target: &AnalysisTarget) -> Result<String> {
+        let url = format!("{}/api/sources/raw", self.base_url);
+        let mut params = vec![("key", component_key.to_string())];
+        params.extend(target.params());
+
+        let request = self.client
+            .get(&url)
+            .query(&params)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))));
+
+        let response = self.send_with_retry(request).await?;
+        let response = SonarQubeError::check(response).await?;
+
+        Ok(response.text().await?)
+    }
+
+    // Compute Engine APIs
+    pub async fn get_ce_component(&self, component_key: &str) -> Result<CeComponentResponse> {
+        let url = format!("{}/api/ce/component", self.base_url);
+        let params = [("component", component_key)];
+
+        let request = self.client
+            .get(&url)
+            .query(&params)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))));
+
+        let response = self.send_with_retry(request).await?;
+        let response = SonarQubeError::check(response).await?;
+
+        let component_response: CeComponentResponse = response.json().await?;
+        Ok(component_response)
+    }
+
+    pub async fn get_ce_task(&self, task_id: &str) -> Result<CeTask> {
+        let url = format!("{}/api/ce/task", self.base_url);
+        let params = [("id", task_id)];
+
+        let request = self.client
+            .get(&url)
+            .query(&params)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))));
+
+        let response = self.send_with_retry(request).await?;
+        let response = SonarQubeError::check(response).await?;
+
+        let task_response: CeTaskResponse = response.json().await?;
+        Ok(task_response.task)
+    }
+
+    // Most recent tasks of a component first
+    pub async fn get_ce_activity(&self, component_key: &str, status: Option<&str>, page_size: i32) -> Result<Vec<CeTask>> {
+        let url = format!("{}/api/ce/activity", self.base_url);
+
+        let mut params = vec![
+            ("component", component_key.to_string()),
+            ("ps", page_size.to_string()),
+        ];
+        if let Some(status) = status {
+            params.push(("status", status.to_string()));
+        }
+
+        let request = self.client
+            .get(&url)
+            .query(&params)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))));
+
+        let response = self.send_with_retry(request).await?;
+        let response = SonarQubeError::check(response).await?;
+
+        let activity_response: CeActivityResponse = response.json().await?;
+        Ok(activity_response.tasks)
+    }
+
+    pub async fn get_analysis_quality_gate(&self, analysis_id: &str) -> Result<QualityGateResponse> {
+        let url = format!("{}/api/qualitygates/project_status", self.base_url);
+        let params = [("analysisId", analysis_id)];
+
+        let request = self.client
+            .get(&url)
+            .query(&params)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))));
+
+        let response = self.send_with_retry(request).await?;
+        let response = SonarQubeError::check(response).await?;
+
+        let quality_gate_response: QualityGateResponse = response.json().await?;
+        Ok(quality_gate_response)
+    }
+
+    // Branch and pull request APIs
+    pub async fn list_branches(&self, project_key: &str) -> Result<Vec<ProjectBranch>> {
+        let url = format!("{}/api/project_branches/list", self.base_url);
+        let params = [("project", project_key)];
+
+        let request = self.client
+            .get(&url)
+            .query(&params)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))));
+
+        let response = self.send_with_retry(request).await?;
+        let response = SonarQubeError::check(response).await?;
+
+        let branches_response: ProjectBranchesResponse = response.json().await?;
+        Ok(branches_response.branches)
+    }
+
+    pub async fn list_pull_requests(&self, project_key: &str) -> Result<Vec<ProjectPullRequest>> {
+        let url = format!("{}/api/project_pull_requests/list", self.base_url);
+        let params = [("project", project_key)];
+
+        let request = self.client
+            .get(&url)
+            .query(&params)
+            .header("Authorization", format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:", self.admin_token))));
+
+        let response = self.send_with_retry(request).await?;
+        let response = SonarQubeError::check(response).await?;
+
+        let pull_requests_response: ProjectPullRequestsResponse = response.json().await?;
+        Ok(pull_requests_response.pull_requests)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn issue(key: &str) -> Issue {
+        serde_json::from_value(serde_json::json!({
+            "key": key,
+            "rule": "java:S1",
+            "severity": "MAJOR",
+            "component": "demo:src/Main.java",
+            "project": "demo",
+            "message": "m",
+            "status": "OPEN",
+            "creationDate": "2024-01-01T00:00:00+0000",
+            "updateDate": "2024-01-01T00:00:00+0000",
+            "tags": [],
+            "type": "BUG"
+        }))
+        .unwrap()
+    }
+
+    fn date(value: &str) -> DateTime<Utc> {
+        SonarQubeClient::parse_sonar_date(value).unwrap()
+    }
+
+    fn filter<'a>(filters: &'a [(&'static str, String)], name: &str) -> Vec<&'a str> {
+        filters.iter().filter(|(key, _)| *key == name).map(|(_, value)| value.as_str()).collect()
+    }
+
+    #[test]
+    fn page_count_stops_at_the_search_cap() {
+        assert_eq!(issue_page_count(0), 0);
+        assert_eq!(issue_page_count(1), 1);
+        assert_eq!(issue_page_count(ISSUES_PAGE_SIZE), 1);
+        assert_eq!(issue_page_count(ISSUES_PAGE_SIZE + 1), 2);
+        assert_eq!(issue_page_count(ISSUES_SEARCH_LIMIT), 20);
+        assert_eq!(issue_page_count(ISSUES_SEARCH_LIMIT + 1), 20);
+        assert_eq!(issue_page_count(250_000), 20);
+    }
+
+    #[test]
+    fn splits_by_type_then_severity() {
+        let base: IssueFilters = vec![("componentKeys", "demo".to_string())];
+
+        let by_type = split_issue_category(&base).unwrap();
+        assert_eq!(by_type.len(), ISSUE_TYPES.len());
+        for (slice, issue_type) in by_type.iter().zip(ISSUE_TYPES) {
+            assert_eq!(filter(slice, "componentKeys"), ["demo"]);
+            assert_eq!(filter(slice, "types"), [issue_type]);
+        }
+
+        let by_severity = split_issue_category(&by_type[0]).unwrap();
+        assert_eq!(by_severity.len(), ISSUE_SEVERITIES.len());
+        for (slice, severity) in by_severity.iter().zip(ISSUE_SEVERITIES) {
+            assert_eq!(filter(slice, "types"), ["BUG"]);
+            assert_eq!(filter(slice, "severities"), [severity]);
+        }
+
+        assert!(split_issue_category(&by_severity[0]).is_none());
+    }
+
+    #[test]
+    fn creation_window_halves_into_adjacent_slices() {
+        let filters: IssueFilters = vec![
+            ("types", "BUG".to_string()),
+            ("createdAfter", "2024-01-01T00:00:00+0000".to_string()),
+            ("createdBefore", "2024-01-01T00:00:10+0000".to_string()),
+        ];
+        let slices = split_creation_window(&filters, date("2024-01-01T00:00:00+0000"), date("2024-01-01T00:00:11+0000")).unwrap();
+
+        assert_eq!(slices.len(), 2);
+        for slice in &slices {
+            assert_eq!(filter(slice, "types"), ["BUG"]);
+            // The previous window is replaced, not narrowed by a second pair
+            assert_eq!(filter(slice, "createdAfter").len(), 1);
+            assert_eq!(filter(slice, "createdBefore").len(), 1);
+        }
+        assert_eq!(filter(&slices[0], "createdAfter"), ["2024-01-01T00:00:00+0000"]);
+        assert_eq!(filter(&slices[0], "createdBefore"), ["2024-01-01T00:00:05+0000"]);
+        assert_eq!(filter(&slices[1], "createdAfter"), ["2024-01-01T00:00:05+0000"]);
+        assert_eq!(filter(&slices[1], "createdBefore"), ["2024-01-01T00:00:11+0000"]);
+    }
+
+    #[test]
+    fn one_second_window_is_not_split() {
+        let after = date("2024-01-01T00:00:00+0000");
+
+        assert!(split_creation_window(&[], after, after + Duration::seconds(1)).is_none());
+        assert!(split_creation_window(&[], after, after).is_none());
+        assert_eq!(split_creation_window(&[], after, after + Duration::seconds(2)).map(|slices| slices.len()), Some(2));
+    }
+
+    #[test]
+    fn empty_next_page_marks_the_list_incomplete() {
+        let mut collector = IssueCollector::new();
+        collector.add(vec![issue("A"), issue("B")]);
+        assert!(collector.add_next_page(vec![issue("B"), issue("C")]));
+        assert!(!collector.add_next_page(Vec::new()));
+
+        let response = collector.into_response(Some(4));
+        assert!(!response.complete);
+        let keys: Vec<_> = response.issues.iter().map(|issue| issue.key.as_str()).collect();
+        assert_eq!(keys, ["A", "B", "C"]);
+        assert_eq!(response.paging.page_size, 3);
+        assert_eq!(response.paging.total, 4);
+    }
+
+    #[test]
+    fn empty_first_page_keeps_the_list_complete() {
+        let mut collector = IssueCollector::new();
+        collector.add(Vec::new());
+
+        let response = collector.into_response(Some(0));
+        assert!(response.complete);
+        assert!(response.issues.is_empty());
+    }
+
+    fn retry_config() -> SonarClientConfig {
+        SonarClientConfig {
+            retry_base_delay: std::time::Duration::from_millis(200),
+            retry_max_delay: std::time::Duration::from_secs(5),
+            ..SonarClientConfig::default()
+        }
+    }
+
+    #[test]
+    fn only_get_requests_are_replayed() {
+        let client = Client::new();
+        let url = "http://sonar.example.com/api/issues/search";
+
+        assert!(replayable(&client.get(url).build().unwrap()).is_some());
+        for request in [client.post(url), client.put(url), client.delete(url), client.patch(url)] {
+            assert!(replayable(&request.form(&[("key", "value")]).build().unwrap()).is_none());
+        }
+    }
+
+    #[test]
+    fn retries_throttling_and_server_errors_only() {
+        assert!(is_retryable_status(StatusCode::TOO_MANY_REQUESTS));
+        assert!(is_retryable_status(StatusCode::INTERNAL_SERVER_ERROR));
+        assert!(is_retryable_status(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(!is_retryable_status(StatusCode::OK));
+        assert!(!is_retryable_status(StatusCode::BAD_REQUEST));
+        assert!(!is_retryable_status(StatusCode::UNAUTHORIZED));
+        assert!(!is_retryable_status(StatusCode::NOT_FOUND));
+    }
+
+    #[test]
+    fn parses_retry_after_seconds_and_dates() {
+        let now = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+        let in_ten_seconds = httpdate::fmt_http_date(now + std::time::Duration::from_secs(10));
+        let ten_seconds_ago = httpdate::fmt_http_date(now - std::time::Duration::from_secs(10));
+
+        assert_eq!(parse_retry_after("3", now), Some(std::time::Duration::from_secs(3)));
+        assert_eq!(parse_retry_after(" 0 ", now), Some(std::time::Duration::ZERO));
+        assert_eq!(parse_retry_after(&in_ten_seconds, now), Some(std::time::Duration::from_secs(10)));
+        assert_eq!(parse_retry_after(&ten_seconds_ago, now), Some(std::time::Duration::ZERO));
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[test]
+    fn retry_after_is_honoured_up_to_the_max_delay() {
+        let config = retry_config();
+
+        assert_eq!(retry_delay(&config, 0, Some(std::time::Duration::from_secs(2))), std::time::Duration::from_secs(2));
+        assert_eq!(retry_delay(&config, 0, Some(std::time::Duration::from_secs(120))), config.retry_max_delay);
+    }
+
+    #[test]
+    fn backoff_doubles_with_jitter_and_is_capped() {
+        let config = retry_config();
+
+        for attempt in 0..40 {
+            let full = config.retry_base_delay.saturating_mul(2u32.saturating_pow(attempt)).min(config.retry_max_delay);
+            for _ in 0..20 {
+                let delay = retry_delay(&config, attempt, None);
+                assert!(delay >= full / 2 && delay <= full, "attempt {}: {:?}", attempt, delay);
+                assert!(delay <= config.retry_max_delay);
+            }
+        }
+        // 200ms, 400ms, 800ms... reaches the 5s cap from the sixth attempt on
+        assert!(retry_delay(&config, 5, None) >= std::time::Duration::from_millis(2500));
     }
 }
@@ -0,0 +1,141 @@
+use reqwest::{Response, StatusCode};
+use serde::Deserialize;
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, SonarQubeError>;
+
+#[derive(Debug, Error)]
+pub enum SonarQubeError {
+    #[error("SonarQube rejected the token: {0}")]
+    Unauthorized(String),
+    #[error("Insufficient privileges in SonarQube: {0}")]
+    Forbidden(String),
+    #[error("Not found in SonarQube: {0}")]
+    NotFound(String),
+    #[error("Already exists in SonarQube: {0}")]
+    Conflict(String),
+    #[error("SonarQube rejected the request: {}", .0.join("; "))]
+    Validation(Vec<String>),
+    #[error("SonarQube returned {status}: {message}")]
+    Server { status: StatusCode, message: String },
+    #[error("Could not reach SonarQube: {0}")]
+    Transport(#[source] reqwest::Error),
+    #[error("Unexpected response from SonarQube: {0}")]
+    Decode(String),
+}
+
+// Error body returned by every SonarQube web service
+#[derive(Debug, Deserialize)]
+struct ErrorBody {
+    errors: Vec<ErrorMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorMessage {
+    msg: String,
+}
+
+impl SonarQubeError {
+    // Passes successful responses through and turns the others into a typed error
+    pub async fn check(response: Response) -> Result<Response> {
+        if response.status().is_success() {
+            return Ok(response);
+        }
+
+        let status = response.status();
+        let body = response.text().await?;
+        Err(Self::from_status(status, &body))
+    }
+
+    pub fn from_status(status: StatusCode, body: &str) -> Self {
+        let mut messages = match serde_json::from_str::<ErrorBody>(body) {
+            Ok(parsed) => parsed.errors.into_iter().map(|e| e.msg).collect(),
+            Err(_) if body.trim().is_empty() => Vec::new(),
+            Err(_) => vec![body.trim().to_string()],
+        };
+        if messages.is_empty() {
+            messages.push(status.canonical_reason().unwrap_or("no details").to_string());
+        }
+
+        match status {
+            StatusCode::UNAUTHORIZED => Self::Unauthorized(messages.join("; ")),
+            StatusCode::FORBIDDEN => Self::Forbidden(messages.join("; ")),
+            StatusCode::NOT_FOUND => Self::NotFound(messages.join("; ")),
+            StatusCode::CONFLICT => Self::Conflict(messages.join("; ")),
+            // SonarQube reports duplicates as a plain 400
+            StatusCode::BAD_REQUEST if messages.iter().any(|m| Self::is_duplicate_message(m)) => {
+                Self::Conflict(messages.join("; "))
+            }
+            StatusCode::BAD_REQUEST => Self::Validation(messages),
+            _ => Self::Server {
+                status,
+                message: messages.join("; "),
+            },
+        }
+    }
+
+    fn is_duplicate_message(message: &str) -> bool {
+        let message = message.to_lowercase();
+        message.contains("already exists") || message.contains("already been taken")
+    }
+}
+
+impl From<reqwest::Error> for SonarQubeError {
+    fn from(error: reqwest::Error) -> Self {
+        if error.is_decode() {
+            Self::Decode(error.to_string())
+        } else {
+            Self::Transport(error)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kind(error: &SonarQubeError) -> &'static str {
+        match error {
+            SonarQubeError::Unauthorized(_) => "unauthorized",
+            SonarQubeError::Forbidden(_) => "forbidden",
+            SonarQubeError::NotFound(_) => "not found",
+            SonarQubeError::Conflict(_) => "conflict",
+            SonarQubeError::Validation(_) => "validation",
+            SonarQubeError::Server { .. } => "server",
+            SonarQubeError::Transport(_) => "transport",
+            SonarQubeError::Decode(_) => "decode",
+        }
+    }
+
+    #[test]
+    fn maps_statuses_to_errors() {
+        let errors = r#"{"errors":[{"msg":"first"},{"msg":"second"}]}"#;
+        let cases = [
+            (StatusCode::BAD_REQUEST, errors, "validation", "SonarQube rejected the request: first; second"),
+            (StatusCode::BAD_REQUEST, r#"{"errors":[{"msg":"Project key already exists: demo"}]}"#, "conflict", "Already exists in SonarQube: Project key already exists: demo"),
+            (StatusCode::UNAUTHORIZED, "", "unauthorized", "SonarQube rejected the token: Unauthorized"),
+            (StatusCode::FORBIDDEN, errors, "forbidden", "Insufficient privileges in SonarQube: first; second"),
+            (StatusCode::NOT_FOUND, r#"{"errors":[{"msg":"Component not found"}]}"#, "not found", "Not found in SonarQube: Component not found"),
+            (StatusCode::CONFLICT, "", "conflict", "Already exists in SonarQube: Conflict"),
+            (StatusCode::TOO_MANY_REQUESTS, "", "server", "SonarQube returned 429 Too Many Requests: Too Many Requests"),
+            (StatusCode::INTERNAL_SERVER_ERROR, "<html>boom</html>", "server", "SonarQube returned 500 Internal Server Error: <html>boom</html>"),
+            (StatusCode::SERVICE_UNAVAILABLE, "  ", "server", "SonarQube returned 503 Service Unavailable: Service Unavailable"),
+        ];
+
+        for (status, body, expected_kind, expected_message) in cases {
+            let error = SonarQubeError::from_status(status, body);
+            assert_eq!(kind(&error), expected_kind, "{}", status);
+            assert_eq!(error.to_string(), expected_message, "{}", status);
+        }
+    }
+
+    #[test]
+    fn server_errors_keep_their_status() {
+        for status in [StatusCode::TOO_MANY_REQUESTS, StatusCode::BAD_GATEWAY, StatusCode::GATEWAY_TIMEOUT] {
+            match SonarQubeError::from_status(status, "") {
+                SonarQubeError::Server { status: kept, .. } => assert_eq!(kept, status),
+                other => panic!("{} mapped to {:?}", status, other),
+            }
+        }
+    }
+}
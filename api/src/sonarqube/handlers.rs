@@ -1,43 +1,210 @@
-use actix_web::{web, HttpResponse, Result};
-use crate::database::service::{CreateProjectRequest, ScanProjectRequest, ProjectService, CreateAdminTokenRequest};
-use crate::sonarqube::client::SonarQubeClient;
-use std::env;
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use actix_web::http::StatusCode;
+use crate::database::service::{
+    CreateProjectRequest, ScanProjectRequest, ProjectService, CreateAdminTokenRequest, ProjectResponse, UpdateProjectRequest,
+    NewSnapshot, SnapshotIssueFilter, SonarInstance, CreateSonarInstanceRequest, UpdateSonarInstanceRequest, GeneratedToken,
+};
+use crate::config::sonar::{rule_cache_max_age, snapshot_retention, token_expiry_days, webhook_secret, webhook_url};
+use crate::sonarqube::client::{
+    AnalysisTarget, BulkChange, BulkChangeResponse, CeTask, ComponentTreeQuery, Issue, Measure, ProjectQualityGate, ProjectStatus, RuleDetails,
+    SettingDefinition, SettingInput, SonarProject, SonarQubeClient,
+    ISSUE_BULK_CHANGE_LIMIT, ISSUE_SEVERITIES, ISSUE_TYPES,
+};
+use crate::sonarqube::error::SonarQubeError;
+use crate::sonarqube::snippets::{attach_snippets, SourceCache, DEFAULT_CONTEXT_LINES};
+use crate::sonarqube::webhooks::{verify_signature, WebhookPayload, SIGNATURE_HEADER, WEBHOOK_NAME};
+use chrono::{Days, NaiveDate, Utc};
+use globset::{GlobBuilder, GlobMatcher};
+use sea_orm::{DbErr, SqlErr};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
 use serde::{Deserialize, Serialize};
-use tracing::info;
+use tracing::{info, warn};
+
+// Repositories with more uncached rules than this are loaded with a single rules search
+const RULE_SEARCH_THRESHOLD: usize = 10;
+
+// Maps a SonarQube failure onto the status our own API should answer with
+fn sonar_error_status(error: &SonarQubeError) -> StatusCode {
+    match error {
+        SonarQubeError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+        SonarQubeError::Forbidden(_) => StatusCode::FORBIDDEN,
+        SonarQubeError::NotFound(_) => StatusCode::NOT_FOUND,
+        SonarQubeError::Conflict(_) => StatusCode::CONFLICT,
+        SonarQubeError::Validation(_) => StatusCode::BAD_REQUEST,
+        SonarQubeError::Transport(e) if e.is_timeout() => StatusCode::GATEWAY_TIMEOUT,
+        SonarQubeError::Server { .. } | SonarQubeError::Transport(_) | SonarQubeError::Decode(_) => StatusCode::BAD_GATEWAY,
+    }
+}
+
+fn sonar_error_response(context: &str, error: &SonarQubeError) -> HttpResponse {
+    let mut body = serde_json::json!({
+        "error": format!("{}: {}", context, error)
+    });
+    if let SonarQubeError::Validation(messages) = error {
+        body["sonar_errors"] = serde_json::json!(messages);
+    }
+    HttpResponse::build(sonar_error_status(error)).json(body)
+}
+
+async fn find_project(project_service: &ProjectService, project_path: &str) -> std::result::Result<ProjectResponse, HttpResponse> {
+    match project_service.get_project_by_path(project_path).await {
+        Ok(Some(project)) => Ok(project),
+        Ok(None) => Err(HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Project not found"
+        }))),
+        Err(e) => Err(HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Database error: {}", e)
+        }))),
+    }
+}
+
+fn invalid_target_response(target: &AnalysisTarget) -> Option<HttpResponse> {
+    (!target.is_valid()).then(|| {
+        HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "Use either branch or pull_request, not both"
+        }))
+    })
+}
+
+async fn find_sonar_instance(project_service: &ProjectService, instance_id: Option<i32>) -> std::result::Result<SonarInstance, HttpResponse> {
+    let instance = match instance_id {
+        Some(instance_id) => project_service.get_sonar_instance(instance_id).await,
+        None => project_service.get_default_sonar_instance().await,
+    };
+    match instance {
+        Ok(Some(instance)) => Ok(instance),
+        Ok(None) => Err(HttpResponse::NotFound().json(serde_json::json!({
+            "error": "SonarQube instance not found",
+            "suggestion": "Register it with POST /api/sonar-instances"
+        }))),
+        Err(e) => Err(HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Database error: {}", e)
+        }))),
+    }
+}
+
+// Builds a client for a SonarQube instance, the default one when `instance_id` is None,
+// authenticated with the instance's token of the given type
+async fn sonar_client_for(project_service: &ProjectService, instance_id: Option<i32>, token_type: &str) -> std::result::Result<SonarQubeClient, HttpResponse> {
+    let instance = find_sonar_instance(project_service, instance_id).await?;
+
+    match instance.token(token_type) {
+        Some(token) => SonarQubeClient::new(instance.url.clone(), token.to_string())
+            .map_err(|e| sonar_error_response("Failed to create SonarQube client", &e)),
+        None => {
+            let (field, privileges) = if token_type == "GLOBAL_ANALYSIS_TOKEN" {
+                ("analysis_token", "")
+            } else {
+                ("admin_token", " (must be created with a user that has admin privileges)")
+            };
+            Err(HttpResponse::BadRequest().json(serde_json::json!({
+                "error": format!("No {} found for SonarQube instance {}. Please create a {} first.", token_type, instance.name, token_type),
+                "suggestion": format!(
+                    "Use PUT /api/sonar-instances with id: {} and {}, or POST /api/admin-token with token_type: '{}'{}",
+                    instance.id, field, token_type, privileges
+                )
+            })))
+        }
+    }
+}
+
+// Attaches rule metadata to each issue, from the cache when it is fresh enough
+async fn enrich_issues_with_rules(sonar_client: &SonarQubeClient, project_service: &ProjectService, issues: &mut [Issue]) {
+    let sonar_host_url = sonar_client.base_url();
+    let mut rule_keys: Vec<String> = issues.iter().map(|issue| issue.rule.clone()).collect();
+    rule_keys.sort();
+    rule_keys.dedup();
+    if rule_keys.is_empty() {
+        return;
+    }
+
+    let max_age = chrono::Duration::from_std(rule_cache_max_age()).unwrap_or_default();
+    let fresh_since = Utc::now().naive_utc() - max_age;
+    let mut rules: HashMap<String, RuleDetails> = match project_service.get_cached_rules(sonar_host_url, &rule_keys, fresh_since).await {
+        Ok(cached) => cached.into_iter().map(|rule| (rule.key.clone(), rule)).collect(),
+        Err(e) => {
+            warn!("Failed to read the rule cache: {}", e);
+            HashMap::new()
+        }
+    };
+
+    let mut missing_by_repository: HashMap<&str, Vec<&String>> = HashMap::new();
+    for key in rule_keys.iter().filter(|key| !rules.contains_key(*key)) {
+        let repository = key.split_once(':').map(|(repository, _)| repository).unwrap_or(key);
+        missing_by_repository.entry(repository).or_default().push(key);
+    }
+
+    let mut fetched = Vec::new();
+    for (repository, keys) in missing_by_repository {
+        if keys.len() > RULE_SEARCH_THRESHOLD {
+            match sonar_client.search_rules_by_repository(repository).await {
+                Ok(repository_rules) => fetched.extend(repository_rules.into_iter().map(RuleDetails::from)),
+                Err(e) => warn!("Failed to search rules of repository {}: {}", repository, e),
+            }
+        } else {
+            for key in keys {
+                match sonar_client.get_rule(key).await {
+                    Ok(rule) => fetched.push(RuleDetails::from(rule)),
+                    Err(e) => warn!("Failed to fetch rule {}: {}", key, e),
+                }
+            }
+        }
+    }
+
+    if !fetched.is_empty() {
+        if let Err(e) = project_service.upsert_cached_rules(sonar_host_url, &fetched).await {
+            warn!("Failed to update the rule cache: {}", e);
+        }
+        rules.extend(fetched.into_iter().map(|rule| (rule.key.clone(), rule)));
+    }
+
+    for issue in issues.iter_mut() {
+        issue.rule_details = rules.get(&issue.rule).cloned();
+    }
+}
 
 pub async fn create_project(
     req: web::Json<CreateProjectRequest>,
     project_service: web::Data<ProjectService>,
 ) -> Result<HttpResponse> {
-    let sonar_host_url = env::var("SONAR_HOST_URL").unwrap_or_else(|_| "http://localhost:9000".to_string());
-    
+    let instance = match find_sonar_instance(&project_service, req.sonar_instance_id).await {
+        Ok(instance) => instance,
+        Err(response) => return Ok(response),
+    };
     // Get USER_TOKEN for admin operations (create/delete projects)
-    let admin_token = match project_service.get_admin_token_by_type(&sonar_host_url, "USER_TOKEN").await {
-        Ok(Some(token)) => token,
-        Ok(None) => {
-            return Ok(HttpResponse::BadRequest().json(serde_json::json!({
-                "error": "No USER_TOKEN found for this SonarQube instance. Please create a USER_TOKEN first.",
-                "suggestion": "Use POST /api/admin-token with token_type: 'USER_TOKEN' (must be created with a user that has admin privileges)"
-            })));
-        }
-        Err(e) => {
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": format!("Database error: {}", e)
-            })));
-        }
+    let sonar_client = match sonar_client_for(&project_service, Some(instance.id), "USER_TOKEN").await {
+        Ok(client) => client,
+        Err(response) => return Ok(response),
     };
-    
-    let sonar_client = SonarQubeClient::new(sonar_host_url.clone(), admin_token);
+
+    // Check the gate and template before anything is created, so a typo does not leave a half onboarded project
+    let quality_gate = req.quality_gate.clone();
+    if let Some(gate_name) = &quality_gate
+        && let Err(e) = sonar_client.get_quality_gate_details(gate_name).await
+    {
+        return Ok(sonar_error_response(&format!("Quality gate '{}' is not usable", gate_name), &e));
+    }
+    let permission_template = req.permission_template.clone();
+    if let Some(template_name) = &permission_template {
+        match sonar_client.find_permission_template(template_name).await {
+            Ok(Some(_)) => {}
+            Ok(None) => {
+                return Ok(HttpResponse::NotFound().json(serde_json::json!({
+                    "error": format!("Permission template not found: {}", template_name)
+                })));
+            }
+            Err(e) => return Ok(sonar_error_response("Failed to look up permission template", &e)),
+        }
+    }
 
     // Create project in SonarQube
     if let Err(e) = sonar_client.create_project(&req.project_key, &req.project_name).await {
-        return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": format!("Failed to create project in SonarQube: {}", e)
-        })));
+        return Ok(sonar_error_response("Failed to create project in SonarQube", &e));
     }
 
     // Create project in our database
-    let mut project_response = match project_service.create_project(req.into_inner()).await {
+    let mut project_response = match project_service.create_project(req.into_inner(), &instance).await {
         Ok(project) => project,
         Err(e) => {
             return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
@@ -47,31 +214,483 @@ pub async fn create_project(
     };
 
     // Create token for the project
-    match sonar_client.create_project_token(&project_response.project_key).await {
+    let token_name = format!("{}_token", project_response.project_key);
+    match generate_project_token(&sonar_client, &project_response.project_key, token_name, default_token_expiration()).await {
         Ok(token) => {
             // Update project with token
-            if let Err(e) = project_service.update_sonar_token(project_response.id, token.clone()).await {
+            if let Err(e) = project_service.update_sonar_token(project_response.id, &token).await {
                 return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
                     "error": format!("Failed to update project with token: {}", e)
                 })));
             }
-            project_response.sonar_token = token;
+            project_response.set_token(token);
         }
         Err(e) => {
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": format!("Failed to create project token: {}", e)
-            })));
+            return Ok(sonar_error_response("Failed to create project token", &e));
+        }
+    }
+
+    // Link the project to its gate
+    if let Some(gate_name) = quality_gate {
+        if let Err(e) = sonar_client.select_quality_gate(&project_response.project_key, &gate_name).await {
+            return Ok(sonar_error_response("Project created, but failed to select its quality gate", &e));
         }
+        project_response.quality_gate = Some(ProjectQualityGate {
+            name: gate_name,
+            default: false,
+        });
     }
 
+    if let Some(template_name) = permission_template
+        && let Err(e) = sonar_client.apply_permission_template(&project_response.project_key, &template_name).await
+    {
+        return Ok(sonar_error_response("Project created, but failed to apply its permission template", &e));
+    }
+
+    register_project_webhook(&sonar_client, &project_response.project_key).await;
+
     Ok(HttpResponse::Ok().json(project_response))
 }
 
+// Expiration date of generated tokens when the request gives none
+fn default_token_expiration() -> Option<NaiveDate> {
+    token_expiry_days().map(|days| Utc::now().date_naive() + Days::new(days))
+}
+
+fn invalid_expiration_response(expiration_date: Option<NaiveDate>) -> Option<HttpResponse> {
+    expiration_date.filter(|date| *date <= Utc::now().date_naive()).map(|_| {
+        HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "expiration_date must be in the future"
+        }))
+    })
+}
+
+async fn generate_project_token(
+    sonar_client: &SonarQubeClient,
+    project_key: &str,
+    token_name: String,
+    expiration_date: Option<NaiveDate>,
+) -> std::result::Result<GeneratedToken, SonarQubeError> {
+    let value = sonar_client.create_project_token(project_key, &token_name, expiration_date).await?;
+    Ok(GeneratedToken {
+        name: token_name,
+        value,
+        expiration_date,
+    })
+}
+
+// Token names are unique per user, so a rotated token gets the original name with a timestamp
+fn rotated_token_name(token_name: &str) -> String {
+    let base_name = match token_name.rsplit_once('-') {
+        Some((base_name, suffix)) if suffix.len() == 14 && suffix.bytes().all(|byte| byte.is_ascii_digit()) => base_name,
+        _ => token_name,
+    };
+    format!("{}-{}", base_name, Utc::now().format("%Y%m%d%H%M%S"))
+}
+
+// Points the project's webhook at our receiver when SONAR_WEBHOOK_URL is configured
+async fn register_project_webhook(sonar_client: &SonarQubeClient, project_key: &str) {
+    let Some(url) = webhook_url() else {
+        return;
+    };
+
+    match sonar_client.list_webhooks(Some(project_key)).await {
+        Ok(webhooks) if webhooks.iter().any(|webhook| webhook.url == url) => return,
+        Ok(_) => {}
+        Err(e) => warn!("Failed to list webhooks of {}: {}", project_key, e),
+    }
+
+    match sonar_client.create_webhook(WEBHOOK_NAME, &url, Some(project_key), webhook_secret().as_deref()).await {
+        Ok(webhook) => info!("Registered webhook {} for {}", webhook.key, project_key),
+        Err(e) => warn!("Failed to register webhook for {}: {}", project_key, e),
+    }
+}
+
+// SonarQube changes applied by update_project, undone in reverse order if a later step fails
+struct AppliedProjectChanges {
+    original_key: String,
+    current_key: String,
+    previous_visibility: Option<String>,
+}
+
+impl AppliedProjectChanges {
+    async fn revert(&self, sonar_client: &SonarQubeClient) -> Vec<String> {
+        let mut failures = Vec::new();
+        if let Some(visibility) = &self.previous_visibility
+            && let Err(e) = sonar_client.update_project_visibility(&self.current_key, visibility).await
+        {
+            failures.push(format!("Failed to restore visibility {}: {}", visibility, e));
+        }
+        if self.current_key != self.original_key
+            && let Err(e) = sonar_client.update_project_key(&self.current_key, &self.original_key).await
+        {
+            failures.push(format!("Failed to restore key {}: {}", self.original_key, e));
+        }
+        failures
+    }
+}
+
+fn failed_update_response(status: StatusCode, error: String, rollback_failures: Vec<String>) -> HttpResponse {
+    if !rollback_failures.is_empty() {
+        warn!("Project update left SonarQube and the database out of sync: {:?}", rollback_failures);
+    }
+    HttpResponse::build(status).json(serde_json::json!({
+        "error": error,
+        "rolled_back": rollback_failures.is_empty(),
+        "rollback_errors": rollback_failures
+    }))
+}
+
+pub async fn update_project(
+    req: web::Json<UpdateProjectRequest>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    let mut req = req.into_inner();
+    if req.is_empty() {
+        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "Nothing to update"
+        })));
+    }
+    let visibility = req.visibility.as_deref().map(str::to_lowercase);
+    if let Some(visibility) = &visibility
+        && visibility != "public"
+        && visibility != "private"
+    {
+        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "visibility must be 'public' or 'private'"
+        })));
+    }
+
+    let project = match find_project(&project_service, &req.project_path).await {
+        Ok(project) => project,
+        Err(response) => return Ok(response),
+    };
+    // A key equal to the current one is not a rename
+    req.new_project_key = req.new_project_key.take().filter(|key| *key != project.project_key);
+
+    let mut applied = AppliedProjectChanges {
+        original_key: project.project_key.clone(),
+        current_key: project.project_key.clone(),
+        previous_visibility: None,
+    };
+
+    // Only key and visibility changes touch SonarQube, the name is picked up on the next analysis
+    let sonar_client = if req.new_project_key.is_some() || visibility.is_some() {
+        match sonar_client_for(&project_service, project.sonar_instance_id, "USER_TOKEN").await {
+            Ok(client) => Some(client),
+            Err(response) => return Ok(response),
+        }
+    } else {
+        None
+    };
+
+    if let Some(sonar_client) = &sonar_client {
+        // Remember the visibility so it can be restored
+        let current_visibility = if visibility.is_some() {
+            match sonar_client.find_project(&project.project_key).await {
+                Ok(sonar_project) => sonar_project.and_then(|sonar_project| sonar_project.visibility),
+                Err(e) => return Ok(sonar_error_response("Failed to read project from SonarQube", &e)),
+            }
+        } else {
+            None
+        };
+
+        if let Some(new_key) = &req.new_project_key {
+            if let Err(e) = sonar_client.update_project_key(&project.project_key, new_key).await {
+                return Ok(sonar_error_response("Failed to rename project key in SonarQube", &e));
+            }
+            applied.current_key = new_key.clone();
+        }
+
+        if let Some(visibility) = &visibility
+            && current_visibility.as_ref() != Some(visibility)
+        {
+            if let Err(e) = sonar_client.update_project_visibility(&applied.current_key, visibility).await {
+                let rollback_failures = applied.revert(sonar_client).await;
+                return Ok(failed_update_response(
+                    sonar_error_status(&e),
+                    format!("Failed to change project visibility: {}", e),
+                    rollback_failures,
+                ));
+            }
+            applied.previous_visibility = current_visibility;
+        }
+    }
+
+    let (status, error) = match project_service.update_project(project.id, &req).await {
+        Ok(Some(updated)) => {
+            info!("Updated project {} ({})", updated.project_key, updated.project_path);
+            return Ok(HttpResponse::Ok().json(updated));
+        }
+        Ok(None) => (StatusCode::NOT_FOUND, "Project not found".to_string()),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update project in database: {}", e)),
+    };
+    let rollback_failures = match &sonar_client {
+        Some(sonar_client) => applied.revert(sonar_client).await,
+        None => Vec::new(),
+    };
+    Ok(failed_update_response(status, error, rollback_failures))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImportProjectsRequest {
+    #[serde(default)]
+    pub projects: Vec<ImportProjectEntry>,
+    // Import every unregistered project, using the entries above only to override what is inferred
+    pub all: Option<bool>,
+    // Inferred project paths are <projects_root>/<project_key>, the bare key otherwise
+    pub projects_root: Option<String>,
+    // Instance to import from, the default instance otherwise
+    pub sonar_instance_id: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImportableProjectsQuery {
+    pub sonar_instance_id: Option<i32>,
+}
+
+// Unset fields are inferred from SonarQube
+#[derive(Debug, Deserialize)]
+pub struct ImportProjectEntry {
+    pub project_key: String,
+    pub project_path: Option<String>,
+    pub language: Option<String>,
+    pub sources_path: Option<String>,
+    pub tests_path: Option<String>,
+    pub coverage_report_path: Option<String>,
+}
+
+// SonarQube projects with no matching row in our database
+async fn unregistered_projects(
+    project_service: &ProjectService,
+    sonar_client: &SonarQubeClient,
+) -> std::result::Result<(Vec<SonarProject>, HashSet<String>), HttpResponse> {
+    let registered: HashSet<String> = match project_service.get_all_projects().await {
+        Ok(projects) => projects.into_iter().map(|project| project.project_key).collect(),
+        Err(e) => {
+            return Err(HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            })));
+        }
+    };
+    let projects = match sonar_client.search_projects().await {
+        Ok(projects) => projects,
+        Err(e) => return Err(sonar_error_response("Failed to list SonarQube projects", &e)),
+    };
+    let unregistered = projects.into_iter().filter(|project| !registered.contains(&project.key)).collect();
+    Ok((unregistered, registered))
+}
+
+// Conventional source and test directories of a language
+fn default_source_layout(language: &str) -> (String, String) {
+    match language {
+        "java" | "kotlin" | "scala" | "groovy" => (format!("src/main/{}", language), format!("src/test/{}", language)),
+        "py" => ("src".to_string(), "tests".to_string()),
+        _ => ("src".to_string(), "test".to_string()),
+    }
+}
+
+pub async fn get_importable_projects(
+    query: web::Query<ImportableProjectsQuery>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    let sonar_client = match sonar_client_for(&project_service, query.sonar_instance_id, "USER_TOKEN").await {
+        Ok(client) => client,
+        Err(response) => return Ok(response),
+    };
+    let (projects, _) = match unregistered_projects(&project_service, &sonar_client).await {
+        Ok(projects) => projects,
+        Err(response) => return Ok(response),
+    };
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "total": projects.len(),
+        "projects": projects
+    })))
+}
+
+pub async fn import_projects(
+    req: web::Json<ImportProjectsRequest>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    let req = req.into_inner();
+    let import_all = req.all.unwrap_or(false);
+    if req.projects.is_empty() && !import_all {
+        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "Give the projects to import, or set all to true"
+        })));
+    }
+
+    let instance = match find_sonar_instance(&project_service, req.sonar_instance_id).await {
+        Ok(instance) => instance,
+        Err(response) => return Ok(response),
+    };
+    let sonar_client = match sonar_client_for(&project_service, Some(instance.id), "USER_TOKEN").await {
+        Ok(client) => client,
+        Err(response) => return Ok(response),
+    };
+    let (unregistered, registered) = match unregistered_projects(&project_service, &sonar_client).await {
+        Ok(projects) => projects,
+        Err(response) => return Ok(response),
+    };
+
+    // Requested projects first, in the given order, then the rest when importing everything
+    let mut keys: Vec<String> = Vec::new();
+    let mut entries: HashMap<String, ImportProjectEntry> = HashMap::new();
+    for entry in req.projects {
+        if !entries.contains_key(&entry.project_key) {
+            keys.push(entry.project_key.clone());
+        }
+        entries.insert(entry.project_key.clone(), entry);
+    }
+    if import_all {
+        keys.extend(unregistered.iter().filter(|project| !entries.contains_key(&project.key)).map(|project| project.key.clone()));
+    }
+    let sonar_projects: HashMap<&str, &SonarProject> = unregistered.iter().map(|project| (project.key.as_str(), project)).collect();
+
+    let mut results = Vec::new();
+    let mut imported = 0;
+    for key in keys {
+        if registered.contains(&key) {
+            results.push(serde_json::json!({ "project_key": key, "status": "skipped", "reason": "Already registered" }));
+            continue;
+        }
+        let Some(sonar_project) = sonar_projects.get(key.as_str()) else {
+            results.push(serde_json::json!({ "project_key": key, "status": "not_found" }));
+            continue;
+        };
+        let entry = entries.remove(&key);
+        match import_project(&project_service, &sonar_client, &instance, sonar_project, entry, req.projects_root.as_deref()).await {
+            Ok(project) => {
+                imported += 1;
+                results.push(serde_json::json!({ "project_key": key, "status": "imported", "project": project }));
+            }
+            Err(error) => {
+                warn!("Failed to import project {}: {}", key, error);
+                results.push(serde_json::json!({ "project_key": key, "status": "failed", "error": error }));
+            }
+        }
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "imported": imported,
+        "results": results
+    })))
+}
+
+// Registers one SonarQube project locally and generates its analysis token
+async fn import_project(
+    project_service: &ProjectService,
+    sonar_client: &SonarQubeClient,
+    instance: &SonarInstance,
+    sonar_project: &SonarProject,
+    entry: Option<ImportProjectEntry>,
+    projects_root: Option<&str>,
+) -> std::result::Result<ProjectResponse, String> {
+    let entry = entry.unwrap_or_else(|| ImportProjectEntry {
+        project_key: sonar_project.key.clone(),
+        project_path: None,
+        language: None,
+        sources_path: None,
+        tests_path: None,
+        coverage_report_path: None,
+    });
+
+    let language = match entry.language {
+        Some(language) => language,
+        None => match sonar_client.get_language_distribution(&sonar_project.key).await {
+            Ok(distribution) => match distribution.into_iter().next() {
+                Some((language, _)) => language,
+                None => return Err("No language given and the project has no analysis to infer it from".to_string()),
+            },
+            Err(e) => return Err(format!("Failed to infer language: {}", e)),
+        },
+    };
+    let (default_sources, default_tests) = default_source_layout(&language);
+    let project_path = entry.project_path.unwrap_or_else(|| match projects_root {
+        Some(root) => format!("{}/{}", root.trim_end_matches('/'), sonar_project.key),
+        None => sonar_project.key.clone(),
+    });
+
+    match project_service.get_project_by_path(&project_path).await {
+        Ok(None) => {}
+        Ok(Some(existing)) => return Err(format!("Project path {} is already used by {}", project_path, existing.project_key)),
+        Err(e) => return Err(format!("Database error: {}", e)),
+    }
+
+    let request = CreateProjectRequest {
+        project_key: sonar_project.key.clone(),
+        project_name: sonar_project.name.clone(),
+        project_path,
+        language,
+        sources_path: entry.sources_path.unwrap_or(default_sources),
+        tests_path: entry.tests_path.unwrap_or(default_tests),
+        coverage_report_path: entry.coverage_report_path,
+        quality_gate: None,
+        permission_template: None,
+        sonar_instance_id: Some(instance.id),
+    };
+    let mut project = project_service.create_project(request, instance).await
+        .map_err(|e| format!("Failed to create project in database: {}", e))?;
+
+    // Without a token the project cannot be analysed, so drop the row again
+    let token_name = format!("{}_token", project.project_key);
+    let token = match generate_project_token(sonar_client, &project.project_key, token_name, default_token_expiration()).await {
+        Ok(token) => token,
+        Err(e) => {
+            if let Err(db_error) = project_service.delete_project_by_path(&project.project_path).await {
+                warn!("Failed to remove {} after its token could not be created: {}", project.project_key, db_error);
+            }
+            return Err(format!("Failed to create project token: {}", e));
+        }
+    };
+    if let Err(e) = project_service.update_sonar_token(project.id, &token).await {
+        return Err(format!("Failed to update project with token: {}", e));
+    }
+    project.set_token(token);
+
+    register_project_webhook(sonar_client, &project.project_key).await;
+
+    Ok(project)
+}
+
+// Fills in the gate each project is evaluated against, one client per instance.
+// A project whose instance can't be read is listed without it.
+async fn attach_quality_gates(project_service: &ProjectService, projects: &mut [ProjectResponse]) {
+    let mut clients: HashMap<Option<i32>, Option<SonarQubeClient>> = HashMap::new();
+
+    for project in projects.iter_mut() {
+        if let Entry::Vacant(entry) = clients.entry(project.sonar_instance_id) {
+            entry.insert(sonar_client_for(project_service, project.sonar_instance_id, "USER_TOKEN").await.ok());
+        }
+        let Some(sonar_client) = clients.get(&project.sonar_instance_id).and_then(Option::as_ref) else {
+            continue;
+        };
+
+        match sonar_client.get_quality_gate_by_project(&project.project_key).await {
+            Ok(gate) => project.quality_gate = Some(gate),
+            Err(e) => warn!("Failed to get the quality gate of {}: {}", project.project_key, e),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProjectsQuery {
+    // Read each project's quality gate from SonarQube, one call per project
+    pub with_quality_gate: Option<bool>,
+}
+
 pub async fn get_all_projects(
+    query: web::Query<ProjectsQuery>,
     project_service: web::Data<ProjectService>,
 ) -> Result<HttpResponse> {
     match project_service.get_all_projects().await {
-        Ok(projects) => Ok(HttpResponse::Ok().json(projects)),
+        Ok(mut projects) => {
+            if query.with_quality_gate.unwrap_or(false) {
+                attach_quality_gates(&project_service, &mut projects).await;
+            }
+            Ok(HttpResponse::Ok().json(projects))
+        }
         Err(e) => {
             Ok(HttpResponse::InternalServerError().json(serde_json::json!({
                 "error": format!("Database error: {}", e)
@@ -84,7 +703,10 @@ pub async fn create_admin_token(
     req: web::Json<CreateAdminTokenRequest>,
     project_service: web::Data<ProjectService>,
 ) -> Result<HttpResponse> {
-    let sonar_client = SonarQubeClient::new(req.sonar_host_url.clone(), String::new());
+    let sonar_client = match SonarQubeClient::new(req.sonar_host_url.clone(), String::new()) {
+        Ok(client) => client,
+        Err(e) => return Ok(sonar_error_response("Failed to create SonarQube client", &e)),
+    };
 
     // Validate token_type
     let token_type = if req.token_type == "GLOBAL_ANALYSIS_TOKEN" {
@@ -93,70 +715,437 @@ pub async fn create_admin_token(
         "USER_TOKEN"
     };
 
+    let expiration_date = req.expiration_date.or_else(default_token_expiration);
+    if let Some(response) = invalid_expiration_response(expiration_date) {
+        return Ok(response);
+    }
+
     // Generate admin token in SonarQube with the specified type
-    let token_value = match sonar_client.generate_admin_token(&req.username, &req.password, &req.token_name, token_type).await {
+    let token_value = match sonar_client.generate_admin_token(&req.username, &req.password, &req.token_name, token_type, expiration_date).await {
         Ok(token) => token,
         Err(e) => {
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": format!("Failed to generate admin token in SonarQube: {}", e)
-            })));
+            return Ok(sonar_error_response("Failed to generate admin token in SonarQube", &e));
         }
     };
 
-    // Create admin token request with validated token_type
-    let mut create_request = req.into_inner();
-    create_request.token_type = token_type.to_string();
-
-    // Create admin token in our database
-    let mut admin_token_response = match project_service.create_admin_token(create_request).await {
-        Ok(admin_token) => admin_token,
+    // A user keeps one row per instance and token type, which a new token replaces
+    let previous = match project_service.find_admin_token(&req.sonar_host_url, &req.username, token_type).await {
+        Ok(previous) => previous,
         Err(e) => {
             return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": format!("Failed to create admin token in database: {}", e)
+                "error": format!("Failed to read admin tokens from database: {}", e)
             })));
         }
     };
 
-    // Update admin token with the generated value
-    if let Err(e) = project_service.update_admin_token_value(admin_token_response.id, token_value.clone()).await {
+    let mut admin_token_response = match &previous {
+        Some(previous) => {
+            let generated = GeneratedToken {
+                name: req.token_name.clone(),
+                value: token_value.clone(),
+                expiration_date,
+            };
+            match project_service.replace_admin_token(previous.id, &generated).await {
+                Ok(Some(admin_token)) => admin_token,
+                Ok(None) => {
+                    return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+                        "error": "Admin token was deleted while it was being replaced"
+                    })));
+                }
+                Err(e) => {
+                    return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+                        "error": format!("Failed to replace admin token in database: {}", e)
+                    })));
+                }
+            }
+        }
+        None => {
+            // Create admin token request with validated token_type
+            let create_request = CreateAdminTokenRequest {
+                username: req.username.clone(),
+                password: req.password.clone(),
+                token_name: req.token_name.clone(),
+                token_type: token_type.to_string(),
+                sonar_host_url: req.sonar_host_url.clone(),
+                expiration_date,
+            };
+
+            // Create admin token in our database
+            let admin_token_response = match project_service.create_admin_token(create_request).await {
+                Ok(admin_token) => admin_token,
+                Err(e) => {
+                    return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+                        "error": format!("Failed to create admin token in database: {}", e)
+                    })));
+                }
+            };
+
+            // Update admin token with the generated value
+            if let Err(e) = project_service.update_admin_token_value(admin_token_response.id, token_value.clone()).await {
+                return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+                    "error": format!("Failed to update admin token with value: {}", e)
+                })));
+            }
+            admin_token_response
+        }
+    };
+
+    // Clients are built from the instance registry, so the token is kept on its instance as well
+    if let Err(e) = project_service.store_sonar_instance_token(&admin_token_response.sonar_host_url, token_type, &token_value).await {
         return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": format!("Failed to update admin token with value: {}", e)
+            "error": format!("Failed to store token on the SonarQube instance: {}", e)
         })));
     }
 
+    // The replaced token is no longer used anywhere. A token of the same name is already gone,
+    // SonarQube refuses to generate a second one.
+    if let Some(previous) = previous
+        && previous.token_name != req.token_name
+        && let Err(e) = sonar_client.revoke_own_token(&req.username, &req.password, &previous.token_name).await
+    {
+        warn!("Failed to revoke replaced admin token {} of {}: {}", previous.token_name, req.username, e);
+    }
+
     admin_token_response.token_value = token_value;
     Ok(HttpResponse::Ok().json(admin_token_response))
 }
 
-pub async fn get_project_results(
-    req: web::Json<ScanProjectRequest>,
+// Days ahead /api/tokens/expiring looks when none are given
+const DEFAULT_TOKEN_EXPIRY_WARNING_DAYS: u64 = 30;
+
+#[derive(Debug, Deserialize)]
+pub struct UserTokensQuery {
+    // User whose tokens to list, the user of the instance's admin token otherwise
+    pub login: Option<String>,
+    pub sonar_instance_id: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RevokeUserTokenRequest {
+    pub name: String,
+    pub login: Option<String>,
+    pub sonar_instance_id: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RotateProjectTokenRequest {
+    pub project_path: String,
+    // SONAR_TOKEN_EXPIRY_DAYS from now when not given
+    pub expiration_date: Option<NaiveDate>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RotateAdminTokenRequest {
+    pub id: i32,
+    // SONAR_TOKEN_EXPIRY_DAYS from now when not given
+    pub expiration_date: Option<NaiveDate>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExpiringTokensQuery {
+    pub days: Option<u64>,
+}
+
+pub async fn get_user_tokens(
+    query: web::Query<UserTokensQuery>,
     project_service: web::Data<ProjectService>,
 ) -> Result<HttpResponse> {
-    // Find project by path
-    let project = match project_service.get_project_by_path(&req.project_path).await {
-        Ok(Some(project)) => project,
-        Ok(None) => {
-            return Ok(HttpResponse::NotFound().json(serde_json::json!({
-                "error": "Project not found"
-            })));
-        }
-        Err(e) => {
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+    let sonar_client = match sonar_client_for(&project_service, query.sonar_instance_id, "USER_TOKEN").await {
+        Ok(client) => client,
+        Err(response) => return Ok(response),
+    };
+
+    match sonar_client.search_user_tokens(query.login.as_deref()).await {
+        Ok(tokens) => Ok(HttpResponse::Ok().json(tokens)),
+        Err(e) => Ok(sonar_error_response("Failed to list user tokens", &e)),
+    }
+}
+
+pub async fn revoke_user_token(
+    req: web::Json<RevokeUserTokenRequest>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    let sonar_client = match sonar_client_for(&project_service, req.sonar_instance_id, "USER_TOKEN").await {
+        Ok(client) => client,
+        Err(response) => return Ok(response),
+    };
+
+    if let Err(e) = sonar_client.revoke_user_token(&req.name, req.login.as_deref()).await {
+        return Ok(sonar_error_response("Failed to revoke user token", &e));
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Token revoked successfully",
+        "name": req.name
+    })))
+}
+
+// Revokes the token a rotation replaced. The new token is already stored by then, so a failure
+// is reported in the response instead of failing the rotation.
+async fn revoke_replaced_token(sonar_client: &SonarQubeClient, token_name: Option<&str>, login: Option<&str>) -> serde_json::Value {
+    let Some(token_name) = token_name else {
+        return serde_json::json!({
+            "revoked": false,
+            "revoke_error": "The name of the previous token is unknown, revoke it with POST /api/user-tokens/revoke"
+        });
+    };
+
+    match sonar_client.revoke_user_token(token_name, login).await {
+        Ok(()) => serde_json::json!({
+            "revoked_token": token_name,
+            "revoked": true
+        }),
+        Err(e) => {
+            warn!("Failed to revoke replaced token {}: {}", token_name, e);
+            serde_json::json!({
+                "revoked_token": token_name,
+                "revoked": false,
+                "revoke_error": e.to_string()
+            })
+        }
+    }
+}
+
+// Generates the new token, stores it, and only then revokes the old one
+pub async fn rotate_project_token(
+    req: web::Json<RotateProjectTokenRequest>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    let expiration_date = req.expiration_date.or_else(default_token_expiration);
+    if let Some(response) = invalid_expiration_response(expiration_date) {
+        return Ok(response);
+    }
+
+    let mut project = match find_project(&project_service, &req.project_path).await {
+        Ok(project) => project,
+        Err(response) => return Ok(response),
+    };
+    let sonar_client = match sonar_client_for(&project_service, project.sonar_instance_id, "USER_TOKEN").await {
+        Ok(client) => client,
+        Err(response) => return Ok(response),
+    };
+
+    let old_token_name = project.sonar_token_name.clone();
+    let token_name = rotated_token_name(old_token_name.as_deref().unwrap_or(&format!("{}_token", project.project_key)));
+    let token = match generate_project_token(&sonar_client, &project.project_key, token_name, expiration_date).await {
+        Ok(token) => token,
+        Err(e) => return Ok(sonar_error_response("Failed to generate the new project token", &e)),
+    };
+
+    if let Err(e) = project_service.update_sonar_token(project.id, &token).await {
+        if let Err(revoke_error) = sonar_client.revoke_user_token(&token.name, None).await {
+            warn!("Failed to revoke unused token {}: {}", token.name, revoke_error);
+        }
+        return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to store the new project token, the old one is kept: {}", e)
+        })));
+    }
+    info!("Rotated the token of project {}", project.project_key);
+
+    let mut body = revoke_replaced_token(&sonar_client, old_token_name.as_deref(), None).await;
+    project.set_token(token);
+    body["project"] = serde_json::json!(project);
+    Ok(HttpResponse::Ok().json(body))
+}
+
+// Generates the new token for the same user, stores it on the admin token row and on the
+// instance that used the old one, and only then revokes the old one
+pub async fn rotate_admin_token(
+    req: web::Json<RotateAdminTokenRequest>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    let expiration_date = req.expiration_date.or_else(default_token_expiration);
+    if let Some(response) = invalid_expiration_response(expiration_date) {
+        return Ok(response);
+    }
+
+    let admin_token = match project_service.get_admin_token(req.id).await {
+        Ok(Some(admin_token)) => admin_token,
+        Ok(None) => {
+            return Ok(HttpResponse::NotFound().json(serde_json::json!({
+                "error": "Admin token not found"
+            })));
+        }
+        Err(e) => {
+            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            })));
+        }
+    };
+    let instance = match project_service.get_sonar_instance_by_url(&admin_token.sonar_host_url).await {
+        Ok(Some(instance)) => instance,
+        Ok(None) => {
+            return Ok(HttpResponse::NotFound().json(serde_json::json!({
+                "error": format!("No SonarQube instance is registered for {}", admin_token.sonar_host_url)
+            })));
+        }
+        Err(e) => {
+            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
                 "error": format!("Database error: {}", e)
             })));
         }
     };
+    let sonar_client = match sonar_client_for(&project_service, Some(instance.id), "USER_TOKEN").await {
+        Ok(client) => client,
+        Err(response) => return Ok(response),
+    };
 
-    // Get results from SonarQube
-    let sonar_host_url = env::var("SONAR_HOST_URL").unwrap_or_else(|_| "http://localhost:9000".to_string());
-    
-    // Get GLOBAL_ANALYSIS_TOKEN for fetching issues, coverage, etc.
-    let admin_token = match project_service.get_admin_token_by_type(&sonar_host_url, "GLOBAL_ANALYSIS_TOKEN").await {
-        Ok(Some(token)) => token,
+    let token_name = rotated_token_name(&admin_token.token_name);
+    let value = match sonar_client
+        .generate_user_token(&admin_token.username, &token_name, &admin_token.token_type, expiration_date)
+        .await
+    {
+        Ok(value) => value,
+        Err(e) => return Ok(sonar_error_response("Failed to generate the new admin token", &e)),
+    };
+    let token = GeneratedToken {
+        name: token_name,
+        value,
+        expiration_date,
+    };
+
+    let stored = match project_service.replace_admin_token(admin_token.id, &token).await {
+        Ok(Some(updated)) => Ok(updated),
+        Ok(None) => Err("the admin token was deleted meanwhile".to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+    let mut updated = match stored {
+        Ok(updated) => updated,
+        Err(error) => {
+            if let Err(revoke_error) = sonar_client.revoke_user_token(&token.name, Some(&admin_token.username)).await {
+                warn!("Failed to revoke unused token {}: {}", token.name, revoke_error);
+            }
+            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": format!("Failed to store the new admin token, the old one is kept: {}", error)
+            })));
+        }
+    };
+    // The old token must not be revoked while the instance still authenticates with it
+    if instance.token(&admin_token.token_type) == Some(admin_token.token_value.as_str())
+        && let Err(e) = project_service
+            .store_sonar_instance_token(&instance.url, &admin_token.token_type, &token.value)
+            .await
+    {
+        return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("New token stored, but not on SonarQube instance {}, the old token is kept: {}", instance.name, e)
+        })));
+    }
+    info!("Rotated admin token {} of {}", admin_token.id, admin_token.username);
+
+    let mut body = revoke_replaced_token(&sonar_client, Some(&admin_token.token_name), Some(&admin_token.username)).await;
+    updated.token_value = token.value;
+    body["admin_token"] = serde_json::json!(updated);
+    Ok(HttpResponse::Ok().json(body))
+}
+
+pub async fn get_expiring_tokens(
+    query: web::Query<ExpiringTokensQuery>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    let days = query.days.unwrap_or(DEFAULT_TOKEN_EXPIRY_WARNING_DAYS);
+    let until = Utc::now().date_naive() + Days::new(days);
+
+    match project_service.get_expiring_tokens(until).await {
+        Ok(tokens) => Ok(HttpResponse::Ok().json(serde_json::json!({
+            "days": days,
+            "total": tokens.len(),
+            "tokens": tokens
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Database error: {}", e)
+        }))),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeleteSonarInstanceRequest {
+    pub id: i32,
+}
+
+fn invalid_sonar_instance_response(name: Option<&str>, url: Option<&str>) -> Option<HttpResponse> {
+    let error = if name.is_some_and(|name| name.trim().is_empty()) {
+        "name must not be empty"
+    } else if url.is_some_and(|url| !url.starts_with("http://") && !url.starts_with("https://")) {
+        "url must start with http:// or https://"
+    } else {
+        return None;
+    };
+    Some(HttpResponse::BadRequest().json(serde_json::json!({
+        "error": error
+    })))
+}
+
+fn sonar_instance_db_error(context: &str, error: DbErr) -> HttpResponse {
+    // Names and URLs are unique
+    if matches!(error.sql_err(), Some(SqlErr::UniqueConstraintViolation(_))) {
+        return HttpResponse::Conflict().json(serde_json::json!({
+            "error": "A SonarQube instance with this name or URL already exists"
+        }));
+    }
+    HttpResponse::InternalServerError().json(serde_json::json!({
+        "error": format!("{}: {}", context, error)
+    }))
+}
+
+pub async fn get_sonar_instances(
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    match project_service.get_sonar_instances().await {
+        Ok(instances) => Ok(HttpResponse::Ok().json(instances)),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Database error: {}", e)
+        }))),
+    }
+}
+
+pub async fn create_sonar_instance(
+    req: web::Json<CreateSonarInstanceRequest>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    if let Some(response) = invalid_sonar_instance_response(Some(&req.name), Some(&req.url)) {
+        return Ok(response);
+    }
+
+    match project_service.create_sonar_instance(req.into_inner()).await {
+        Ok(instance) => {
+            info!("Registered SonarQube instance {} ({})", instance.name, instance.url);
+            Ok(HttpResponse::Ok().json(instance))
+        }
+        Err(e) => Ok(sonar_instance_db_error("Failed to create SonarQube instance", e)),
+    }
+}
+
+pub async fn update_sonar_instance(
+    req: web::Json<UpdateSonarInstanceRequest>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    if let Some(response) = invalid_sonar_instance_response(req.name.as_deref(), req.url.as_deref()) {
+        return Ok(response);
+    }
+    if req.is_default == Some(false) {
+        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "is_default can only be set to true, make another instance the default instead"
+        })));
+    }
+
+    match project_service.update_sonar_instance(&req).await {
+        Ok(Some(instance)) => Ok(HttpResponse::Ok().json(instance)),
+        Ok(None) => Ok(HttpResponse::NotFound().json(serde_json::json!({
+            "error": "SonarQube instance not found"
+        }))),
+        Err(e) => Ok(sonar_instance_db_error("Failed to update SonarQube instance", e)),
+    }
+}
+
+pub async fn delete_sonar_instance(
+    req: web::Json<DeleteSonarInstanceRequest>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    let instance = match project_service.get_sonar_instance_details(req.id).await {
+        Ok(Some(instance)) => instance,
         Ok(None) => {
-            return Ok(HttpResponse::BadRequest().json(serde_json::json!({
-                "error": "No GLOBAL_ANALYSIS_TOKEN found for this SonarQube instance. Please create a GLOBAL_ANALYSIS_TOKEN first.",
-                "suggestion": "Use POST /api/admin-token with token_type: 'GLOBAL_ANALYSIS_TOKEN'"
+            return Ok(HttpResponse::NotFound().json(serde_json::json!({
+                "error": "SonarQube instance not found"
             })));
         }
         Err(e) => {
@@ -165,27 +1154,73 @@ pub async fn get_project_results(
             })));
         }
     };
-    
-    let sonar_client = SonarQubeClient::new(sonar_host_url, admin_token);
-    
-    // Fetch issues, coverage, and quality gate in parallel
-    let issues_result = sonar_client.get_project_issues(&project.project_key).await;
-    let coverage_result = sonar_client.get_project_coverage(&project.project_key).await;
-    let quality_gate_result = sonar_client.get_project_quality_gate(&project.project_key).await;
+    if instance.is_default {
+        return Ok(HttpResponse::Conflict().json(serde_json::json!({
+            "error": "This is the default instance, make another instance the default first"
+        })));
+    }
+    if instance.project_count > 0 {
+        return Ok(HttpResponse::Conflict().json(serde_json::json!({
+            "error": format!("{} project(s) still use this instance", instance.project_count)
+        })));
+    }
+
+    match project_service.delete_sonar_instance(req.id).await {
+        Ok(_) => Ok(HttpResponse::Ok().json(serde_json::json!({
+            "message": "SonarQube instance deleted successfully",
+            "id": req.id
+        }))),
+        Err(e) => Ok(sonar_instance_db_error("Failed to delete SonarQube instance", e)),
+    }
+}
+
+// Live results of a project, kept typed next to the response so they can be stored as a snapshot
+struct ProjectResults {
+    response: serde_json::Value,
+    // None when the issues could not be fetched
+    issues: Option<Vec<Issue>>,
+    measures: Vec<Measure>,
+    quality_gate: Option<ProjectStatus>,
+}
+
+// Issues, coverage, quality gate and hotspots as returned by /api/results; failures are reported in the body
+async fn collect_project_results(
+    sonar_client: &SonarQubeClient,
+    project_service: &ProjectService,
+    project: &ProjectResponse,
+    target: &AnalysisTarget,
+    // Cache files are read from and lines of context, when snippets are attached
+    snippets: Option<(&SourceCache, i32)>,
+) -> ProjectResults {
+    let issues_result = sonar_client.get_project_issues(&project.project_key, target).await;
+    let coverage_result = sonar_client.get_project_coverage(&project.project_key, target).await;
+    let quality_gate_result = sonar_client.get_project_quality_gate(&project.project_key, target).await;
+    let hotspots_result = sonar_client.search_hotspots(&project.project_key, target, None, None).await;
 
     let mut response_data = serde_json::json!({
         "project": project,
+        "branch": target.branch,
+        "pull_request": target.pull_request,
     });
+    let mut issues = None;
+    let mut measures = Vec::new();
+    let mut quality_gate = None;
 
     // Handle issues response
     match issues_result {
-        Ok(issues_response) => {
+        Ok(mut issues_response) => {
+            enrich_issues_with_rules(sonar_client, project_service, &mut issues_response.issues).await;
+            if let Some((source_cache, context_lines)) = snippets {
+                attach_snippets(sonar_client, source_cache, target, &mut issues_response.issues, context_lines).await;
+            }
             response_data["issues"] = serde_json::to_value(&issues_response).unwrap_or(serde_json::Value::Null);
+            issues = Some(issues_response.issues);
         }
         Err(e) => {
             println!("Error fetching issues: {}", e);
             response_data["issues_error"] = serde_json::json!({
-                "error": format!("Failed to fetch issues: {}", e)
+                "error": format!("Failed to fetch issues: {}", e),
+                "status": sonar_error_status(&e).as_u16()
             });
         }
     }
@@ -194,11 +1229,12 @@ pub async fn get_project_results(
     match coverage_result {
         Ok(coverage_response) => {
             response_data["coverage"] = serde_json::to_value(&coverage_response).unwrap_or(serde_json::Value::Null);
+            measures = coverage_response.component.measures;
         }
         Err(e) => {
             println!("Error fetching coverage: {}", e);
-            // Check if it's a decoding error (likely no coverage data available)
-            if e.to_string().contains("decoding response body") {
+            // A body we can't decode means there is no coverage data yet
+            if matches!(e, SonarQubeError::Decode(_)) {
                 response_data["coverage"] = serde_json::json!({
                     "message": "No coverage data available for this project. Please ensure the project has been analyzed with coverage reports.",
                     "component": {
@@ -207,7 +1243,8 @@ pub async fn get_project_results(
                 });
             } else {
                 response_data["coverage_error"] = serde_json::json!({
-                    "error": format!("Failed to fetch coverage: {}", e)
+                    "error": format!("Failed to fetch coverage: {}", e),
+                    "status": sonar_error_status(&e).as_u16()
                 });
             }
         }
@@ -217,11 +1254,12 @@ pub async fn get_project_results(
     match quality_gate_result {
         Ok(quality_gate_response) => {
             response_data["quality_gate"] = serde_json::to_value(&quality_gate_response).unwrap_or(serde_json::Value::Null);
+            quality_gate = Some(quality_gate_response.project_status);
         }
         Err(e) => {
             println!("Error fetching quality gate: {}", e);
-            // Check if it's a decoding error (likely no quality gate data available)
-            if e.to_string().contains("decoding response body") {
+            // A body we can't decode means there is no quality gate data yet
+            if matches!(e, SonarQubeError::Decode(_)) {
                 response_data["quality_gate"] = serde_json::json!({
                     "message": "No quality gate data available for this project. Please ensure the project has been analyzed and quality gate is configured.",
                     "projectStatus": {
@@ -231,19 +1269,119 @@ pub async fn get_project_results(
                 });
             } else {
                 response_data["quality_gate_error"] = serde_json::json!({
-                    "error": format!("Failed to fetch quality gate: {}", e)
+                    "error": format!("Failed to fetch quality gate: {}", e),
+                    "status": sonar_error_status(&e).as_u16()
                 });
             }
         }
     }
 
-    Ok(HttpResponse::Ok().json(response_data))
+    // Handle security hotspots response
+    match hotspots_result {
+        Ok(hotspots_response) => {
+            response_data["hotspots"] = serde_json::to_value(&hotspots_response).unwrap_or(serde_json::Value::Null);
+        }
+        Err(e) => {
+            warn!("Failed to fetch hotspots of {}: {}", project.project_key, e);
+            response_data["hotspots_error"] = serde_json::json!({
+                "error": format!("Failed to fetch hotspots: {}", e),
+                "status": sonar_error_status(&e).as_u16()
+            });
+        }
+    }
+
+    ProjectResults {
+        response: response_data,
+        issues,
+        measures,
+        quality_gate,
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProjectResultsRequest {
+    pub project_path: String,
+    #[serde(flatten)]
+    pub target: AnalysisTarget,
+    // Attach code around each issue
+    pub include_snippets: Option<bool>,
+    pub snippet_context: Option<i32>,
+    // Store a snapshot of the results, off by default so polling doesn't fill the table
+    pub save_snapshot: Option<bool>,
+}
+
+pub async fn get_project_results(
+    req: web::Json<ProjectResultsRequest>,
+    project_service: web::Data<ProjectService>,
+    source_cache: web::Data<SourceCache>,
+) -> Result<HttpResponse> {
+    if let Some(response) = invalid_target_response(&req.target) {
+        return Ok(response);
+    }
+
+    // Find project by path
+    let project = match project_service.get_project_by_path(&req.project_path).await {
+        Ok(Some(project)) => project,
+        Ok(None) => {
+            return Ok(HttpResponse::NotFound().json(serde_json::json!({
+                "error": "Project not found"
+            })));
+        }
+        Err(e) => {
+            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            })));
+        }
+    };
+
+    // Get GLOBAL_ANALYSIS_TOKEN for fetching issues, coverage, etc.
+    let sonar_client = match sonar_client_for(&project_service, project.sonar_instance_id, "GLOBAL_ANALYSIS_TOKEN").await {
+        Ok(client) => client,
+        Err(response) => return Ok(response),
+    };
+    
+    let target = &req.target;
+    let snippets = req
+        .include_snippets
+        .unwrap_or(false)
+        .then(|| (source_cache.get_ref(), req.snippet_context.unwrap_or(DEFAULT_CONTEXT_LINES)));
+    let mut results = collect_project_results(&sonar_client, &project_service, &project, target, snippets).await;
+
+    // Keep the results as they were returned, so the same report can be rebuilt later
+    if req.save_snapshot.unwrap_or(false) {
+        let snapshot = NewSnapshot {
+            project_key: &project.project_key,
+            target,
+            source: "results",
+            analysis_id: None,
+            quality_gate: results.quality_gate.as_ref(),
+            measures: &results.measures,
+            issues: results.issues.as_deref(),
+            results: &results.response,
+        };
+        match project_service.save_snapshot(snapshot).await {
+            Ok(snapshot) => results.response["snapshot_id"] = serde_json::json!(snapshot.id),
+            Err(e) => warn!("Failed to store snapshot of {}: {}", project.project_key, e),
+        }
+
+        if let Some(keep) = snapshot_retention()
+            && let Err(e) = project_service.prune_snapshots(&project.project_key, "results", keep).await
+        {
+            warn!("Failed to prune snapshots of {}: {}", project.project_key, e);
+        }
+    }
+
+    Ok(HttpResponse::Ok().json(results.response))
 }
 
 pub async fn generate_sonar_command(
     req: web::Json<ScanProjectRequest>,
     project_service: web::Data<ProjectService>,
 ) -> Result<HttpResponse> {
+    if let Some(response) = invalid_target_response(&req.target) {
+        return Ok(response);
+    }
+
     // Find project by path
     let project = match project_service.get_project_by_path(&req.project_path).await {
         Ok(Some(project)) => project,
@@ -280,6 +1418,22 @@ pub async fn generate_sonar_command(
     command = format!("{} -Dsonar.sources={}", command, project.sources_path);
     command = format!("{} -Dsonar.tests={}", command, project.tests_path);
 
+    // Add branch or pull request
+    if let Some(pull_request) = &req.target.pull_request {
+        let Some(pull_request_branch) = &req.pull_request_branch else {
+            return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "pull_request_branch is required to analyse a pull request"
+            })));
+        };
+        command = format!("{} -Dsonar.pullrequest.key={}", command, pull_request);
+        command = format!("{} -Dsonar.pullrequest.branch={}", command, pull_request_branch);
+        if let Some(base) = &req.pull_request_base {
+            command = format!("{} -Dsonar.pullrequest.base={}", command, base);
+        }
+    } else if let Some(branch) = &req.target.branch {
+        command = format!("{} -Dsonar.branch.name={}", command, branch);
+    }
+
     Ok(HttpResponse::Ok().json(serde_json::json!({
         "command": command,
         "project_path": project.project_path
@@ -290,8 +1444,6 @@ pub async fn delete_project(
     req: web::Json<ScanProjectRequest>,
     project_service: web::Data<ProjectService>,
 ) -> Result<HttpResponse> {
-    let sonar_host_url = env::var("SONAR_HOST_URL").unwrap_or_else(|_| "http://localhost:9000".to_string());
-    
     // Find project by path
     let project = match project_service.get_project_by_path(&req.project_path).await {
         Ok(Some(project)) => project,
@@ -308,23 +1460,11 @@ pub async fn delete_project(
     };
 
     // Get USER_TOKEN for admin operations (create/delete projects)
-    let admin_token = match project_service.get_admin_token_by_type(&sonar_host_url, "USER_TOKEN").await {
-        Ok(Some(token)) => token,
-        Ok(None) => {
-            return Ok(HttpResponse::BadRequest().json(serde_json::json!({
-                "error": "No USER_TOKEN found for this SonarQube instance. Please create a USER_TOKEN first.",
-                "suggestion": "Use POST /api/admin-token with token_type: 'USER_TOKEN' (must be created with a user that has admin privileges)"
-            })));
-        }
-        Err(e) => {
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": format!("Database error: {}", e)
-            })));
-        }
+    let sonar_client = match sonar_client_for(&project_service, project.sonar_instance_id, "USER_TOKEN").await {
+        Ok(client) => client,
+        Err(response) => return Ok(response),
     };
     
-    let sonar_client = SonarQubeClient::new(sonar_host_url.clone(), admin_token);
-    
     // Delete project from SonarQube first
     let sonar_delete_result = sonar_client.delete_project(&project.project_key).await;
     let mut sonar_delete_error = None;
@@ -333,15 +1473,10 @@ pub async fn delete_project(
         let error_msg = e.to_string();
         sonar_delete_error = Some(error_msg.clone());
         
-        // Check if it's a privileges error (case-insensitive, check multiple variations)
-        let error_lower = error_msg.to_lowercase();
-        if error_lower.contains("insufficient privileges") 
-            || error_lower.contains("privilege") 
-            || error_lower.contains("not authorized")
-            || error_lower.contains("unauthorized")
-            || error_lower.contains("access denied") {
+        // Stop on privilege errors, the token needs fixing before anything is removed
+        if matches!(e, SonarQubeError::Unauthorized(_) | SonarQubeError::Forbidden(_)) {
             // Return a helpful error message - don't delete from database
-            return Ok(HttpResponse::Forbidden().json(serde_json::json!({
+            return Ok(HttpResponse::build(sonar_error_status(&e)).json(serde_json::json!({
                 "error": "Insufficient privileges to delete project from SonarQube",
                 "details": "The admin token does not have the necessary permissions to delete projects. Please ensure the token has admin privileges in SonarQube, or create a new admin token with proper permissions.",
                 "sonar_error": error_msg,
@@ -398,6 +1533,8 @@ pub struct CreateQualityGateRequest {
     pub condition_metric: Option<String>,
     pub condition_op: Option<String>,
     pub condition_error: Option<String>,
+    // Instance to act on, the default instance otherwise
+    pub sonar_instance_id: Option<i32>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -409,16 +1546,28 @@ pub struct UpdateQualityGateRequest {
     pub condition_error: Option<String>,
     pub add_conditions: Option<Vec<QualityGateConditionInput>>,
     pub delete_condition_ids: Option<Vec<String>>,
+    // Instance to act on, the default instance otherwise
+    pub sonar_instance_id: Option<i32>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DeleteQualityGateRequest {
     pub name: String,
+    // Instance to act on, the default instance otherwise
+    pub sonar_instance_id: Option<i32>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SetDefaultQualityGateRequest {
     pub name: String,
+    // Instance to act on, the default instance otherwise
+    pub sonar_instance_id: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QualityGatesQuery {
+    // Instance to act on, the default instance otherwise
+    pub sonar_instance_id: Option<i32>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -432,39 +1581,20 @@ pub async fn create_quality_gate(
     req: web::Json<CreateQualityGateRequest>,
     project_service: web::Data<ProjectService>,
 ) -> Result<HttpResponse> {
-    let sonar_host_url = env::var("SONAR_HOST_URL").unwrap_or_else(|_| "http://localhost:9000".to_string());
-
-    // Need USER_TOKEN for admin operations
-    let admin_token = match project_service.get_admin_token_by_type(&sonar_host_url, "USER_TOKEN").await {
-        Ok(Some(token)) => token,
-        Ok(None) => {
-            return Ok(HttpResponse::BadRequest().json(serde_json::json!({
-                "error": "No USER_TOKEN found for this SonarQube instance. Please create a USER_TOKEN first.",
-                "suggestion": "Use POST /api/admin-token with token_type: 'USER_TOKEN' (must be created with a user that has admin privileges)"
-            })));
-        }
-        Err(e) => {
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": format!("Database error: {}", e)
-            })));
-        }
+    let sonar_client = match sonar_client_for(&project_service, req.sonar_instance_id, "USER_TOKEN").await {
+        Ok(client) => client,
+        Err(response) => return Ok(response),
     };
 
-    let sonar_client = SonarQubeClient::new(sonar_host_url.clone(), admin_token);
-
     if let Err(e) = sonar_client.create_quality_gate(&req.name).await {
-        return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": format!("Failed to create quality gate: {}", e)
-        })));
+        return Ok(sonar_error_response("Failed to create quality gate", &e));
     }
 
     // Optionally add a condition to the newly created gate
-    if let (Some(metric), Some(op), Some(error)) = (&req.condition_metric, &req.condition_op, &req.condition_error) {
-        if let Err(e) = sonar_client.add_quality_gate_condition(&req.name, metric, op, error).await {
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": format!("Quality gate created, but failed to add condition: {}", e)
-            })));
-        }
+    if let (Some(metric), Some(op), Some(error)) = (&req.condition_metric, &req.condition_op, &req.condition_error)
+        && let Err(e) = sonar_client.add_quality_gate_condition(&req.name, metric, op, error).await
+    {
+        return Ok(sonar_error_response("Quality gate created, but failed to add condition", &e));
     }
 
     Ok(HttpResponse::Ok().json(serde_json::json!({
@@ -477,49 +1607,29 @@ pub async fn update_quality_gate(
     req: web::Json<UpdateQualityGateRequest>,
     project_service: web::Data<ProjectService>,
 ) -> Result<HttpResponse> {
-    let sonar_host_url = env::var("SONAR_HOST_URL").unwrap_or_else(|_| "http://localhost:9000".to_string());
-
-    let admin_token = match project_service.get_admin_token_by_type(&sonar_host_url, "USER_TOKEN").await {
-        Ok(Some(token)) => token,
-        Ok(None) => {
-            return Ok(HttpResponse::BadRequest().json(serde_json::json!({
-                "error": "No USER_TOKEN found for this SonarQube instance. Please create a USER_TOKEN first.",
-                "suggestion": "Use POST /api/admin-token with token_type: 'USER_TOKEN' (must be created with a user that has admin privileges)"
-            })));
-        }
-        Err(e) => {
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": format!("Database error: {}", e)
-            })));
-        }
+    let sonar_client = match sonar_client_for(&project_service, req.sonar_instance_id, "USER_TOKEN").await {
+        Ok(client) => client,
+        Err(response) => return Ok(response),
     };
 
-    let sonar_client = SonarQubeClient::new(sonar_host_url.clone(), admin_token);
-
-    if let Some(new_name) = &req.new_name {
-        if let Err(e) = sonar_client.rename_quality_gate(&req.name, new_name).await {
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": format!("Failed to rename quality gate: {}", e)
-            })));
-        }
+    if let Some(new_name) = &req.new_name
+        && let Err(e) = sonar_client.rename_quality_gate(&req.name, new_name).await
+    {
+        return Ok(sonar_error_response("Failed to rename quality gate", &e));
     }
 
     // Backward-compatible single condition add
-    if let (Some(metric), Some(op), Some(error)) = (&req.condition_metric, &req.condition_op, &req.condition_error) {
-        if let Err(e) = sonar_client.add_quality_gate_condition(req.new_name.as_ref().unwrap_or(&req.name), metric, op, error).await {
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": format!("Failed to add condition: {}", e)
-            })));
-        }
+    if let (Some(metric), Some(op), Some(error)) = (&req.condition_metric, &req.condition_op, &req.condition_error)
+        && let Err(e) = sonar_client.add_quality_gate_condition(req.new_name.as_ref().unwrap_or(&req.name), metric, op, error).await
+    {
+        return Ok(sonar_error_response("Failed to add condition", &e));
     }
 
     // Multiple deletes by condition id
     if let Some(ids) = &req.delete_condition_ids {
         for id in ids {
             if let Err(e) = sonar_client.delete_quality_gate_condition(id).await {
-                return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                    "error": format!("Failed to delete condition id {}: {}", id, e)
-                })));
+                return Ok(sonar_error_response(&format!("Failed to delete condition id {}", id), &e));
             }
         }
     }
@@ -529,9 +1639,7 @@ pub async fn update_quality_gate(
         let gate_name = req.new_name.as_ref().unwrap_or(&req.name);
         for c in conds {
             if let Err(e) = sonar_client.add_quality_gate_condition(gate_name, &c.metric, &c.op, &c.error).await {
-                return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                    "error": format!("Failed to add condition (metric {}): {}", c.metric, e)
-                })));
+                return Ok(sonar_error_response(&format!("Failed to add condition (metric {})", c.metric), &e));
             }
         }
     }
@@ -546,29 +1654,13 @@ pub async fn delete_quality_gate(
     req: web::Json<DeleteQualityGateRequest>,
     project_service: web::Data<ProjectService>,
 ) -> Result<HttpResponse> {
-    let sonar_host_url = env::var("SONAR_HOST_URL").unwrap_or_else(|_| "http://localhost:9000".to_string());
-
-    let admin_token = match project_service.get_admin_token_by_type(&sonar_host_url, "USER_TOKEN").await {
-        Ok(Some(token)) => token,
-        Ok(None) => {
-            return Ok(HttpResponse::BadRequest().json(serde_json::json!({
-                "error": "No USER_TOKEN found for this SonarQube instance. Please create a USER_TOKEN first.",
-                "suggestion": "Use POST /api/admin-token with token_type: 'USER_TOKEN' (must be created with a user that has admin privileges)"
-            })));
-        }
-        Err(e) => {
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": format!("Database error: {}", e)
-            })));
-        }
+    let sonar_client = match sonar_client_for(&project_service, req.sonar_instance_id, "USER_TOKEN").await {
+        Ok(client) => client,
+        Err(response) => return Ok(response),
     };
 
-    let sonar_client = SonarQubeClient::new(sonar_host_url.clone(), admin_token);
-
     if let Err(e) = sonar_client.delete_quality_gate(&req.name).await {
-        return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": format!("Failed to delete quality gate: {}", e)
-        })));
+        return Ok(sonar_error_response("Failed to delete quality gate", &e));
     }
 
     Ok(HttpResponse::Ok().json(serde_json::json!({
@@ -581,29 +1673,13 @@ pub async fn set_default_quality_gate(
     req: web::Json<SetDefaultQualityGateRequest>,
     project_service: web::Data<ProjectService>,
 ) -> Result<HttpResponse> {
-    let sonar_host_url = env::var("SONAR_HOST_URL").unwrap_or_else(|_| "http://localhost:9000".to_string());
-
-    let admin_token = match project_service.get_admin_token_by_type(&sonar_host_url, "USER_TOKEN").await {
-        Ok(Some(token)) => token,
-        Ok(None) => {
-            return Ok(HttpResponse::BadRequest().json(serde_json::json!({
-                "error": "No USER_TOKEN found for this SonarQube instance. Please create a USER_TOKEN first.",
-                "suggestion": "Use POST /api/admin-token with token_type: 'USER_TOKEN' (must be created with a user that has admin privileges)"
-            })));
-        }
-        Err(e) => {
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": format!("Database error: {}", e)
-            })));
-        }
-    };
-
-    let sonar_client = SonarQubeClient::new(sonar_host_url.clone(), admin_token);
+    let sonar_client = match sonar_client_for(&project_service, req.sonar_instance_id, "USER_TOKEN").await {
+        Ok(client) => client,
+        Err(response) => return Ok(response),
+    };
 
     if let Err(e) = sonar_client.set_default_quality_gate(&req.name).await {
-        return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": format!("Failed to set default quality gate: {}", e)
-        })));
+        return Ok(sonar_error_response("Failed to set default quality gate", &e));
     }
 
     Ok(HttpResponse::Ok().json(serde_json::json!({
@@ -613,69 +1689,2488 @@ pub async fn set_default_quality_gate(
 }
 
 pub async fn get_quality_gates(
+    query: web::Query<QualityGatesQuery>,
     project_service: web::Data<ProjectService>,
 ) -> Result<HttpResponse> {
-    let sonar_host_url = env::var("SONAR_HOST_URL").unwrap_or_else(|_| "http://localhost:9000".to_string());
+    let sonar_client = match sonar_client_for(&project_service, query.sonar_instance_id, "USER_TOKEN").await {
+        Ok(client) => client,
+        Err(response) => return Ok(response),
+    };
 
-    let admin_token = match project_service.get_admin_token_by_type(&sonar_host_url, "USER_TOKEN").await {
-        Ok(Some(token)) => token,
-        Ok(None) => {
-            return Ok(HttpResponse::BadRequest().json(serde_json::json!({
-                "error": "No USER_TOKEN found for this SonarQube instance. Please create a USER_TOKEN first.",
-                "suggestion": "Use POST /api/admin-token with token_type: 'USER_TOKEN' (must be created with a user that has admin privileges)"
-            })));
-        }
-        Err(e) => {
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": format!("Database error: {}", e)
-            })));
-        }
+    match sonar_client.get_quality_gates().await {
+        Ok(list) => Ok(HttpResponse::Ok().json(list)),
+        Err(e) => Ok(sonar_error_response("Failed to list quality gates", &e)),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProjectQualityGateRequest {
+    pub project_path: String,
+    // Required to select a gate
+    pub name: Option<String>,
+}
+
+pub async fn select_project_quality_gate(
+    req: web::Json<ProjectQualityGateRequest>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    let Some(gate_name) = &req.name else {
+        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "name is required"
+        })));
+    };
+    let project = match find_project(&project_service, &req.project_path).await {
+        Ok(project) => project,
+        Err(response) => return Ok(response),
+    };
+    let sonar_client = match sonar_client_for(&project_service, project.sonar_instance_id, "USER_TOKEN").await {
+        Ok(client) => client,
+        Err(response) => return Ok(response),
     };
 
-    info!("admin_token : {}", admin_token);
+    if let Err(e) = sonar_client.select_quality_gate(&project.project_key, gate_name).await {
+        return Ok(sonar_error_response("Failed to select quality gate", &e));
+    }
 
-    let sonar_client = SonarQubeClient::new(sonar_host_url.clone(), admin_token);
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Quality gate selected for project",
+        "project_key": project.project_key,
+        "name": gate_name
+    })))
+}
 
-    match sonar_client.get_quality_gates().await {
-        Ok(list) => Ok(HttpResponse::Ok().json(list)),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": format!("Failed to list quality gates: {}", e)
+pub async fn deselect_project_quality_gate(
+    req: web::Json<ProjectQualityGateRequest>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    let project = match find_project(&project_service, &req.project_path).await {
+        Ok(project) => project,
+        Err(response) => return Ok(response),
+    };
+    let sonar_client = match sonar_client_for(&project_service, project.sonar_instance_id, "USER_TOKEN").await {
+        Ok(client) => client,
+        Err(response) => return Ok(response),
+    };
+
+    if let Err(e) = sonar_client.deselect_quality_gate(&project.project_key).await {
+        return Ok(sonar_error_response("Failed to deselect quality gate", &e));
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Project now uses the default quality gate",
+        "project_key": project.project_key
+    })))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProjectQualityGateQuery {
+    pub project_path: String,
+}
+
+pub async fn get_project_quality_gate(
+    query: web::Query<ProjectQualityGateQuery>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    let project = match find_project(&project_service, &query.project_path).await {
+        Ok(project) => project,
+        Err(response) => return Ok(response),
+    };
+    let sonar_client = match sonar_client_for(&project_service, project.sonar_instance_id, "USER_TOKEN").await {
+        Ok(client) => client,
+        Err(response) => return Ok(response),
+    };
+
+    match sonar_client.get_quality_gate_by_project(&project.project_key).await {
+        Ok(gate) => Ok(HttpResponse::Ok().json(serde_json::json!({
+            "project_key": project.project_key,
+            "quality_gate": gate
         }))),
+        Err(e) => Ok(sonar_error_response("Failed to get project quality gate", &e)),
     }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct QualityGateDetailsQuery {
     pub name: String,
+    // Instance to act on, the default instance otherwise
+    pub sonar_instance_id: Option<i32>,
 }
 
 pub async fn get_quality_gate_details(
     query: web::Query<QualityGateDetailsQuery>,
     project_service: web::Data<ProjectService>,
 ) -> Result<HttpResponse> {
-    let sonar_host_url = env::var("SONAR_HOST_URL").unwrap_or_else(|_| "http://localhost:9000".to_string());
+    let sonar_client = match sonar_client_for(&project_service, query.sonar_instance_id, "USER_TOKEN").await {
+        Ok(client) => client,
+        Err(response) => return Ok(response),
+    };
 
-    let admin_token = match project_service.get_admin_token_by_type(&sonar_host_url, "USER_TOKEN").await {
-        Ok(Some(token)) => token,
-        Ok(None) => {
-            return Ok(HttpResponse::BadRequest().json(serde_json::json!({
-                "error": "No USER_TOKEN found for this SonarQube instance. Please create a USER_TOKEN first.",
-                "suggestion": "Use POST /api/admin-token with token_type: 'USER_TOKEN' (must be created with a user that has admin privileges)"
-            })));
+    match sonar_client.get_quality_gate_details(&query.name).await {
+        Ok(details) => Ok(HttpResponse::Ok().json(details)),
+        Err(e) => Ok(sonar_error_response("Failed to get quality gate details", &e)),
+    }
+}
+
+// Quality Profile DTOs
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QualityProfilesQuery {
+    pub language: Option<String>,
+    // Restricts the list to the profiles this project is analysed with
+    pub project_path: Option<String>,
+    // Instance to list the profiles of when no project is given, the default instance otherwise
+    pub sonar_instance_id: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateQualityProfileRequest {
+    pub name: String,
+    pub language: String,
+    // Key of an existing profile to copy the rules from
+    pub copy_from: Option<String>,
+    // Name of a profile of the same language to inherit from
+    pub parent: Option<String>,
+    // Instance to act on, the default instance otherwise
+    pub sonar_instance_id: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QualityProfileParentRequest {
+    pub name: String,
+    pub language: String,
+    // Omit to stop inheriting
+    pub parent: Option<String>,
+    // Instance to act on, the default instance otherwise
+    pub sonar_instance_id: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QualityProfileRuleRequest {
+    pub profile_key: String,
+    pub rule_key: String,
+    // Only used when activating, defaults to the rule's own severity
+    pub severity: Option<String>,
+    // Instance to act on, the default instance otherwise
+    pub sonar_instance_id: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QualityProfileProjectRequest {
+    pub project_path: String,
+    pub profile_name: String,
+    // Defaults to the project's language
+    pub language: Option<String>,
+}
+
+pub async fn get_quality_profiles(
+    query: web::Query<QualityProfilesQuery>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    let project = match optional_project(&project_service, &query.project_path).await {
+        Ok(project) => project,
+        Err(response) => return Ok(response),
+    };
+    let project_key = project.as_ref().map(|project| project.project_key.clone());
+    let instance_id = match &project {
+        Some(project) => project.sonar_instance_id,
+        None => query.sonar_instance_id,
+    };
+    let sonar_client = match sonar_client_for(&project_service, instance_id, "USER_TOKEN").await {
+        Ok(client) => client,
+        Err(response) => return Ok(response),
+    };
+
+    match sonar_client.search_quality_profiles(query.language.as_deref(), project_key.as_deref()).await {
+        Ok(profiles) => Ok(HttpResponse::Ok().json(serde_json::json!({
+            "profiles": profiles
+        }))),
+        Err(e) => Ok(sonar_error_response("Failed to list quality profiles", &e)),
+    }
+}
+
+pub async fn create_quality_profile(
+    req: web::Json<CreateQualityProfileRequest>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    let sonar_client = match sonar_client_for(&project_service, req.sonar_instance_id, "USER_TOKEN").await {
+        Ok(client) => client,
+        Err(response) => return Ok(response),
+    };
+
+    let created = match &req.copy_from {
+        Some(from_key) => sonar_client.copy_quality_profile(from_key, &req.name).await,
+        None => sonar_client.create_quality_profile(&req.name, &req.language).await,
+    };
+    let profile = match created {
+        Ok(profile) => profile,
+        Err(e) => return Ok(sonar_error_response("Failed to create quality profile", &e)),
+    };
+
+    if let Some(parent) = &req.parent
+        && let Err(e) = sonar_client.change_quality_profile_parent(&profile.name, &profile.language, Some(parent)).await
+    {
+        return Ok(sonar_error_response("Quality profile created, but failed to set its parent", &e));
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Quality profile created successfully",
+        "profile": profile
+    })))
+}
+
+pub async fn set_quality_profile_parent(
+    req: web::Json<QualityProfileParentRequest>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    let sonar_client = match sonar_client_for(&project_service, req.sonar_instance_id, "USER_TOKEN").await {
+        Ok(client) => client,
+        Err(response) => return Ok(response),
+    };
+
+    if let Err(e) = sonar_client.change_quality_profile_parent(&req.name, &req.language, req.parent.as_deref()).await {
+        return Ok(sonar_error_response("Failed to change quality profile parent", &e));
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Quality profile parent updated",
+        "name": req.name,
+        "parent": req.parent
+    })))
+}
+
+pub async fn activate_quality_profile_rule(
+    req: web::Json<QualityProfileRuleRequest>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    let severity = req.severity.as_deref().map(str::to_uppercase);
+    if let Some(severity) = &severity
+        && !ISSUE_SEVERITIES.contains(&severity.as_str())
+    {
+        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("Unknown severity: {}", severity),
+            "allowed_severities": ISSUE_SEVERITIES
+        })));
+    }
+
+    let sonar_client = match sonar_client_for(&project_service, req.sonar_instance_id, "USER_TOKEN").await {
+        Ok(client) => client,
+        Err(response) => return Ok(response),
+    };
+
+    if let Err(e) = sonar_client.activate_quality_profile_rule(&req.profile_key, &req.rule_key, severity.as_deref()).await {
+        return Ok(sonar_error_response("Failed to activate rule", &e));
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Rule activated",
+        "profile_key": req.profile_key,
+        "rule_key": req.rule_key
+    })))
+}
+
+pub async fn deactivate_quality_profile_rule(
+    req: web::Json<QualityProfileRuleRequest>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    let sonar_client = match sonar_client_for(&project_service, req.sonar_instance_id, "USER_TOKEN").await {
+        Ok(client) => client,
+        Err(response) => return Ok(response),
+    };
+
+    if let Err(e) = sonar_client.deactivate_quality_profile_rule(&req.profile_key, &req.rule_key).await {
+        return Ok(sonar_error_response("Failed to deactivate rule", &e));
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Rule deactivated",
+        "profile_key": req.profile_key,
+        "rule_key": req.rule_key
+    })))
+}
+
+pub async fn set_project_quality_profile(
+    req: web::Json<QualityProfileProjectRequest>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    let project = match find_project(&project_service, &req.project_path).await {
+        Ok(project) => project,
+        Err(response) => return Ok(response),
+    };
+    let sonar_client = match sonar_client_for(&project_service, project.sonar_instance_id, "USER_TOKEN").await {
+        Ok(client) => client,
+        Err(response) => return Ok(response),
+    };
+
+    let language = req.language.as_deref().unwrap_or(&project.language);
+    if let Err(e) = sonar_client.add_project_to_quality_profile(&project.project_key, language, &req.profile_name).await {
+        return Ok(sonar_error_response("Failed to associate quality profile", &e));
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Quality profile associated with project",
+        "project_key": project.project_key,
+        "language": language,
+        "profile_name": req.profile_name
+    })))
+}
+
+pub async fn get_project_details(
+    req: web::Json<ScanProjectRequest>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    let project = match find_project(&project_service, &req.project_path).await {
+        Ok(project) => project,
+        Err(response) => return Ok(response),
+    };
+    let sonar_client = match sonar_client_for(&project_service, project.sonar_instance_id, "USER_TOKEN").await {
+        Ok(client) => client,
+        Err(response) => return Ok(response),
+    };
+
+    let mut project = project;
+    let quality_gate_error = match sonar_client.get_quality_gate_by_project(&project.project_key).await {
+        Ok(gate) => {
+            project.quality_gate = Some(gate);
+            None
         }
         Err(e) => {
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": format!("Database error: {}", e)
-            })));
+            warn!("Failed to fetch quality gate of {}: {}", project.project_key, e);
+            Some(serde_json::json!({
+                "message": format!("Failed to fetch quality gate: {}", e),
+                "status": sonar_error_status(&e).as_u16()
+            }))
         }
     };
 
-    let sonar_client = SonarQubeClient::new(sonar_host_url.clone(), admin_token);
+    let mut response_data = serde_json::json!({
+        "project": project,
+    });
+    if let Some(error) = quality_gate_error {
+        response_data["quality_gate_error"] = error;
+    }
 
-    match sonar_client.get_quality_gate_details(&query.name).await {
-        Ok(details) => Ok(HttpResponse::Ok().json(details)),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": format!("Failed to get quality gate details: {}", e)
+    // Rule sets the project is measured against, one per language
+    match sonar_client.search_quality_profiles(None, Some(&project.project_key)).await {
+        Ok(profiles) => {
+            response_data["quality_profiles"] = serde_json::json!(profiles);
+        }
+        Err(e) => {
+            warn!("Failed to fetch quality profiles of {}: {}", project.project_key, e);
+            response_data["quality_profiles_error"] = serde_json::json!({
+                "message": format!("Failed to fetch quality profiles: {}", e),
+                "status": sonar_error_status(&e).as_u16()
+            });
+        }
+    }
+
+    Ok(HttpResponse::Ok().json(response_data))
+}
+
+// Project settings DTOs
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProjectSettingsQuery {
+    pub project_path: String,
+    // Comma separated setting keys, defaults to DEFAULT_PROJECT_SETTINGS
+    pub keys: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateProjectSettingsRequest {
+    pub project_path: String,
+    pub settings: HashMap<String, SettingInput>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResetProjectSettingsRequest {
+    pub project_path: String,
+    pub keys: Vec<String>,
+}
+
+// Scanner properties most teams want managed server-side
+const DEFAULT_PROJECT_SETTINGS: [&str; 6] = [
+    "sonar.exclusions",
+    "sonar.inclusions",
+    "sonar.test.exclusions",
+    "sonar.coverage.exclusions",
+    "sonar.cpd.exclusions",
+    "sonar.sourceEncoding",
+];
+
+// Checks a value against the setting's definition from /api/settings/list_definitions
+fn validate_setting(key: &str, value: &SettingInput, definition: Option<&SettingDefinition>) -> std::result::Result<(), String> {
+    let Some(definition) = definition else {
+        return Err(format!("{}: unknown setting", key));
+    };
+
+    let values = match (value, definition.multi_values) {
+        (SettingInput::Single(value), false) => vec![value],
+        (SettingInput::Multiple(values), true) => values.iter().collect(),
+        (SettingInput::Single(_), true) => return Err(format!("{}: expects a list of values", key)),
+        (SettingInput::Multiple(_), false) => return Err(format!("{}: expects a single value", key)),
+    };
+
+    let setting_type = definition.setting_type.as_deref().unwrap_or("STRING");
+    for value in values {
+        let valid = match setting_type {
+            "BOOLEAN" => value == "true" || value == "false",
+            "INTEGER" | "LONG" => value.parse::<i64>().is_ok(),
+            "FLOAT" => value.parse::<f64>().is_ok(),
+            "SINGLE_SELECT_LIST" => definition.options.contains(value),
+            "PROPERTY_SET" => return Err(format!("{}: property sets are not supported", key)),
+            _ => true,
+        };
+        if !valid {
+            return Err(format!("{}: '{}' is not a valid {} value", key, value, setting_type));
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn get_project_settings(
+    query: web::Query<ProjectSettingsQuery>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    let project = match find_project(&project_service, &query.project_path).await {
+        Ok(project) => project,
+        Err(response) => return Ok(response),
+    };
+    let sonar_client = match sonar_client_for(&project_service, project.sonar_instance_id, "USER_TOKEN").await {
+        Ok(client) => client,
+        Err(response) => return Ok(response),
+    };
+
+    let keys: Vec<String> = match &query.keys {
+        Some(keys) => keys.split(',').map(str::trim).filter(|key| !key.is_empty()).map(str::to_string).collect(),
+        None => DEFAULT_PROJECT_SETTINGS.iter().map(|key| key.to_string()).collect(),
+    };
+
+    match sonar_client.get_settings(&project.project_key, &keys).await {
+        Ok(settings) => Ok(HttpResponse::Ok().json(serde_json::json!({
+            "project_key": project.project_key,
+            "settings": settings
+        }))),
+        Err(e) => Ok(sonar_error_response("Failed to read project settings", &e)),
+    }
+}
+
+pub async fn update_project_settings(
+    req: web::Json<UpdateProjectSettingsRequest>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    if req.settings.is_empty() {
+        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "settings must not be empty"
+        })));
+    }
+
+    let project = match find_project(&project_service, &req.project_path).await {
+        Ok(project) => project,
+        Err(response) => return Ok(response),
+    };
+    let sonar_client = match sonar_client_for(&project_service, project.sonar_instance_id, "USER_TOKEN").await {
+        Ok(client) => client,
+        Err(response) => return Ok(response),
+    };
+
+    let definitions: HashMap<String, SettingDefinition> = match sonar_client.list_setting_definitions(&project.project_key).await {
+        Ok(definitions) => definitions.into_iter().map(|definition| (definition.key.clone(), definition)).collect(),
+        Err(e) => return Ok(sonar_error_response("Failed to load setting definitions", &e)),
+    };
+
+    // Nothing is changed unless every setting is valid
+    let invalid: Vec<String> = req
+        .settings
+        .iter()
+        .filter_map(|(key, value)| validate_setting(key, value, definitions.get(key)).err())
+        .collect();
+    if !invalid.is_empty() {
+        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "Invalid project settings",
+            "invalid_settings": invalid
+        })));
+    }
+
+    for (key, value) in &req.settings {
+        if let Err(e) = sonar_client.set_setting(&project.project_key, key, value).await {
+            return Ok(sonar_error_response(&format!("Failed to set {}", key), &e));
+        }
+    }
+    info!("Updated {} settings of {}", req.settings.len(), project.project_key);
+
+    let keys: Vec<String> = req.settings.keys().cloned().collect();
+    match sonar_client.get_settings(&project.project_key, &keys).await {
+        Ok(settings) => Ok(HttpResponse::Ok().json(serde_json::json!({
+            "message": "Project settings updated",
+            "project_key": project.project_key,
+            "settings": settings
         }))),
+        Err(e) => Ok(sonar_error_response("Settings were updated but could not be read back", &e)),
+    }
+}
+
+pub async fn reset_project_settings(
+    req: web::Json<ResetProjectSettingsRequest>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    if req.keys.is_empty() {
+        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "keys must not be empty"
+        })));
+    }
+
+    let project = match find_project(&project_service, &req.project_path).await {
+        Ok(project) => project,
+        Err(response) => return Ok(response),
+    };
+    let sonar_client = match sonar_client_for(&project_service, project.sonar_instance_id, "USER_TOKEN").await {
+        Ok(client) => client,
+        Err(response) => return Ok(response),
+    };
+
+    if let Err(e) = sonar_client.reset_settings(&project.project_key, &req.keys).await {
+        return Ok(sonar_error_response("Failed to reset project settings", &e));
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Project settings reset to their inherited values",
+        "project_key": project.project_key,
+        "keys": req.keys
+    })))
+}
+
+// New code period DTOs
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NewCodePeriodQuery {
+    pub project_path: String,
+    pub branch: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NewCodePeriodRequest {
+    pub project_path: String,
+    pub branch: Option<String>,
+    // Only used when setting the period
+    #[serde(rename = "type")]
+    pub period_type: Option<String>,
+    pub value: Option<String>,
+}
+
+const NEW_CODE_PERIOD_TYPES: [&str; 4] = ["PREVIOUS_VERSION", "NUMBER_OF_DAYS", "REFERENCE_BRANCH", "SPECIFIC_ANALYSIS"];
+
+fn validate_new_code_period(period_type: &str, value: Option<&str>, branch: Option<&str>) -> std::result::Result<(), String> {
+    match (period_type, value) {
+        ("PREVIOUS_VERSION", _) => Ok(()),
+        ("NUMBER_OF_DAYS", Some(days)) if days.parse::<u32>().is_ok_and(|days| (1..=90).contains(&days)) => Ok(()),
+        ("NUMBER_OF_DAYS", _) => Err("NUMBER_OF_DAYS needs a value between 1 and 90".to_string()),
+        ("REFERENCE_BRANCH", Some(_)) => Ok(()),
+        ("REFERENCE_BRANCH", None) => Err("REFERENCE_BRANCH needs the reference branch name as value".to_string()),
+        ("SPECIFIC_ANALYSIS", Some(_)) if branch.is_some() => Ok(()),
+        ("SPECIFIC_ANALYSIS", _) => Err("SPECIFIC_ANALYSIS needs a branch and an analysis key as value".to_string()),
+        _ => Err(format!("Unknown new code period type: {}", period_type)),
+    }
+}
+
+pub async fn get_new_code_period(
+    query: web::Query<NewCodePeriodQuery>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    let project = match find_project(&project_service, &query.project_path).await {
+        Ok(project) => project,
+        Err(response) => return Ok(response),
+    };
+    let sonar_client = match sonar_client_for(&project_service, project.sonar_instance_id, "USER_TOKEN").await {
+        Ok(client) => client,
+        Err(response) => return Ok(response),
+    };
+
+    match sonar_client.get_new_code_period(&project.project_key, query.branch.as_deref()).await {
+        Ok(period) => Ok(HttpResponse::Ok().json(serde_json::json!({
+            "project_key": project.project_key,
+            "new_code_period": period
+        }))),
+        Err(e) => Ok(sonar_error_response("Failed to read new code period", &e)),
+    }
+}
+
+pub async fn set_new_code_period(
+    req: web::Json<NewCodePeriodRequest>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    let period_type = req.period_type.as_deref().unwrap_or_default().to_uppercase();
+    let value = req.value.as_deref().map(str::trim).filter(|value| !value.is_empty());
+    if let Err(message) = validate_new_code_period(&period_type, value, req.branch.as_deref()) {
+        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": message,
+            "allowed_types": NEW_CODE_PERIOD_TYPES
+        })));
+    }
+
+    let project = match find_project(&project_service, &req.project_path).await {
+        Ok(project) => project,
+        Err(response) => return Ok(response),
+    };
+    let sonar_client = match sonar_client_for(&project_service, project.sonar_instance_id, "USER_TOKEN").await {
+        Ok(client) => client,
+        Err(response) => return Ok(response),
+    };
+
+    // PREVIOUS_VERSION takes no value
+    let value = value.filter(|_| period_type != "PREVIOUS_VERSION");
+    if let Err(e) = sonar_client.set_new_code_period(&project.project_key, req.branch.as_deref(), &period_type, value).await {
+        return Ok(sonar_error_response("Failed to set new code period", &e));
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "New code period updated",
+        "project_key": project.project_key,
+        "branch": req.branch,
+        "type": period_type,
+        "value": value
+    })))
+}
+
+pub async fn unset_new_code_period(
+    req: web::Json<NewCodePeriodRequest>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    let project = match find_project(&project_service, &req.project_path).await {
+        Ok(project) => project,
+        Err(response) => return Ok(response),
+    };
+    let sonar_client = match sonar_client_for(&project_service, project.sonar_instance_id, "USER_TOKEN").await {
+        Ok(client) => client,
+        Err(response) => return Ok(response),
+    };
+
+    if let Err(e) = sonar_client.unset_new_code_period(&project.project_key, req.branch.as_deref()).await {
+        return Ok(sonar_error_response("Failed to reset new code period", &e));
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "New code period reset to the inherited value",
+        "project_key": project.project_key,
+        "branch": req.branch
+    })))
+}
+
+// Project permission DTOs
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProjectPermissionsQuery {
+    pub project_path: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PermissionChange {
+    // Exactly one of user (login) or group (name)
+    pub user: Option<String>,
+    pub group: Option<String>,
+    pub permission: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateProjectPermissionsRequest {
+    pub project_path: String,
+    // Applied first, replacing the current permissions
+    pub template: Option<String>,
+    #[serde(default)]
+    pub add: Vec<PermissionChange>,
+    #[serde(default)]
+    pub remove: Vec<PermissionChange>,
+}
+
+const PROJECT_PERMISSIONS: [&str; 6] = ["user", "codeviewer", "issueadmin", "securityhotspotadmin", "admin", "scan"];
+
+fn validate_permission_change(change: &PermissionChange) -> std::result::Result<(), String> {
+    if change.user.is_some() == change.group.is_some() {
+        return Err(format!("{}: set either user or group", change.permission));
+    }
+    if !PROJECT_PERMISSIONS.contains(&change.permission.as_str()) {
+        return Err(format!("Unknown permission: {}", change.permission));
+    }
+    Ok(())
+}
+
+async fn apply_permission_change(
+    sonar_client: &SonarQubeClient,
+    project_key: &str,
+    change: &PermissionChange,
+    add: bool,
+) -> std::result::Result<(), SonarQubeError> {
+    match (&change.user, &change.group, add) {
+        (Some(login), _, true) => sonar_client.add_user_permission(project_key, login, &change.permission).await,
+        (Some(login), _, false) => sonar_client.remove_user_permission(project_key, login, &change.permission).await,
+        (None, Some(group), true) => sonar_client.add_group_permission(project_key, group, &change.permission).await,
+        (None, Some(group), false) => sonar_client.remove_group_permission(project_key, group, &change.permission).await,
+        // Rejected by validate_permission_change
+        (None, None, _) => Ok(()),
+    }
+}
+
+async fn project_permissions_response(sonar_client: &SonarQubeClient, project_key: &str) -> HttpResponse {
+    let users = match sonar_client.get_project_user_permissions(project_key).await {
+        Ok(users) => users,
+        Err(e) => return sonar_error_response("Failed to read user permissions", &e),
+    };
+    let groups = match sonar_client.get_project_group_permissions(project_key).await {
+        Ok(groups) => groups,
+        Err(e) => return sonar_error_response("Failed to read group permissions", &e),
+    };
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "project_key": project_key,
+        "users": users,
+        "groups": groups
+    }))
+}
+
+pub async fn get_project_permissions(
+    query: web::Query<ProjectPermissionsQuery>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    let project = match find_project(&project_service, &query.project_path).await {
+        Ok(project) => project,
+        Err(response) => return Ok(response),
+    };
+    let sonar_client = match sonar_client_for(&project_service, project.sonar_instance_id, "USER_TOKEN").await {
+        Ok(client) => client,
+        Err(response) => return Ok(response),
+    };
+
+    Ok(project_permissions_response(&sonar_client, &project.project_key).await)
+}
+
+pub async fn update_project_permissions(
+    req: web::Json<UpdateProjectPermissionsRequest>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    if req.template.is_none() && req.add.is_empty() && req.remove.is_empty() {
+        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "Provide a template or permissions to add or remove"
+        })));
+    }
+    let invalid: Vec<String> = req
+        .add
+        .iter()
+        .chain(&req.remove)
+        .filter_map(|change| validate_permission_change(change).err())
+        .collect();
+    if !invalid.is_empty() {
+        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "Invalid permission changes",
+            "invalid_changes": invalid,
+            "allowed_permissions": PROJECT_PERMISSIONS
+        })));
+    }
+
+    let project = match find_project(&project_service, &req.project_path).await {
+        Ok(project) => project,
+        Err(response) => return Ok(response),
+    };
+    let sonar_client = match sonar_client_for(&project_service, project.sonar_instance_id, "USER_TOKEN").await {
+        Ok(client) => client,
+        Err(response) => return Ok(response),
+    };
+
+    if let Some(template_name) = &req.template
+        && let Err(e) = sonar_client.apply_permission_template(&project.project_key, template_name).await
+    {
+        return Ok(sonar_error_response("Failed to apply permission template", &e));
+    }
+
+    for change in &req.add {
+        if let Err(e) = apply_permission_change(&sonar_client, &project.project_key, change, true).await {
+            return Ok(sonar_error_response(&format!("Failed to grant {}", change.permission), &e));
+        }
+    }
+    for change in &req.remove {
+        if let Err(e) = apply_permission_change(&sonar_client, &project.project_key, change, false).await {
+            return Ok(sonar_error_response(&format!("Failed to revoke {}", change.permission), &e));
+        }
+    }
+    info!("Updated permissions of {}", project.project_key);
+
+    Ok(project_permissions_response(&sonar_client, &project.project_key).await)
+}
+
+// Webhook DTOs
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WebhooksQuery {
+    // Lists the global webhooks when omitted
+    pub project_path: Option<String>,
+    // Instance of the global webhooks, the default instance otherwise
+    pub sonar_instance_id: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateWebhookRequest {
+    pub name: String,
+    pub url: String,
+    // Creates a global webhook when omitted
+    pub project_path: Option<String>,
+    // Instance of a global webhook, the default instance otherwise
+    pub sonar_instance_id: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeleteWebhookRequest {
+    pub key: String,
+    // Project the webhook belongs to, to find its instance
+    pub project_path: Option<String>,
+    // Instance of a global webhook, the default instance otherwise
+    pub sonar_instance_id: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WebhookEventsQuery {
+    pub project_path: String,
+    pub limit: Option<u64>,
+}
+
+const DEFAULT_WEBHOOK_EVENTS: u64 = 20;
+const MAX_WEBHOOK_EVENTS: u64 = 200;
+
+async fn optional_project(project_service: &ProjectService, project_path: &Option<String>) -> std::result::Result<Option<ProjectResponse>, HttpResponse> {
+    match project_path {
+        Some(project_path) => find_project(project_service, project_path).await.map(Some),
+        None => Ok(None),
+    }
+}
+
+fn webhook_instance(project: &Option<ProjectResponse>, sonar_instance_id: Option<i32>) -> Option<i32> {
+    match project {
+        Some(project) => project.sonar_instance_id,
+        None => sonar_instance_id,
+    }
+}
+
+pub async fn get_webhooks(
+    query: web::Query<WebhooksQuery>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    let project = match optional_project(&project_service, &query.project_path).await {
+        Ok(project) => project,
+        Err(response) => return Ok(response),
+    };
+    let project_key = project.as_ref().map(|project| project.project_key.clone());
+    // Webhooks of a project live on its instance, global ones on the given or default instance
+    let instance_id = webhook_instance(&project, query.sonar_instance_id);
+    let sonar_client = match sonar_client_for(&project_service, instance_id, "USER_TOKEN").await {
+        Ok(client) => client,
+        Err(response) => return Ok(response),
+    };
+
+    match sonar_client.list_webhooks(project_key.as_deref()).await {
+        Ok(webhooks) => Ok(HttpResponse::Ok().json(serde_json::json!({
+            "project_key": project_key,
+            "webhooks": webhooks
+        }))),
+        Err(e) => Ok(sonar_error_response("Failed to list webhooks", &e)),
+    }
+}
+
+pub async fn create_webhook(
+    req: web::Json<CreateWebhookRequest>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    let project = match optional_project(&project_service, &req.project_path).await {
+        Ok(project) => project,
+        Err(response) => return Ok(response),
+    };
+    let project_key = project.as_ref().map(|project| project.project_key.clone());
+    // Webhooks of a project live on its instance, global ones on the given or default instance
+    let instance_id = webhook_instance(&project, req.sonar_instance_id);
+    let sonar_client = match sonar_client_for(&project_service, instance_id, "USER_TOKEN").await {
+        Ok(client) => client,
+        Err(response) => return Ok(response),
+    };
+
+    // Signed with the secret /api/webhooks/sonarqube verifies, so deliveries aren't rejected
+    let secret = webhook_secret();
+    match sonar_client.create_webhook(&req.name, &req.url, project_key.as_deref(), secret.as_deref()).await {
+        Ok(webhook) => Ok(HttpResponse::Ok().json(serde_json::json!({
+            "message": "Webhook created successfully",
+            "project_key": project_key,
+            "webhook": webhook
+        }))),
+        Err(e) => Ok(sonar_error_response("Failed to create webhook", &e)),
+    }
+}
+
+pub async fn delete_webhook(
+    req: web::Json<DeleteWebhookRequest>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    let project = match optional_project(&project_service, &req.project_path).await {
+        Ok(project) => project,
+        Err(response) => return Ok(response),
+    };
+    let instance_id = webhook_instance(&project, req.sonar_instance_id);
+    let sonar_client = match sonar_client_for(&project_service, instance_id, "USER_TOKEN").await {
+        Ok(client) => client,
+        Err(response) => return Ok(response),
+    };
+
+    if let Err(e) = sonar_client.delete_webhook(&req.key).await {
+        return Ok(sonar_error_response("Failed to delete webhook", &e));
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Webhook deleted successfully",
+        "key": req.key
+    })))
+}
+
+// Called by SonarQube when an analysis has been processed
+pub async fn receive_sonar_webhook(
+    request: HttpRequest,
+    body: web::Bytes,
+    project_service: web::Data<ProjectService>,
+    source_cache: web::Data<SourceCache>,
+) -> Result<HttpResponse> {
+    let Some(secret) = webhook_secret() else {
+        return Ok(HttpResponse::ServiceUnavailable().json(serde_json::json!({
+            "error": "Webhook receiver is disabled, set SONAR_WEBHOOK_SECRET to enable it"
+        })));
+    };
+
+    let signature = request.headers().get(SIGNATURE_HEADER).and_then(|value| value.to_str().ok());
+    if !signature.is_some_and(|signature| verify_signature(&secret, &body, signature)) {
+        warn!("Rejected webhook delivery with a missing or invalid signature");
+        return Ok(HttpResponse::Unauthorized().json(serde_json::json!({
+            "error": "Invalid webhook signature"
+        })));
+    }
+
+    let payload: WebhookPayload = match serde_json::from_slice(&body) {
+        Ok(payload) => payload,
+        Err(e) => {
+            return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+                "error": format!("Invalid webhook payload: {}", e)
+            })));
+        }
+    };
+
+    // Older payloads only carry the task id, the task knows which analysis it produced
+    let mut analysis_id = payload.analysis_id.clone();
+    if analysis_id.is_none()
+        && let Some(task_id) = &payload.task_id
+        && let Ok(sonar_client) = sonar_client_for(&project_service, webhook_instance_id(&project_service, &payload).await, "GLOBAL_ANALYSIS_TOKEN").await
+    {
+        match sonar_client.get_ce_task(task_id).await {
+            Ok(task) => analysis_id = task.analysis_id,
+            Err(e) => warn!("Failed to look up analysis of task {}: {}", task_id, e),
+        }
+    }
+
+    let raw_payload = String::from_utf8_lossy(&body).into_owned();
+    match project_service.record_webhook_event(&payload, analysis_id, raw_payload).await {
+        Ok(event) => {
+            info!(
+                "Received analysis of {} ({}), quality gate {}",
+                event.project_key,
+                event.branch.as_deref().unwrap_or("main branch"),
+                event.quality_gate_status.as_deref().unwrap_or("unknown")
+            );
+            // SonarQube gives up on a delivery after 10 seconds, so the snapshot is taken in the background
+            if payload.status.as_deref() == Some("SUCCESS") {
+                // The analysed code may have changed, snippets must not be built from older files
+                source_cache.invalidate_project(&event.project_key);
+                let target = webhook_target(&payload);
+                let project_service = project_service.clone();
+                actix_web::rt::spawn(async move {
+                    snapshot_analysis(&project_service, event.project_key, target, event.analysis_id).await;
+                });
+            }
+            Ok(HttpResponse::Ok().json(serde_json::json!({
+                "received": true,
+                "id": event.id
+            })))
+        }
+        Err(e) => Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to store webhook event: {}", e)
+        }))),
+    }
+}
+
+// Instance that sent a delivery: the one of the registered project, else the one at the payload's serverUrl
+async fn webhook_instance_id(project_service: &ProjectService, payload: &WebhookPayload) -> Option<i32> {
+    if let Ok(Some(project)) = project_service.get_project_by_key(&payload.project.key).await {
+        return project.sonar_instance_id;
+    }
+    let server_url = payload.server_url.as_deref()?;
+    match project_service.get_sonar_instance_by_url(server_url).await {
+        Ok(instance) => instance.map(|instance| instance.id),
+        Err(e) => {
+            warn!("Failed to look up the instance at {}: {}", server_url, e);
+            None
+        }
+    }
+}
+
+// Branch or pull request a webhook reports on, the main branch being the default target
+fn webhook_target(payload: &WebhookPayload) -> AnalysisTarget {
+    match &payload.branch {
+        Some(branch) if branch.branch_type.as_deref() == Some("PULL_REQUEST") => AnalysisTarget {
+            branch: None,
+            pull_request: Some(branch.name.clone()),
+        },
+        Some(branch) if branch.is_main != Some(true) => AnalysisTarget {
+            branch: Some(branch.name.clone()),
+            pull_request: None,
+        },
+        _ => AnalysisTarget::default(),
+    }
+}
+
+// Stores the results of a finished analysis of a registered project
+async fn snapshot_analysis(project_service: &ProjectService, project_key: String, target: AnalysisTarget, analysis_id: Option<String>) {
+    let project = match project_service.get_project_by_key(&project_key).await {
+        Ok(Some(project)) => project,
+        Ok(None) => return,
+        Err(e) => {
+            warn!("Failed to look up project {}: {}", project_key, e);
+            return;
+        }
+    };
+    let Ok(sonar_client) = sonar_client_for(project_service, project.sonar_instance_id, "GLOBAL_ANALYSIS_TOKEN").await else {
+        warn!("No GLOBAL_ANALYSIS_TOKEN, skipping snapshot of {}", project_key);
+        return;
+    };
+
+    let results = collect_project_results(&sonar_client, project_service, &project, &target, None).await;
+    let snapshot = NewSnapshot {
+        project_key: &project.project_key,
+        target: &target,
+        source: "webhook",
+        analysis_id,
+        quality_gate: results.quality_gate.as_ref(),
+        measures: &results.measures,
+        issues: results.issues.as_deref(),
+        results: &results.response,
+    };
+    match project_service.save_snapshot(snapshot).await {
+        Ok(snapshot) => info!("Stored snapshot {} of {}", snapshot.id, project_key),
+        Err(e) => warn!("Failed to store snapshot of {}: {}", project_key, e),
+    }
+}
+
+pub async fn get_webhook_events(
+    query: web::Query<WebhookEventsQuery>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    let project = match find_project(&project_service, &query.project_path).await {
+        Ok(project) => project,
+        Err(response) => return Ok(response),
+    };
+
+    let limit = query.limit.unwrap_or(DEFAULT_WEBHOOK_EVENTS).clamp(1, MAX_WEBHOOK_EVENTS);
+    match project_service.get_webhook_events(&project.project_key, limit).await {
+        Ok(events) => Ok(HttpResponse::Ok().json(serde_json::json!({
+            "project_key": project.project_key,
+            "events": events
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Database error: {}", e)
+        }))),
+    }
+}
+
+// Snapshot DTOs
+#[derive(Debug, Deserialize)]
+pub struct SnapshotsQuery {
+    pub project_path: String,
+    pub branch: Option<String>,
+    pub pull_request: Option<String>,
+    pub limit: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SnapshotQuery {
+    pub id: i32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SnapshotIssuesQuery {
+    pub id: i32,
+    pub severity: Option<String>,
+    #[serde(rename = "type")]
+    pub issue_type: Option<String>,
+    pub rule: Option<String>,
+}
+
+const DEFAULT_SNAPSHOTS: u64 = 20;
+const MAX_SNAPSHOTS: u64 = 200;
+
+pub async fn get_snapshots(
+    query: web::Query<SnapshotsQuery>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    let query = query.into_inner();
+    let target = AnalysisTarget {
+        branch: query.branch,
+        pull_request: query.pull_request,
+    };
+    if let Some(response) = invalid_target_response(&target) {
+        return Ok(response);
+    }
+    let project = match find_project(&project_service, &query.project_path).await {
+        Ok(project) => project,
+        Err(response) => return Ok(response),
+    };
+
+    let limit = query.limit.unwrap_or(DEFAULT_SNAPSHOTS).clamp(1, MAX_SNAPSHOTS);
+    match project_service.get_snapshots(&project.project_key, &target, limit).await {
+        Ok(snapshots) => Ok(HttpResponse::Ok().json(serde_json::json!({
+            "project_key": project.project_key,
+            "snapshots": snapshots
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Database error: {}", e)
+        }))),
+    }
+}
+
+pub async fn get_snapshot(
+    query: web::Query<SnapshotQuery>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    match project_service.get_snapshot(query.id).await {
+        Ok(Some(snapshot)) => Ok(HttpResponse::Ok().json(snapshot)),
+        Ok(None) => Ok(HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Snapshot not found"
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Database error: {}", e)
+        }))),
+    }
+}
+
+pub async fn get_snapshot_issues(
+    query: web::Query<SnapshotIssuesQuery>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    let query = query.into_inner();
+    let filter = SnapshotIssueFilter {
+        severity: query.severity.map(|severity| severity.to_uppercase()),
+        issue_type: query.issue_type.map(|issue_type| issue_type.to_uppercase()),
+        rule: query.rule,
+    };
+
+    match project_service.get_snapshot_issues(query.id, &filter).await {
+        Ok(issues) => Ok(HttpResponse::Ok().json(serde_json::json!({
+            "snapshot_id": query.id,
+            "total": issues.len(),
+            "issues": issues
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Database error: {}", e)
+        }))),
+    }
+}
+
+// Security hotspots DTOs
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HotspotsRequest {
+    pub project_path: String,
+    #[serde(flatten)]
+    pub target: AnalysisTarget,
+    pub status: Option<String>, // "TO_REVIEW" or "REVIEWED"
+    pub resolution: Option<String>, // "FIXED", "SAFE" or "ACKNOWLEDGED", only with status REVIEWED
+    pub vulnerability_probability: Option<String>, // "HIGH", "MEDIUM" or "LOW"
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HotspotDetailsQuery {
+    pub key: String,
+    // Project the hotspot belongs to, to find its instance
+    pub project_path: Option<String>,
+}
+
+const HOTSPOT_STATUSES: [&str; 2] = ["TO_REVIEW", "REVIEWED"];
+const HOTSPOT_RESOLUTIONS: [&str; 3] = ["FIXED", "SAFE", "ACKNOWLEDGED"];
+const VULNERABILITY_PROBABILITIES: [&str; 3] = ["HIGH", "MEDIUM", "LOW"];
+
+pub async fn get_project_hotspots(
+    req: web::Json<HotspotsRequest>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    if let Some(response) = invalid_target_response(&req.target) {
+        return Ok(response);
+    }
+
+    let status = req.status.as_deref().map(str::to_uppercase);
+    let resolution = req.resolution.as_deref().map(str::to_uppercase);
+    let probability = req.vulnerability_probability.as_deref().map(str::to_uppercase);
+
+    if let Some(status) = &status
+        && !HOTSPOT_STATUSES.contains(&status.as_str())
+    {
+        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("Invalid status '{}', expected one of {:?}", status, HOTSPOT_STATUSES)
+        })));
+    }
+    if let Some(resolution) = &resolution {
+        if !HOTSPOT_RESOLUTIONS.contains(&resolution.as_str()) {
+            return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+                "error": format!("Invalid resolution '{}', expected one of {:?}", resolution, HOTSPOT_RESOLUTIONS)
+            })));
+        }
+        if status.as_deref() != Some("REVIEWED") {
+            return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "A resolution can only be used with status REVIEWED"
+            })));
+        }
+    }
+    if let Some(probability) = &probability
+        && !VULNERABILITY_PROBABILITIES.contains(&probability.as_str())
+    {
+        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("Invalid vulnerability_probability '{}', expected one of {:?}", probability, VULNERABILITY_PROBABILITIES)
+        })));
+    }
+
+    let project = match find_project(&project_service, &req.project_path).await {
+        Ok(project) => project,
+        Err(response) => return Ok(response),
+    };
+    let sonar_client = match sonar_client_for(&project_service, project.sonar_instance_id, "GLOBAL_ANALYSIS_TOKEN").await {
+        Ok(client) => client,
+        Err(response) => return Ok(response),
+    };
+
+    let mut hotspots_response = match sonar_client
+        .search_hotspots(&project.project_key, &req.target, status.as_deref(), resolution.as_deref())
+        .await
+    {
+        Ok(hotspots) => hotspots,
+        Err(e) => return Ok(sonar_error_response("Failed to fetch hotspots", &e)),
+    };
+
+    // SonarQube can't filter on probability, so it is applied here
+    if let Some(probability) = &probability {
+        hotspots_response.hotspots.retain(|h| &h.vulnerability_probability == probability);
+        hotspots_response.paging.total = hotspots_response.hotspots.len() as i32;
+        hotspots_response.paging.page_size = hotspots_response.paging.total;
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "project_key": project.project_key,
+        "hotspots": hotspots_response
+    })))
+}
+
+pub async fn get_hotspot_details(
+    query: web::Query<HotspotDetailsQuery>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    let project = match optional_project(&project_service, &query.project_path).await {
+        Ok(project) => project,
+        Err(response) => return Ok(response),
+    };
+    let instance_id = project.and_then(|project| project.sonar_instance_id);
+    let sonar_client = match sonar_client_for(&project_service, instance_id, "GLOBAL_ANALYSIS_TOKEN").await {
+        Ok(client) => client,
+        Err(response) => return Ok(response),
+    };
+
+    match sonar_client.get_hotspot(&query.key).await {
+        Ok(details) => Ok(HttpResponse::Ok().json(details)),
+        Err(e) => Ok(sonar_error_response("Failed to get hotspot details", &e)),
+    }
+}
+
+// Code snippet DTOs
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SnippetsRequest {
+    pub project_path: String,
+    #[serde(flatten)]
+    pub target: AnalysisTarget,
+    // Defaults to every unresolved issue of the project
+    pub issue_keys: Option<Vec<String>>,
+    pub context: Option<i32>,
+}
+
+pub async fn get_issue_snippets(
+    req: web::Json<SnippetsRequest>,
+    project_service: web::Data<ProjectService>,
+    source_cache: web::Data<SourceCache>,
+) -> Result<HttpResponse> {
+    if let Some(response) = invalid_target_response(&req.target) {
+        return Ok(response);
+    }
+
+    let project = match find_project(&project_service, &req.project_path).await {
+        Ok(project) => project,
+        Err(response) => return Ok(response),
+    };
+    let sonar_client = match sonar_client_for(&project_service, project.sonar_instance_id, "GLOBAL_ANALYSIS_TOKEN").await {
+        Ok(client) => client,
+        Err(response) => return Ok(response),
+    };
+
+    let issues_result = match &req.issue_keys {
+        Some(keys) => sonar_client.get_issues_by_keys(keys, &req.target).await,
+        None => sonar_client.get_project_issues(&project.project_key, &req.target).await.map(|r| r.issues),
+    };
+    let mut issues = match issues_result {
+        // Keys from another project must not leak their code
+        Ok(issues) => issues.into_iter().filter(|issue| issue.project == project.project_key).collect::<Vec<_>>(),
+        Err(e) => return Ok(sonar_error_response("Failed to fetch issues", &e)),
+    };
+
+    attach_snippets(&sonar_client, &source_cache, &req.target, &mut issues, req.context.unwrap_or(DEFAULT_CONTEXT_LINES)).await;
+
+    let snippets: HashMap<String, _> = issues
+        .into_iter()
+        .filter_map(|issue| issue.snippet.map(|snippet| (issue.key, snippet)))
+        .collect();
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "project_key": project.project_key,
+        "snippets": snippets
+    })))
+}
+
+// Issue workflow DTOs
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IssueTransitionRequest {
+    pub project_path: String,
+    #[serde(flatten)]
+    pub target: AnalysisTarget,
+    pub issue_key: String,
+    pub transition: String,
+    pub comment: Option<String>,
+}
+
+const ISSUE_TRANSITIONS: [&str; 8] = [
+    "confirm",
+    "unconfirm",
+    "reopen",
+    "resolve",
+    "falsepositive",
+    "wontfix",
+    "accept",
+    "close",
+];
+
+// Looks the issue up within the project so keys from other projects are rejected
+async fn find_project_issue(
+    sonar_client: &SonarQubeClient,
+    project_key: &str,
+    target: &AnalysisTarget,
+    issue_key: &str,
+) -> std::result::Result<Issue, HttpResponse> {
+    match sonar_client.get_issues_by_keys(&[issue_key.to_string()], target).await {
+        Ok(issues) => issues
+            .into_iter()
+            .find(|issue| issue.key == issue_key && issue.project == project_key)
+            .ok_or_else(|| {
+                HttpResponse::NotFound().json(serde_json::json!({
+                    "error": format!("Issue not found in project: {}", issue_key)
+                }))
+            }),
+        Err(e) => Err(sonar_error_response("Failed to fetch issue", &e)),
+    }
+}
+
+pub async fn transition_issue(
+    req: web::Json<IssueTransitionRequest>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    let transition = req.transition.trim().to_lowercase();
+    if !ISSUE_TRANSITIONS.contains(&transition.as_str()) {
+        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("Unknown transition: {}", req.transition),
+            "allowed_transitions": ISSUE_TRANSITIONS
+        })));
+    }
+
+    let (project, sonar_client, issue) =
+        match issue_write_context(&project_service, &req.project_path, &req.target, &req.issue_key).await {
+            Ok(context) => context,
+            Err(response) => return Ok(response),
+        };
+    if !issue.transitions.contains(&transition) {
+        return Ok(HttpResponse::Conflict().json(serde_json::json!({
+            "error": format!("Transition '{}' is not available for issue {} in status {}", transition, issue.key, issue.status),
+            "available_transitions": issue.transitions
+        })));
+    }
+
+    let mut issue = match sonar_client.do_issue_transition(&issue.key, &transition).await {
+        Ok(issue) => issue,
+        Err(e) => return Ok(sonar_error_response("Failed to transition issue", &e)),
+    };
+    info!("Applied transition {} to issue {}", transition, issue.key);
+
+    if let Some(comment) = req.comment.as_deref().map(str::trim).filter(|c| !c.is_empty()) {
+        issue = match sonar_client.add_issue_comment(&issue.key, comment).await {
+            Ok(issue) => issue,
+            Err(e) => {
+                // The transition already happened, so report it alongside the failure
+                return Ok(HttpResponse::build(sonar_error_status(&e)).json(serde_json::json!({
+                    "error": format!("Issue transitioned but the comment failed: {}", e),
+                    "issue": issue
+                })));
+            }
+        };
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "project_key": project.project_key,
+        "issue": issue
+    })))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IssueAssignRequest {
+    pub project_path: String,
+    #[serde(flatten)]
+    pub target: AnalysisTarget,
+    pub issue_key: String,
+    // SonarQube login; omit to unassign
+    pub assignee: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IssueCommentRequest {
+    pub project_path: String,
+    #[serde(flatten)]
+    pub target: AnalysisTarget,
+    pub issue_key: String,
+    // Required to edit or delete an existing comment
+    pub comment_key: Option<String>,
+    // Required to add or edit a comment
+    pub text: Option<String>,
+}
+
+// Resolves the project, a USER_TOKEN client and the issue shared by the issue write endpoints
+async fn issue_write_context(
+    project_service: &ProjectService,
+    project_path: &str,
+    target: &AnalysisTarget,
+    issue_key: &str,
+) -> std::result::Result<(ProjectResponse, SonarQubeClient, Issue), HttpResponse> {
+    if let Some(response) = invalid_target_response(target) {
+        return Err(response);
+    }
+
+    let project = find_project(project_service, project_path).await?;
+    let sonar_client = sonar_client_for(project_service, project.sonar_instance_id, "USER_TOKEN").await?;
+    let issue = find_project_issue(&sonar_client, &project.project_key, target, issue_key).await?;
+
+    Ok((project, sonar_client, issue))
+}
+
+fn comment_text(text: &Option<String>) -> std::result::Result<&str, HttpResponse> {
+    text.as_deref().map(str::trim).filter(|text| !text.is_empty()).ok_or_else(|| {
+        HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "text is required"
+        }))
+    })
+}
+
+// Comment keys are only accepted when they belong to the issue
+fn find_issue_comment<'a>(issue: &Issue, comment_key: &'a Option<String>) -> std::result::Result<&'a str, HttpResponse> {
+    let Some(comment_key) = comment_key.as_deref() else {
+        return Err(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "comment_key is required"
+        })));
+    };
+    if !issue.comments.iter().any(|comment| comment.key == comment_key) {
+        return Err(HttpResponse::NotFound().json(serde_json::json!({
+            "error": format!("Comment not found on issue {}: {}", issue.key, comment_key)
+        })));
+    }
+    Ok(comment_key)
+}
+
+fn issue_changed_response(project_key: String, result: std::result::Result<Issue, SonarQubeError>, context: &str) -> HttpResponse {
+    match result {
+        Ok(issue) => HttpResponse::Ok().json(serde_json::json!({
+            "project_key": project_key,
+            "issue": issue
+        })),
+        Err(e) => sonar_error_response(context, &e),
+    }
+}
+
+pub async fn assign_issue(
+    req: web::Json<IssueAssignRequest>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    let (project, sonar_client, issue) =
+        match issue_write_context(&project_service, &req.project_path, &req.target, &req.issue_key).await {
+            Ok(context) => context,
+            Err(response) => return Ok(response),
+        };
+
+    let assignee = req.assignee.as_deref().map(str::trim).filter(|assignee| !assignee.is_empty());
+    let result = sonar_client.assign_issue(&issue.key, assignee).await;
+    if result.is_ok() {
+        info!("Assigned issue {} to {}", issue.key, assignee.unwrap_or("nobody"));
+    }
+
+    Ok(issue_changed_response(project.project_key, result, "Failed to assign issue"))
+}
+
+pub async fn add_issue_comment(
+    req: web::Json<IssueCommentRequest>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    let text = match comment_text(&req.text) {
+        Ok(text) => text,
+        Err(response) => return Ok(response),
+    };
+    let (project, sonar_client, issue) =
+        match issue_write_context(&project_service, &req.project_path, &req.target, &req.issue_key).await {
+            Ok(context) => context,
+            Err(response) => return Ok(response),
+        };
+
+    let result = sonar_client.add_issue_comment(&issue.key, text).await;
+    Ok(issue_changed_response(project.project_key, result, "Failed to add comment"))
+}
+
+pub async fn edit_issue_comment(
+    req: web::Json<IssueCommentRequest>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    let text = match comment_text(&req.text) {
+        Ok(text) => text,
+        Err(response) => return Ok(response),
+    };
+    let (project, sonar_client, issue) =
+        match issue_write_context(&project_service, &req.project_path, &req.target, &req.issue_key).await {
+            Ok(context) => context,
+            Err(response) => return Ok(response),
+        };
+    let comment_key = match find_issue_comment(&issue, &req.comment_key) {
+        Ok(comment_key) => comment_key,
+        Err(response) => return Ok(response),
+    };
+
+    let result = sonar_client.edit_issue_comment(comment_key, text).await;
+    Ok(issue_changed_response(project.project_key, result, "Failed to edit comment"))
+}
+
+pub async fn delete_issue_comment(
+    req: web::Json<IssueCommentRequest>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    let (project, sonar_client, issue) =
+        match issue_write_context(&project_service, &req.project_path, &req.target, &req.issue_key).await {
+            Ok(context) => context,
+            Err(response) => return Ok(response),
+        };
+    let comment_key = match find_issue_comment(&issue, &req.comment_key) {
+        Ok(comment_key) => comment_key,
+        Err(response) => return Ok(response),
+    };
+
+    let result = sonar_client.delete_issue_comment(comment_key).await;
+    Ok(issue_changed_response(project.project_key, result, "Failed to delete comment"))
+}
+
+// Bulk issue change DTOs
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BulkIssueFilter {
+    pub rules: Option<Vec<String>>,
+    pub severities: Option<Vec<String>>,
+    pub types: Option<Vec<String>>,
+    // Glob on the file path relative to the project root, e.g. "src/main/**/*.java"
+    pub file: Option<String>,
+    pub author: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BulkIssueChangeRequest {
+    pub project_path: String,
+    #[serde(flatten)]
+    pub target: AnalysisTarget,
+    // Either explicit keys or a filter over the project's unresolved issues
+    pub issue_keys: Option<Vec<String>>,
+    pub filter: Option<BulkIssueFilter>,
+    pub changes: BulkChange,
+}
+
+#[derive(Debug, Serialize)]
+struct BulkIssueResult {
+    key: String,
+    // "updated", "not_applied", "failed" or "not_found"
+    status: &'static str,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    problems: Vec<String>,
+}
+
+impl BulkIssueFilter {
+    fn file_matcher(&self) -> std::result::Result<Option<GlobMatcher>, globset::Error> {
+        self.file
+            .as_deref()
+            .map(|pattern| GlobBuilder::new(pattern).literal_separator(true).build().map(|glob| glob.compile_matcher()))
+            .transpose()
+    }
+
+    fn matches(&self, issue: &Issue, project_key: &str, file_matcher: Option<&GlobMatcher>) -> bool {
+        if let Some(rules) = &self.rules && !rules.contains(&issue.rule) {
+            return false;
+        }
+        if let Some(severities) = &self.severities && !severities.iter().any(|s| s.eq_ignore_ascii_case(&issue.severity)) {
+            return false;
+        }
+        if let Some(types) = &self.types && !types.iter().any(|t| t.eq_ignore_ascii_case(&issue.issue_type)) {
+            return false;
+        }
+        if let Some(author) = &self.author && issue.author.as_deref() != Some(author.as_str()) {
+            return false;
+        }
+        if let Some(matcher) = file_matcher {
+            let prefix = format!("{}:", project_key);
+            match issue.component.strip_prefix(&prefix) {
+                Some(path) if matcher.is_match(path) => {}
+                _ => return false,
+            }
+        }
+        true
+    }
+}
+
+// Normalises the requested changes and reports the first invalid value
+fn validate_bulk_change(change: &mut BulkChange) -> std::result::Result<(), String> {
+    if let Some(transition) = &mut change.transition {
+        *transition = transition.trim().to_lowercase();
+        if !ISSUE_TRANSITIONS.contains(&transition.as_str()) {
+            return Err(format!("Unknown transition: {}", transition));
+        }
+    }
+    if let Some(severity) = &mut change.set_severity {
+        *severity = severity.trim().to_uppercase();
+        if !ISSUE_SEVERITIES.contains(&severity.as_str()) {
+            return Err(format!("Unknown severity: {}", severity));
+        }
+    }
+    if let Some(issue_type) = &mut change.set_type {
+        *issue_type = issue_type.trim().to_uppercase();
+        if !ISSUE_TYPES.contains(&issue_type.as_str()) {
+            return Err(format!("Unknown issue type: {}", issue_type));
+        }
+    }
+    if let Some(assign) = &mut change.assign {
+        *assign = assign.trim().to_string();
+    }
+    if change.comment.as_deref().is_some_and(|comment| comment.trim().is_empty()) {
+        change.comment = None;
+    }
+    if change.is_empty() {
+        return Err("changes must contain at least one action".to_string());
+    }
+    Ok(())
+}
+
+// SonarQube only reports counts, so each issue is compared before and after the change
+fn unapplied_changes(before: &Issue, after: &Issue, change: &BulkChange) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    if let Some(assign) = &change.assign && after.assignee.as_deref().unwrap_or("") != assign {
+        problems.push(format!("assignee is {}", after.assignee.as_deref().unwrap_or("unset")));
+    }
+    if let Some(transition) = &change.transition {
+        if !before.transitions.contains(transition) {
+            problems.push(format!("transition {} is not available in status {}", transition, before.status));
+        } else if after.status == before.status && after.resolution == before.resolution {
+            problems.push(format!("transition {} was not applied", transition));
+        }
+    }
+    for tag in change.add_tags.iter().filter(|tag| !after.tags.contains(tag)) {
+        problems.push(format!("tag {} was not added", tag));
+    }
+    for tag in change.remove_tags.iter().filter(|tag| after.tags.contains(tag)) {
+        problems.push(format!("tag {} was not removed", tag));
+    }
+    if let Some(severity) = &change.set_severity && &after.severity != severity {
+        problems.push(format!("severity is {}", after.severity));
+    }
+    if let Some(issue_type) = &change.set_type && &after.issue_type != issue_type {
+        problems.push(format!("type is {}", after.issue_type));
+    }
+    if change.comment.is_some() && after.comments.len() <= before.comments.len() {
+        problems.push("comment was not added".to_string());
+    }
+
+    problems
+}
+
+fn failed_results(keys: Vec<String>, error: &SonarQubeError) -> impl Iterator<Item = BulkIssueResult> + '_ {
+    keys.into_iter().map(move |key| BulkIssueResult {
+        key,
+        status: "failed",
+        problems: vec![error.to_string()],
+    })
+}
+
+// Outcome of a change SonarQube accepted, `after` is the issue read back
+fn verified_result(before: &Issue, after: Option<&Issue>, change: &BulkChange) -> BulkIssueResult {
+    let problems = match after {
+        Some(after) => unapplied_changes(before, after, change),
+        None => vec!["issue could not be read back".to_string()],
+    };
+    BulkIssueResult {
+        key: before.key.clone(),
+        status: if problems.is_empty() { "updated" } else { "not_applied" },
+        problems,
+    }
+}
+
+fn count_results(results: &[BulkIssueResult], status: &str) -> usize {
+    results.iter().filter(|result| result.status == status).count()
+}
+
+pub async fn bulk_change_issues(
+    req: web::Json<BulkIssueChangeRequest>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    let req = req.into_inner();
+    if let Some(response) = invalid_target_response(&req.target) {
+        return Ok(response);
+    }
+    if req.issue_keys.is_some() == req.filter.is_some() {
+        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "Provide either issue_keys or filter"
+        })));
+    }
+
+    let mut changes = req.changes;
+    if let Err(message) = validate_bulk_change(&mut changes) {
+        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": message
+        })));
+    }
+    let file_matcher = match req.filter.as_ref().map(BulkIssueFilter::file_matcher).transpose() {
+        Ok(matcher) => matcher.flatten(),
+        Err(e) => {
+            return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+                "error": format!("Invalid file glob: {}", e)
+            })));
+        }
+    };
+
+    let project = match find_project(&project_service, &req.project_path).await {
+        Ok(project) => project,
+        Err(response) => return Ok(response),
+    };
+    let sonar_client = match sonar_client_for(&project_service, project.sonar_instance_id, "USER_TOKEN").await {
+        Ok(client) => client,
+        Err(response) => return Ok(response),
+    };
+
+    let mut results = Vec::new();
+    let issues_result = match &req.issue_keys {
+        Some(keys) => sonar_client.get_issues_by_keys(keys, &req.target).await,
+        None => sonar_client.get_project_issues(&project.project_key, &req.target).await.map(|r| r.issues),
+    };
+    let issues: Vec<Issue> = match issues_result {
+        Ok(issues) => issues
+            .into_iter()
+            .filter(|issue| issue.project == project.project_key)
+            .filter(|issue| {
+                req.filter
+                    .as_ref()
+                    .is_none_or(|filter| filter.matches(issue, &project.project_key, file_matcher.as_ref()))
+            })
+            .collect(),
+        Err(e) => return Ok(sonar_error_response("Failed to fetch issues", &e)),
+    };
+    if let Some(keys) = &req.issue_keys {
+        for key in keys.iter().filter(|key| !issues.iter().any(|issue| &issue.key == *key)) {
+            results.push(BulkIssueResult {
+                key: key.clone(),
+                status: "not_found",
+                problems: vec!["Issue not found in project".to_string()],
+            });
+        }
+    }
+
+    let mut sonar_counts = BulkChangeResponse::default();
+    let mut changed_keys = Vec::new();
+    for batch in issues.chunks(ISSUE_BULK_CHANGE_LIMIT) {
+        let keys: Vec<String> = batch.iter().map(|issue| issue.key.clone()).collect();
+        match sonar_client.bulk_change_issues(&keys, &changes).await {
+            Ok(counts) => {
+                sonar_counts.total += counts.total;
+                sonar_counts.success += counts.success;
+                sonar_counts.ignored += counts.ignored;
+                sonar_counts.failures += counts.failures;
+                changed_keys.extend(keys);
+            }
+            Err(e) => {
+                warn!("Bulk change of {} issues failed: {}", keys.len(), e);
+                results.extend(failed_results(keys, &e));
+            }
+        }
+    }
+
+    if !changed_keys.is_empty() {
+        let after: HashMap<String, Issue> = match sonar_client.get_issues_by_keys(&changed_keys, &req.target).await {
+            Ok(issues) => issues.into_iter().map(|issue| (issue.key.clone(), issue)).collect(),
+            Err(e) => return Ok(sonar_error_response("Issues were changed but could not be verified", &e)),
+        };
+        let changed_keys: HashSet<String> = changed_keys.into_iter().collect();
+        for before in issues.iter().filter(|issue| changed_keys.contains(&issue.key)) {
+            results.push(verified_result(before, after.get(&before.key), &changes));
+        }
+    }
+
+    let count = |status: &str| count_results(&results, status);
+    info!(
+        "Bulk change on {}: {} updated, {} not applied, {} failed",
+        project.project_key,
+        count("updated"),
+        count("not_applied"),
+        count("failed")
+    );
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "project_key": project.project_key,
+        "matched": issues.len(),
+        "summary": {
+            "updated": count("updated"),
+            "not_applied": count("not_applied"),
+            "failed": count("failed"),
+            "not_found": count("not_found")
+        },
+        "sonar": sonar_counts,
+        "results": results
+    })))
+}
+
+// Per-file measures DTOs
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ComponentMeasuresRequest {
+    pub project_path: String,
+    #[serde(flatten)]
+    pub target: AnalysisTarget,
+    pub qualifiers: Option<Vec<String>>, // "FIL" and/or "DIR", defaults to both
+    pub sort_metric: Option<String>,
+    pub ascending: Option<bool>,
+    pub page: Option<i32>,
+    pub page_size: Option<i32>,
+}
+
+const COMPONENT_TREE_METRICS: [&str; 7] = [
+    "coverage",
+    "lines_to_cover",
+    "uncovered_lines",
+    "complexity",
+    "duplicated_lines",
+    "duplicated_lines_density",
+    "ncloc",
+];
+const COMPONENT_QUALIFIERS: [&str; 2] = ["FIL", "DIR"];
+const COMPONENT_TREE_MAX_PAGE_SIZE: i32 = 500;
+
+pub async fn get_component_measures(
+    req: web::Json<ComponentMeasuresRequest>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    if let Some(response) = invalid_target_response(&req.target) {
+        return Ok(response);
+    }
+
+    let qualifiers: Vec<String> = req
+        .qualifiers
+        .clone()
+        .unwrap_or_else(|| COMPONENT_QUALIFIERS.iter().map(|q| q.to_string()).collect())
+        .into_iter()
+        .map(|q| q.to_uppercase())
+        .collect();
+
+    if let Some(invalid) = qualifiers.iter().find(|q| !COMPONENT_QUALIFIERS.contains(&q.as_str())) {
+        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("Invalid qualifier '{}', expected one of {:?}", invalid, COMPONENT_QUALIFIERS)
+        })));
+    }
+    if let Some(metric) = &req.sort_metric
+        && !COMPONENT_TREE_METRICS.contains(&metric.as_str())
+    {
+        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("Invalid sort_metric '{}', expected one of {:?}", metric, COMPONENT_TREE_METRICS)
+        })));
+    }
+
+    let page = req.page.unwrap_or(1);
+    let page_size = req.page_size.unwrap_or(100);
+    if page < 1 || !(1..=COMPONENT_TREE_MAX_PAGE_SIZE).contains(&page_size) {
+        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("page must be at least 1 and page_size between 1 and {}", COMPONENT_TREE_MAX_PAGE_SIZE)
+        })));
+    }
+
+    let project = match find_project(&project_service, &req.project_path).await {
+        Ok(project) => project,
+        Err(response) => return Ok(response),
+    };
+    let sonar_client = match sonar_client_for(&project_service, project.sonar_instance_id, "GLOBAL_ANALYSIS_TOKEN").await {
+        Ok(client) => client,
+        Err(response) => return Ok(response),
+    };
+
+    let query = ComponentTreeQuery {
+        metric_keys: &COMPONENT_TREE_METRICS,
+        qualifiers: &qualifiers,
+        sort_metric: req.sort_metric.as_deref(),
+        ascending: req.ascending.unwrap_or(true),
+        page,
+        page_size,
+    };
+
+    match sonar_client.get_component_tree(&project.project_key, &req.target, &query).await {
+        Ok(tree) => Ok(HttpResponse::Ok().json(serde_json::json!({
+            "project_key": project.project_key,
+            "paging": tree.paging,
+            "components": tree.components
+        }))),
+        Err(e) => Ok(sonar_error_response("Failed to fetch component measures", &e)),
+    }
+}
+
+// Metric history DTOs
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProjectHistoryRequest {
+    pub project_path: String,
+    #[serde(flatten)]
+    pub target: AnalysisTarget,
+    pub metrics: Option<Vec<String>>,
+    pub from: Option<String>, // "YYYY-MM-DD" or "YYYY-MM-DDThh:mm:ss+hhmm"
+    pub to: Option<String>,
+}
+
+const DEFAULT_HISTORY_METRICS: [&str; 6] = [
+    "coverage",
+    "bugs",
+    "vulnerabilities",
+    "code_smells",
+    "security_hotspots",
+    "duplicated_lines_density",
+];
+
+// SonarQube accepts either a date or a date-time for history bounds
+fn parse_history_bound(value: &str) -> Option<chrono::NaiveDate> {
+    chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .or_else(|| chrono::DateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%z").ok().map(|d| d.date_naive()))
+}
+
+pub async fn get_project_history(
+    req: web::Json<ProjectHistoryRequest>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    if let Some(response) = invalid_target_response(&req.target) {
+        return Ok(response);
+    }
+
+    let metrics: Vec<String> = match &req.metrics {
+        Some(metrics) if !metrics.is_empty() => metrics.clone(),
+        _ => DEFAULT_HISTORY_METRICS.iter().map(|m| m.to_string()).collect(),
+    };
+
+    let mut bounds = Vec::new();
+    for (name, value) in [("from", &req.from), ("to", &req.to)] {
+        if let Some(value) = value {
+            match parse_history_bound(value) {
+                Some(date) => bounds.push(date),
+                None => {
+                    return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+                        "error": format!("Invalid '{}' date '{}', expected YYYY-MM-DD or YYYY-MM-DDThh:mm:ss+hhmm", name, value)
+                    })));
+                }
+            }
+        }
+    }
+    if req.from.is_some() && req.to.is_some() && bounds[0] > bounds[1] {
+        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "'from' must not be after 'to'"
+        })));
+    }
+
+    let project = match find_project(&project_service, &req.project_path).await {
+        Ok(project) => project,
+        Err(response) => return Ok(response),
+    };
+    let sonar_client = match sonar_client_for(&project_service, project.sonar_instance_id, "GLOBAL_ANALYSIS_TOKEN").await {
+        Ok(client) => client,
+        Err(response) => return Ok(response),
+    };
+
+    match sonar_client
+        .get_measures_history(&project.project_key, &req.target, &metrics, req.from.as_deref(), req.to.as_deref())
+        .await
+    {
+        Ok(history) => Ok(HttpResponse::Ok().json(serde_json::json!({
+            "project_key": project.project_key,
+            "from": req.from,
+            "to": req.to,
+            "analyses": history.paging.total,
+            "measures": history.measures
+        }))),
+        Err(e) => Ok(sonar_error_response("Failed to fetch measures history", &e)),
+    }
+}
+
+// Analysis tracking DTOs
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WaitForAnalysisRequest {
+    pub project_path: String,
+    // Task id printed by the scanner
+    pub task_id: Option<String>,
+    // Otherwise the project's latest task submitted at or after this time, e.g. when the scanner was started
+    pub submitted_after: Option<String>,
+    pub timeout_secs: Option<u64>,
+}
+
+const DEFAULT_ANALYSIS_WAIT_SECS: u64 = 120;
+const MAX_ANALYSIS_WAIT_SECS: u64 = 600;
+const ANALYSIS_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
+// The task being waited for: the given id, else the newest queued task, else the last finished one
+async fn latest_analysis_task(
+    sonar_client: &SonarQubeClient,
+    project_key: &str,
+    task_id: Option<&str>,
+) -> std::result::Result<Option<CeTask>, SonarQubeError> {
+    if let Some(task_id) = task_id {
+        let task = sonar_client.get_ce_task(task_id).await?;
+        // Task ids are global, so one of another project must not pass for this project's
+        if task.component_key.as_deref() != Some(project_key) {
+            return Err(SonarQubeError::NotFound(format!("Task {} does not belong to project {}", task_id, project_key)));
+        }
+        return Ok(Some(task));
+    }
+
+    let component = sonar_client.get_ce_component(project_key).await?;
+    if let Some(queued) = component.queue.into_iter().max_by(|a, b| a.submitted_at.cmp(&b.submitted_at)) {
+        return Ok(Some(queued));
+    }
+    if component.current.is_some() {
+        return Ok(component.current);
+    }
+
+    let tasks = sonar_client.get_ce_activity(project_key, None, 1).await?;
+    Ok(tasks.into_iter().next())
+}
+
+pub async fn wait_for_analysis(
+    req: web::Json<WaitForAnalysisRequest>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    // Without either, the latest finished task may be the previous analysis
+    if req.task_id.is_none() && req.submitted_after.is_none() {
+        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "Provide task_id or submitted_after to identify the analysis"
+        })));
+    }
+    let submitted_after = match req.submitted_after.as_deref().map(SonarQubeClient::parse_sonar_date).transpose() {
+        Ok(date) => date,
+        Err(_) => {
+            return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "Invalid submitted_after, expected YYYY-MM-DDThh:mm:ss+hhmm"
+            })));
+        }
+    };
+    let timeout_secs = req.timeout_secs.unwrap_or(DEFAULT_ANALYSIS_WAIT_SECS).min(MAX_ANALYSIS_WAIT_SECS);
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(timeout_secs);
+
+    let project = match find_project(&project_service, &req.project_path).await {
+        Ok(project) => project,
+        Err(response) => return Ok(response),
+    };
+    let sonar_client = match sonar_client_for(&project_service, project.sonar_instance_id, "GLOBAL_ANALYSIS_TOKEN").await {
+        Ok(client) => client,
+        Err(response) => return Ok(response),
+    };
+
+    let task = loop {
+        let task = match latest_analysis_task(&sonar_client, &project.project_key, req.task_id.as_deref()).await {
+            Ok(task) => task,
+            Err(e) => return Ok(sonar_error_response("Failed to fetch analysis status", &e)),
+        };
+
+        let submitted_in_time = |task: &CeTask| match (submitted_after, task.submitted_at.as_deref()) {
+            (None, _) => true,
+            (Some(after), Some(submitted_at)) => SonarQubeClient::parse_sonar_date(submitted_at).is_ok_and(|at| at >= after),
+            (Some(_), None) => false,
+        };
+        if let Some(task) = &task
+            && task.is_finished()
+            && submitted_in_time(task)
+        {
+            break task.clone();
+        }
+
+        let now = std::time::Instant::now();
+        if now >= deadline {
+            return Ok(HttpResponse::Accepted().json(serde_json::json!({
+                "message": "Analysis did not finish before the timeout",
+                "timed_out": true,
+                "project_key": project.project_key,
+                "status": task.as_ref().map(|t| t.status.as_str()).unwrap_or("NOT_FOUND"),
+                "task": task
+            })));
+        }
+        actix_web::rt::time::sleep(ANALYSIS_POLL_INTERVAL.min(deadline - now)).await;
+    };
+
+    let mut response_data = serde_json::json!({
+        "project_key": project.project_key,
+        "task_id": task.id,
+        "status": task.status,
+        "error_message": task.error_message,
+        "analysis_id": task.analysis_id,
+        "executed_at": task.executed_at,
+    });
+
+    if task.status == "SUCCESS"
+        && let Some(analysis_id) = &task.analysis_id
+    {
+        match sonar_client.get_analysis_quality_gate(analysis_id).await {
+            Ok(quality_gate) => {
+                response_data["quality_gate"] = serde_json::to_value(&quality_gate).unwrap_or(serde_json::Value::Null);
+            }
+            Err(e) => {
+                response_data["quality_gate_error"] = serde_json::json!({
+                    "error": format!("Failed to fetch quality gate: {}", e),
+                    "status": sonar_error_status(&e).as_u16()
+                });
+            }
+        }
+    }
+
+    Ok(HttpResponse::Ok().json(response_data))
+}
+
+pub async fn get_project_branches(
+    req: web::Json<ScanProjectRequest>,
+    project_service: web::Data<ProjectService>,
+) -> Result<HttpResponse> {
+    let project = match find_project(&project_service, &req.project_path).await {
+        Ok(project) => project,
+        Err(response) => return Ok(response),
+    };
+    let sonar_client = match sonar_client_for(&project_service, project.sonar_instance_id, "GLOBAL_ANALYSIS_TOKEN").await {
+        Ok(client) => client,
+        Err(response) => return Ok(response),
+    };
+
+    let branches = match sonar_client.list_branches(&project.project_key).await {
+        Ok(branches) => branches,
+        Err(e) => return Ok(sonar_error_response("Failed to list branches", &e)),
+    };
+    let pull_requests = match sonar_client.list_pull_requests(&project.project_key).await {
+        Ok(pull_requests) => pull_requests,
+        Err(e) => return Ok(sonar_error_response("Failed to list pull requests", &e)),
+    };
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "project_key": project.project_key,
+        "branches": branches,
+        "pull_requests": pull_requests
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn issue(key: &str, fields: serde_json::Value) -> Issue {
+        let mut issue = serde_json::json!({
+            "key": key,
+            "rule": "java:S1234",
+            "severity": "MAJOR",
+            "component": "demo:src/main/java/App.java",
+            "project": "demo",
+            "message": "m",
+            "status": "OPEN",
+            "creationDate": "2024-01-01T00:00:00+0000",
+            "updateDate": "2024-01-01T00:00:00+0000",
+            "tags": [],
+            "type": "CODE_SMELL",
+            "transitions": ["confirm", "wontfix"]
+        });
+        if let (Some(issue), Some(fields)) = (issue.as_object_mut(), fields.as_object()) {
+            issue.extend(fields.clone());
+        }
+        serde_json::from_value(issue).unwrap()
+    }
+
+    fn bulk_filter(filter: serde_json::Value) -> BulkIssueFilter {
+        serde_json::from_value(filter).unwrap()
+    }
+
+    fn bulk_change(change: serde_json::Value) -> BulkChange {
+        serde_json::from_value(change).unwrap()
+    }
+
+    fn filter_matches(filter: &BulkIssueFilter, issue: &Issue) -> bool {
+        let matcher = filter.file_matcher().unwrap();
+        filter.matches(issue, "demo", matcher.as_ref())
+    }
+
+    #[test]
+    fn bulk_filter_matches_severity_and_type_ignoring_case() {
+        let filter = bulk_filter(serde_json::json!({ "severities": ["minor", "Major"], "types": ["code_smell"] }));
+
+        assert!(filter_matches(&filter, &issue("A", serde_json::json!({}))));
+        assert!(!filter_matches(&filter, &issue("B", serde_json::json!({ "severity": "BLOCKER" }))));
+        assert!(!filter_matches(&filter, &issue("C", serde_json::json!({ "type": "BUG" }))));
+    }
+
+    #[test]
+    fn bulk_filter_matches_rule_and_author() {
+        let filter = bulk_filter(serde_json::json!({ "rules": ["java:S1234", "java:S100"], "author": "jdoe@example.com" }));
+
+        assert!(filter_matches(&filter, &issue("A", serde_json::json!({ "author": "jdoe@example.com" }))));
+        assert!(!filter_matches(&filter, &issue("B", serde_json::json!({ "author": "other@example.com" }))));
+        assert!(!filter_matches(&filter, &issue("C", serde_json::json!({ "author": null }))));
+        assert!(!filter_matches(&filter, &issue("D", serde_json::json!({ "rule": "java:S999", "author": "jdoe@example.com" }))));
+    }
+
+    #[test]
+    fn bulk_filter_matches_file_glob_inside_the_project() {
+        let filter = bulk_filter(serde_json::json!({ "file": "src/main/*/*.java" }));
+        let in_file = |component: &str| filter_matches(&filter, &issue("A", serde_json::json!({ "component": component })));
+
+        assert!(in_file("demo:src/main/java/App.java"));
+        // `*` does not cross directories
+        assert!(!in_file("demo:src/main/java/nested/App.java"));
+        assert!(!in_file("demo:src/test/java/AppTest.java"));
+        // Components of another project never match
+        assert!(!in_file("other:src/main/java/App.java"));
+
+        let recursive = bulk_filter(serde_json::json!({ "file": "src/**/*.java" }));
+        assert!(filter_matches(&recursive, &issue("B", serde_json::json!({ "component": "demo:src/main/java/nested/App.java" }))));
+    }
+
+    #[test]
+    fn empty_bulk_filter_matches_everything() {
+        assert!(filter_matches(&bulk_filter(serde_json::json!({})), &issue("A", serde_json::json!({}))));
+    }
+
+    #[test]
+    fn invalid_glob_is_rejected() {
+        assert!(bulk_filter(serde_json::json!({ "file": "src/[main" })).file_matcher().is_err());
+    }
+
+    #[test]
+    fn applied_changes_report_updated() {
+        let change = bulk_change(serde_json::json!({
+            "assign": "jdoe",
+            "transition": "confirm",
+            "add_tags": ["legacy"],
+            "set_severity": "MINOR",
+            "comment": "triaged"
+        }));
+        let before = issue("A", serde_json::json!({ "tags": ["todo"] }));
+        let after = issue("A", serde_json::json!({
+            "assignee": "jdoe",
+            "status": "CONFIRMED",
+            "tags": ["todo", "legacy"],
+            "severity": "MINOR",
+            "comments": [{ "key": "C1", "createdAt": "2024-01-02T00:00:00+0000" }]
+        }));
+
+        let result = verified_result(&before, Some(&after), &change);
+        assert_eq!(result.status, "updated");
+        assert!(result.problems.is_empty());
+    }
+
+    #[test]
+    fn ignored_changes_report_each_problem() {
+        let change = bulk_change(serde_json::json!({
+            "assign": "jdoe",
+            "transition": "reopen",
+            "remove_tags": ["todo"],
+            "set_type": "BUG",
+            "comment": "triaged"
+        }));
+        let before = issue("A", serde_json::json!({ "tags": ["todo"] }));
+
+        let result = verified_result(&before, Some(&before), &change);
+        assert_eq!(result.status, "not_applied");
+        assert_eq!(
+            result.problems,
+            [
+                "assignee is unset",
+                "transition reopen is not available in status OPEN",
+                "tag todo was not removed",
+                "type is CODE_SMELL",
+                "comment was not added",
+            ]
+        );
+
+        let available = bulk_change(serde_json::json!({ "transition": "confirm" }));
+        assert_eq!(verified_result(&before, Some(&before), &available).problems, ["transition confirm was not applied"]);
+    }
+
+    #[test]
+    fn missing_read_back_is_not_applied() {
+        let change = bulk_change(serde_json::json!({ "assign": "jdoe" }));
+        let result = verified_result(&issue("A", serde_json::json!({})), None, &change);
+
+        assert_eq!(result.status, "not_applied");
+        assert_eq!(result.problems, ["issue could not be read back"]);
+    }
+
+    #[test]
+    fn partial_failures_are_counted_per_issue() {
+        let change = bulk_change(serde_json::json!({ "set_severity": "MINOR" }));
+        let applied = issue("A", serde_json::json!({ "severity": "MINOR" }));
+        let error = SonarQubeError::Server {
+            status: reqwest::StatusCode::SERVICE_UNAVAILABLE,
+            message: "down".to_string(),
+        };
+
+        let mut results = vec![
+            verified_result(&issue("A", serde_json::json!({})), Some(&applied), &change),
+            verified_result(&issue("B", serde_json::json!({})), None, &change),
+        ];
+        results.extend(failed_results(vec!["C".to_string(), "D".to_string()], &error));
+
+        assert_eq!(count_results(&results, "updated"), 1);
+        assert_eq!(count_results(&results, "not_applied"), 1);
+        assert_eq!(count_results(&results, "failed"), 2);
+        assert_eq!(results[2].key, "C");
+        assert_eq!(results[3].problems, [error.to_string()]);
+    }
+
+    fn definition(fields: serde_json::Value) -> SettingDefinition {
+        let mut definition = serde_json::json!({ "key": "sonar.test" });
+        if let (Some(definition), Some(fields)) = (definition.as_object_mut(), fields.as_object()) {
+            definition.extend(fields.clone());
+        }
+        serde_json::from_value(definition).unwrap()
+    }
+
+    fn single(value: &str) -> SettingInput {
+        SettingInput::Single(value.to_string())
+    }
+
+    #[test]
+    fn setting_values_are_checked_against_their_type() {
+        let cases = [
+            (serde_json::json!({ "type": "BOOLEAN" }), "true", true),
+            (serde_json::json!({ "type": "BOOLEAN" }), "yes", false),
+            (serde_json::json!({ "type": "INTEGER" }), "-12", true),
+            (serde_json::json!({ "type": "INTEGER" }), "1.5", false),
+            (serde_json::json!({ "type": "LONG" }), "9000000000", true),
+            (serde_json::json!({ "type": "FLOAT" }), "0.75", true),
+            (serde_json::json!({ "type": "FLOAT" }), "high", false),
+            (serde_json::json!({ "type": "SINGLE_SELECT_LIST", "options": ["UTF-8", "ISO-8859-1"] }), "UTF-8", true),
+            (serde_json::json!({ "type": "SINGLE_SELECT_LIST", "options": ["UTF-8", "ISO-8859-1"] }), "ASCII", false),
+            (serde_json::json!({ "type": "PROPERTY_SET" }), "anything", false),
+            (serde_json::json!({ "type": "TEXT" }), "anything", true),
+            // A definition without a type is a string
+            (serde_json::json!({}), "anything", true),
+        ];
+
+        for (fields, value, valid) in cases {
+            let definition = definition(fields.clone());
+            assert_eq!(validate_setting("sonar.test", &single(value), Some(&definition)).is_ok(), valid, "{} {}", fields, value);
+        }
+    }
+
+    #[test]
+    fn setting_values_match_single_or_multiple() {
+        let multi = definition(serde_json::json!({ "multiValues": true }));
+        let single_value = definition(serde_json::json!({}));
+        let list = SettingInput::Multiple(vec!["**/generated/**".to_string(), "**/*.min.js".to_string()]);
+
+        assert!(validate_setting("sonar.exclusions", &list, Some(&multi)).is_ok());
+        assert_eq!(
+            validate_setting("sonar.exclusions", &single("**/generated/**"), Some(&multi)),
+            Err("sonar.exclusions: expects a list of values".to_string())
+        );
+        assert_eq!(
+            validate_setting("sonar.sourceEncoding", &list, Some(&single_value)),
+            Err("sonar.sourceEncoding: expects a single value".to_string())
+        );
+
+        let integers = definition(serde_json::json!({ "type": "INTEGER", "multiValues": true }));
+        let mixed = SettingInput::Multiple(vec!["1".to_string(), "two".to_string()]);
+        assert_eq!(
+            validate_setting("sonar.test", &mixed, Some(&integers)),
+            Err("sonar.test: 'two' is not a valid INTEGER value".to_string())
+        );
+    }
+
+    #[test]
+    fn unknown_settings_are_rejected() {
+        assert_eq!(validate_setting("sonar.nope", &single("x"), None), Err("sonar.nope: unknown setting".to_string()));
+    }
+
+    #[test]
+    fn new_code_period_previous_version_needs_nothing() {
+        assert!(validate_new_code_period("PREVIOUS_VERSION", None, None).is_ok());
+        assert!(validate_new_code_period("PREVIOUS_VERSION", Some("ignored"), Some("main")).is_ok());
+    }
+
+    #[test]
+    fn new_code_period_number_of_days_is_between_1_and_90() {
+        for days in ["1", "30", "90"] {
+            assert!(validate_new_code_period("NUMBER_OF_DAYS", Some(days), None).is_ok(), "{}", days);
+        }
+        for days in [None, Some("0"), Some("91"), Some("-5"), Some("thirty"), Some("1.5")] {
+            assert!(validate_new_code_period("NUMBER_OF_DAYS", days, None).is_err(), "{:?}", days);
+        }
+    }
+
+    #[test]
+    fn new_code_period_reference_branch_needs_a_branch_name() {
+        assert!(validate_new_code_period("REFERENCE_BRANCH", Some("main"), None).is_ok());
+        assert!(validate_new_code_period("REFERENCE_BRANCH", None, None).is_err());
+    }
+
+    #[test]
+    fn new_code_period_specific_analysis_needs_a_branch_and_analysis() {
+        assert!(validate_new_code_period("SPECIFIC_ANALYSIS", Some("AYanalysis"), Some("main")).is_ok());
+        assert!(validate_new_code_period("SPECIFIC_ANALYSIS", Some("AYanalysis"), None).is_err());
+        assert!(validate_new_code_period("SPECIFIC_ANALYSIS", None, Some("main")).is_err());
+    }
+
+    #[test]
+    fn unknown_new_code_period_type_is_rejected() {
+        assert_eq!(
+            validate_new_code_period("LAST_RELEASE", Some("1"), None),
+            Err("Unknown new code period type: LAST_RELEASE".to_string())
+        );
+        // Types are upper-cased by the handler before validation
+        assert!(validate_new_code_period("previous_version", None, None).is_err());
+    }
+
+    fn permission_change(change: serde_json::Value) -> PermissionChange {
+        serde_json::from_value(change).unwrap()
+    }
+
+    #[test]
+    fn permission_change_accepts_a_user_or_a_group() {
+        for permission in PROJECT_PERMISSIONS {
+            assert!(validate_permission_change(&permission_change(serde_json::json!({ "user": "jdoe", "permission": permission }))).is_ok());
+            assert!(validate_permission_change(&permission_change(serde_json::json!({ "group": "devs", "permission": permission }))).is_ok());
+        }
+    }
+
+    #[test]
+    fn permission_change_needs_exactly_one_of_user_or_group() {
+        let neither = permission_change(serde_json::json!({ "permission": "admin" }));
+        let both = permission_change(serde_json::json!({ "user": "jdoe", "group": "devs", "permission": "admin" }));
+
+        assert_eq!(validate_permission_change(&neither), Err("admin: set either user or group".to_string()));
+        assert_eq!(validate_permission_change(&both), Err("admin: set either user or group".to_string()));
+    }
+
+    #[test]
+    fn unknown_permissions_are_rejected() {
+        for permission in ["root", "ADMIN", "gateadmin", ""] {
+            let change = permission_change(serde_json::json!({ "user": "jdoe", "permission": permission }));
+            assert_eq!(validate_permission_change(&change), Err(format!("Unknown permission: {}", permission)));
+        }
     }
 }
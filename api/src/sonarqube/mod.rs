@@ -1,2 +1,5 @@
 pub mod client;
+pub mod error;
 pub mod handlers;
+pub mod snippets;
+pub mod webhooks;
@@ -0,0 +1,165 @@
+use crate::sonarqube::client::{AnalysisTarget, Issue, SonarQubeClient, TextRange};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+pub const DEFAULT_CONTEXT_LINES: i32 = 3;
+pub const MAX_CONTEXT_LINES: i32 = 50;
+// Files kept by the source cache, the oldest one is dropped past this
+const SOURCE_CACHE_CAPACITY: usize = 2000;
+
+// Instance URL, component, branch and pull request of a downloaded file
+type SourceKey = (String, String, Option<String>, Option<String>);
+// When the file was downloaded, and its lines
+type CachedSource = (Instant, Arc<Vec<String>>);
+
+// Source files downloaded for snippets, shared by all requests. A file is fetched again once
+// it is older than `max_age`, or as soon as a webhook reports a new analysis of its project.
+pub struct SourceCache {
+    max_age: Duration,
+    files: Mutex<HashMap<SourceKey, CachedSource>>,
+}
+
+impl SourceCache {
+    pub fn new(max_age: Duration) -> Self {
+        Self {
+            max_age,
+            files: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn get(&self, key: &SourceKey) -> Option<Arc<Vec<String>>> {
+        let files = self.files.lock().unwrap_or_else(|e| e.into_inner());
+        files
+            .get(key)
+            .filter(|(fetched_at, _)| fetched_at.elapsed() < self.max_age)
+            .map(|(_, lines)| lines.clone())
+    }
+
+    fn insert(&self, key: SourceKey, lines: Arc<Vec<String>>) {
+        let mut files = self.files.lock().unwrap_or_else(|e| e.into_inner());
+        files.retain(|_, (fetched_at, _)| fetched_at.elapsed() < self.max_age);
+        if files.len() >= SOURCE_CACHE_CAPACITY
+            && let Some(oldest) = files.iter().min_by_key(|(_, (fetched_at, _))| *fetched_at).map(|(key, _)| key.clone())
+        {
+            files.remove(&oldest);
+        }
+        files.insert(key, (Instant::now(), lines));
+    }
+
+    // Drops every file of the project, its components are keyed "<project key>:<path>"
+    pub fn invalidate_project(&self, project_key: &str) {
+        let prefix = format!("{}:", project_key);
+        let mut files = self.files.lock().unwrap_or_else(|e| e.into_inner());
+        files.retain(|(_, component, _, _), _| !component.starts_with(&prefix));
+    }
+}
+
+// Lines of code around an issue, with the issue's own lines flagged
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CodeSnippet {
+    pub component: String,
+    #[serde(rename = "startLine")]
+    pub start_line: i32,
+    #[serde(rename = "endLine")]
+    pub end_line: i32,
+    pub highlight: TextRange,
+    pub lines: Vec<SnippetLine>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnippetLine {
+    pub line: i32,
+    pub code: String,
+    pub highlighted: bool,
+}
+
+// Fills `snippet` on every issue that has a location, downloading each file only once
+pub async fn attach_snippets(
+    sonar_client: &SonarQubeClient,
+    source_cache: &SourceCache,
+    target: &AnalysisTarget,
+    issues: &mut [Issue],
+    context_lines: i32,
+) {
+    let mut sources: HashMap<String, Option<Arc<Vec<String>>>> = HashMap::new();
+
+    for issue in issues.iter_mut() {
+        if issue_range(issue).is_none() {
+            continue;
+        }
+
+        if !sources.contains_key(&issue.component) {
+            let key = (
+                sonar_client.base_url().to_string(),
+                issue.component.clone(),
+                target.branch.clone(),
+                target.pull_request.clone(),
+            );
+            let lines = match source_cache.get(&key) {
+                Some(lines) => Some(lines),
+                None => match sonar_client.get_source_raw(&issue.component, target).await {
+                    Ok(raw) => {
+                        let lines = Arc::new(raw.lines().map(str::to_string).collect::<Vec<_>>());
+                        source_cache.insert(key, lines.clone());
+                        Some(lines)
+                    }
+                    Err(e) => {
+                        warn!("Failed to fetch source of {}: {}", issue.component, e);
+                        None
+                    }
+                },
+            };
+            sources.insert(issue.component.clone(), lines);
+        }
+
+        if let Some(Some(lines)) = sources.get(&issue.component) {
+            let snippet = build_snippet(issue, lines, context_lines);
+            issue.snippet = snippet;
+        }
+    }
+}
+
+pub fn build_snippet(issue: &Issue, source_lines: &[String], context_lines: i32) -> Option<CodeSnippet> {
+    let highlight = issue_range(issue)?;
+    let line_count = source_lines.len() as i32;
+    if line_count == 0 || highlight.start_line > line_count {
+        return None;
+    }
+
+    let context_lines = context_lines.clamp(0, MAX_CONTEXT_LINES);
+    let start_line = (highlight.start_line - context_lines).max(1);
+    let end_line = (highlight.end_line + context_lines).min(line_count);
+
+    let lines = (start_line..=end_line)
+        .map(|line| SnippetLine {
+            line,
+            code: source_lines[(line - 1) as usize].clone(),
+            highlighted: line >= highlight.start_line && line <= highlight.end_line,
+        })
+        .collect();
+
+    Some(CodeSnippet {
+        component: issue.component.clone(),
+        start_line,
+        end_line,
+        highlight,
+        lines,
+    })
+}
+
+// Issue location, falling back to its single line when SonarQube sent no text range
+fn issue_range(issue: &Issue) -> Option<TextRange> {
+    match (&issue.text_range, issue.line) {
+        (Some(range), _) => Some(range.clone()),
+        (None, Some(line)) => Some(TextRange {
+            start_line: line,
+            end_line: line,
+            start_offset: None,
+            end_offset: None,
+        }),
+        (None, None) => None,
+    }
+}
@@ -0,0 +1,106 @@
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+pub const SIGNATURE_HEADER: &str = "X-Sonar-Webhook-HMAC-SHA256";
+// Name of the webhook registered on each project we create
+pub const WEBHOOK_NAME: &str = "SonarCute";
+
+// Body SonarQube posts once the background task of an analysis has finished
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookPayload {
+    #[serde(rename = "serverUrl")]
+    pub server_url: Option<String>,
+    #[serde(rename = "taskId")]
+    pub task_id: Option<String>,
+    pub status: Option<String>,
+    #[serde(rename = "analysedAt")]
+    pub analysed_at: Option<String>,
+    // Not sent by every SonarQube version, the task knows it otherwise
+    #[serde(rename = "analysisId")]
+    pub analysis_id: Option<String>,
+    pub revision: Option<String>,
+    pub project: WebhookProject,
+    pub branch: Option<WebhookBranch>,
+    #[serde(rename = "qualityGate")]
+    pub quality_gate: Option<WebhookQualityGate>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookProject {
+    pub key: String,
+    pub name: Option<String>,
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookBranch {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub branch_type: Option<String>,
+    #[serde(rename = "isMain")]
+    pub is_main: Option<bool>,
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookQualityGate {
+    pub name: Option<String>,
+    pub status: Option<String>,
+    #[serde(default)]
+    pub conditions: Vec<serde_json::Value>,
+}
+
+// SonarQube signs the raw body with HMAC-SHA256 and sends the hex digest
+pub fn verify_signature(secret: &str, body: &[u8], signature: &str) -> bool {
+    let Ok(expected) = hex::decode(signature.trim()) else {
+        return false;
+    };
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret.as_bytes()) else {
+        return false;
+    };
+    mac.update(body);
+    // Constant time comparison
+    mac.verify_slice(&expected).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "webhook-secret";
+    const BODY: &[u8] = br#"{"taskId":"AX1","status":"SUCCESS","project":{"key":"demo"}}"#;
+
+    fn sign(secret: &str, body: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(body);
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    #[test]
+    fn accepts_a_valid_signature() {
+        let signature = sign(SECRET, BODY);
+
+        assert!(verify_signature(SECRET, BODY, &signature));
+        assert!(verify_signature(SECRET, BODY, &format!(" {}\n", signature.to_uppercase())));
+    }
+
+    #[test]
+    fn rejects_a_tampered_body_or_other_secret() {
+        let signature = sign(SECRET, BODY);
+        let tampered = br#"{"taskId":"AX1","status":"FAILED","project":{"key":"demo"}}"#;
+
+        assert!(!verify_signature(SECRET, tampered, &signature));
+        assert!(!verify_signature("other-secret", BODY, &signature));
+    }
+
+    #[test]
+    fn rejects_a_malformed_header() {
+        let signature = sign(SECRET, BODY);
+
+        assert!(!verify_signature(SECRET, BODY, "not-hex"));
+        assert!(!verify_signature(SECRET, BODY, &signature[1..]));
+        assert!(!verify_signature(SECRET, BODY, &signature[..32]));
+        assert!(!verify_signature(SECRET, BODY, ""));
+    }
+}
@@ -5,11 +5,34 @@ use actix_web::App;
 use actix_web::HttpServer;
 use listenfd::ListenFd;
 use std::env;
-use tracing::info;
+use tracing::{info, warn};
 use crate::config::logger;
-use crate::database::{connect, service::ProjectService};
+use crate::config::sonar::{sonar_host_url, source_cache_max_age};
+use crate::database::{connect, encryption, encryption::TokenCipher, migrations, service::ProjectService};
 use crate::sonarqube::handlers;
+use crate::sonarqube::snippets::SourceCache;
 
+// Entry point of `sonar-cute-api migrate <up|down|status>`
+pub async fn migrate(args: &[String]) -> std::io::Result<()> {
+    dotenvy::dotenv().ok();
+
+    logger::init();
+
+    let db = connect().await.expect("Failed to connect to database");
+    migrations::run_command(&db, args).await.map_err(std::io::Error::other)
+}
+
+// Entry point of `sonar-cute-api tokens <status|encrypt|reencrypt>`
+pub async fn tokens(args: &[String]) -> std::io::Result<()> {
+    dotenvy::dotenv().ok();
+
+    logger::init();
+
+    let db = connect().await.expect("Failed to connect to database");
+    let cipher = TokenCipher::from_env().map_err(std::io::Error::other)?;
+    let project_service = ProjectService::new(db, cipher);
+    encryption::run_command(&project_service, args).await.map_err(std::io::Error::other)
+}
 
 pub async fn start() -> std::io::Result<()> {
     dotenvy::dotenv().ok();
@@ -21,7 +44,39 @@ pub async fn start() -> std::io::Result<()> {
     let server_url = format!("{server_host}:{server_port}");
 
     let db = connect().await.expect("Failed to connect to database");
-    let project_service = ProjectService::new(db);
+    // Set DATABASE_AUTO_MIGRATE=false to manage the schema with the migrate subcommand only
+    if env::var("DATABASE_AUTO_MIGRATE").map(|value| value != "false").unwrap_or(true) {
+        migrations::up(&db, None).await.expect("Failed to run database migrations");
+    }
+    let missing_columns = migrations::check_entities(&db).await.expect("Failed to read database schema");
+    if !missing_columns.is_empty() {
+        panic!("Database schema is behind the entities, missing columns: {}", missing_columns.join(", "));
+    }
+
+    let cipher = TokenCipher::from_env().expect("Failed to load token encryption keys");
+    let project_service = ProjectService::new(db, cipher.clone());
+    if cipher.is_enabled() {
+        // Rows written before encryption was configured are encrypted once
+        let encrypted = project_service.encrypt_plaintext_tokens().await.expect("Failed to encrypt stored tokens");
+        if encrypted > 0 {
+            info!("Encrypted {encrypted} token(s) stored in plaintext");
+        }
+    } else {
+        warn!("TOKEN_ENCRYPTION_KEYS is not set, SonarQube tokens are stored in plaintext");
+    }
+
+    // SONAR_HOST_URL only seeds the default instance, clients are built from the instance registry
+    let (default_instance, linked) = project_service
+        .ensure_default_sonar_instance(&sonar_host_url())
+        .await
+        .expect("Failed to set up the default SonarQube instance");
+    info!("Default SonarQube instance: {} ({})", default_instance.name, default_instance.url);
+    if linked > 0 {
+        info!("Linked {linked} project(s) to the default SonarQube instance");
+    }
+
+    // Created once, so every worker shares the same files
+    let source_cache = web::Data::new(SourceCache::new(source_cache_max_age()));
 
     let mut server = HttpServer::new(move || {
         let cors = Cors::default()
@@ -31,16 +86,59 @@ pub async fn start() -> std::io::Result<()> {
 
         App::new()
             .app_data(web::Data::new(project_service.clone()))
+            .app_data(source_cache.clone())
             .wrap(middleware::Logger::default())
             .wrap(cors)
             .service(
                 web::scope("/api")
                     .route("/admin-token", web::post().to(handlers::create_admin_token))
+                    .route("/admin-token/rotate", web::post().to(handlers::rotate_admin_token))
+                    .route("/user-tokens", web::get().to(handlers::get_user_tokens))
+                    .route("/user-tokens/revoke", web::post().to(handlers::revoke_user_token))
+                    .route("/tokens/expiring", web::get().to(handlers::get_expiring_tokens))
+                    .route("/sonar-instances", web::get().to(handlers::get_sonar_instances))
+                    .route("/sonar-instances", web::post().to(handlers::create_sonar_instance))
+                    .route("/sonar-instances", web::put().to(handlers::update_sonar_instance))
+                    .route("/sonar-instances", web::delete().to(handlers::delete_sonar_instance))
                     .route("/projects", web::get().to(handlers::get_all_projects))
                     .route("/projects", web::post().to(handlers::create_project))
+                    .route("/projects", web::put().to(handlers::update_project))
                     .route("/projects", web::delete().to(handlers::delete_project))
+                    .route("/projects/import", web::get().to(handlers::get_importable_projects))
+                    .route("/projects/import", web::post().to(handlers::import_projects))
+                    .route("/projects/token/rotate", web::post().to(handlers::rotate_project_token))
+                    .route("/projects/measures", web::post().to(handlers::get_component_measures))
+                    .route("/projects/history", web::post().to(handlers::get_project_history))
+                    .route("/projects/branches", web::post().to(handlers::get_project_branches))
+                    .route("/projects/details", web::post().to(handlers::get_project_details))
+                    .route("/projects/settings", web::get().to(handlers::get_project_settings))
+                    .route("/projects/settings", web::put().to(handlers::update_project_settings))
+                    .route("/projects/settings", web::delete().to(handlers::reset_project_settings))
+                    .route("/projects/new-code-period", web::get().to(handlers::get_new_code_period))
+                    .route("/projects/new-code-period", web::put().to(handlers::set_new_code_period))
+                    .route("/projects/new-code-period", web::delete().to(handlers::unset_new_code_period))
+                    .route("/projects/permissions", web::get().to(handlers::get_project_permissions))
+                    .route("/projects/permissions", web::post().to(handlers::update_project_permissions))
                     .route("/results", web::post().to(handlers::get_project_results))
+                    .route("/snapshots", web::get().to(handlers::get_snapshots))
+                    .route("/snapshots/details", web::get().to(handlers::get_snapshot))
+                    .route("/snapshots/issues", web::get().to(handlers::get_snapshot_issues))
+                    .route("/snippets", web::post().to(handlers::get_issue_snippets))
+                    .route("/issues/transition", web::post().to(handlers::transition_issue))
+                    .route("/issues/assign", web::post().to(handlers::assign_issue))
+                    .route("/issues/bulk", web::post().to(handlers::bulk_change_issues))
+                    .route("/issues/comments", web::post().to(handlers::add_issue_comment))
+                    .route("/issues/comments", web::put().to(handlers::edit_issue_comment))
+                    .route("/issues/comments", web::delete().to(handlers::delete_issue_comment))
+                    .route("/analysis/wait", web::post().to(handlers::wait_for_analysis))
+                    .route("/webhooks", web::get().to(handlers::get_webhooks))
+                    .route("/webhooks", web::post().to(handlers::create_webhook))
+                    .route("/webhooks", web::delete().to(handlers::delete_webhook))
+                    .route("/webhooks/sonarqube", web::post().to(handlers::receive_sonar_webhook))
+                    .route("/webhooks/events", web::get().to(handlers::get_webhook_events))
                     .route("/generate-command", web::post().to(handlers::generate_sonar_command))
+                    .route("/hotspots", web::post().to(handlers::get_project_hotspots))
+                    .route("/hotspots/details", web::get().to(handlers::get_hotspot_details))
                     .route("/quality-gates", web::get().to(handlers::get_quality_gates))
                     .route("/quality-gates/details", web::get().to(handlers::get_quality_gate_details))
                     // Quality Gate management
@@ -48,6 +146,16 @@ pub async fn start() -> std::io::Result<()> {
                     .route("/quality-gates", web::put().to(handlers::update_quality_gate))
                     .route("/quality-gates", web::delete().to(handlers::delete_quality_gate))
                     .route("/quality-gates/set-default", web::post().to(handlers::set_default_quality_gate))
+                    .route("/quality-gates/project", web::get().to(handlers::get_project_quality_gate))
+                    .route("/quality-gates/select", web::post().to(handlers::select_project_quality_gate))
+                    .route("/quality-gates/deselect", web::post().to(handlers::deselect_project_quality_gate))
+                    // Quality Profile management
+                    .route("/quality-profiles", web::get().to(handlers::get_quality_profiles))
+                    .route("/quality-profiles", web::post().to(handlers::create_quality_profile))
+                    .route("/quality-profiles/parent", web::post().to(handlers::set_quality_profile_parent))
+                    .route("/quality-profiles/rules", web::post().to(handlers::activate_quality_profile_rule))
+                    .route("/quality-profiles/rules", web::delete().to(handlers::deactivate_quality_profile_rule))
+                    .route("/quality-profiles/projects", web::post().to(handlers::set_project_quality_profile))
             )
     });
 